@@ -0,0 +1,597 @@
+use crate::error::CorrodeError;
+use crate::expr::{Depth, Expr, ExprVisitor, LiteralValue, Pattern};
+use crate::interner::Symbol;
+use crate::stmt::{Binding, Stmt, StmtVisitor};
+use crate::tokenizer::{Token, TokenType};
+use std::collections::HashMap;
+
+// Static types, named like LiteralValue::to_type. Anything the checker can not tell is Any
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Type {
+    Int,
+    Float,
+    String,
+    Boolean,
+    Null,
+    Array,
+    Tuple,
+    Map,
+    Any,
+}
+
+impl Type {
+    fn from_annotation(annotation: &Token) -> Option<Self> {
+        match annotation.lexeme.to_lowercase().as_str() {
+            "int" => Some(Type::Int),
+            "float" => Some(Type::Float),
+            "str" | "string" => Some(Type::String),
+            "bool" | "boolean" => Some(Type::Boolean),
+            "null" => Some(Type::Null),
+            "array" => Some(Type::Array),
+            "tuple" => Some(Type::Tuple),
+            "map" => Some(Type::Map),
+            "any" => Some(Type::Any),
+            _ => None,
+        }
+    }
+
+    fn of_value(value: &LiteralValue) -> Self {
+        match value {
+            LiteralValue::IntValue(_) => Type::Int,
+            LiteralValue::FloatValue(_) => Type::Float,
+            LiteralValue::StringValue(_) => Type::String,
+            LiteralValue::True | LiteralValue::False => Type::Boolean,
+            LiteralValue::Null => Type::Null,
+            _ => Type::Any,
+        }
+    }
+
+    fn is_number(&self) -> bool {
+        matches!(self, Type::Int | Type::Float)
+    }
+
+    // Null is accepted everywhere so `let x: int;` stays valid, and ints widen to floats
+    fn accepts(&self, other: Type) -> bool {
+        *self == other
+            || *self == Type::Any
+            || other == Type::Any
+            || other == Type::Null
+            || (*self == Type::Float && other == Type::Int)
+    }
+}
+
+impl std::fmt::Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+// Walks the program before it runs and reports type mismatches between annotated variables and
+// the values given to them, and operators applied to types they can never work on
+pub struct TypeChecker {
+    scopes: Vec<HashMap<Symbol, Type>>,
+    errors: Vec<CorrodeError>,
+}
+
+impl Default for TypeChecker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TypeChecker {
+    pub fn new() -> Self {
+        Self {
+            scopes: vec![HashMap::new()],
+            errors: Vec::new(),
+        }
+    }
+
+    pub fn check(&mut self, stmts: &[Stmt]) -> Result<(), Vec<CorrodeError>> {
+        for stmt in stmts {
+            stmt.accept(self);
+        }
+
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(std::mem::take(&mut self.errors))
+        }
+    }
+
+    // `token` is where the error points, the name or operator it is about
+    fn error(&mut self, token: &Token, message: String) {
+        self.errors.push(CorrodeError::type_error(token.line_number, message).with_column(token.column));
+    }
+
+    fn declare(&mut self, name: &Token, declared: Type) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.symbol(), declared);
+        }
+    }
+
+    // Names that were never declared, like natives, are Any
+    fn lookup(&self, name: &Token) -> Type {
+        let symbol = name.symbol();
+        self.scopes.iter().rev()
+            .find_map(|scope| scope.get(&symbol).copied())
+            .unwrap_or(Type::Any)
+    }
+
+    // Destructured names are not checked against their annotations, the parts of the value are not known
+    fn declare_pattern(&mut self, pattern: &Pattern) {
+        match pattern {
+            Pattern::Name(name, _) => self.declare(name, Type::Any),
+            Pattern::Tuple(elements) => {
+                for element in elements {
+                    self.declare_pattern(element);
+                }
+            },
+        }
+    }
+
+    fn scoped(&mut self, check: impl FnOnce(&mut Self)) {
+        self.scopes.push(HashMap::new());
+        check(self);
+        self.scopes.pop();
+    }
+
+    fn check_assignable(&mut self, name: &Token, expected: Type, actual: Type) {
+        if !expected.accepts(actual) {
+            self.error(name, format!("Can not assign {} to '{}' of type {}", actual, name.lexeme, expected));
+        }
+    }
+
+    // Elements and fields are not typed, so a compound assignment to one is checked against Any
+    fn assigned_type(&mut self, operator: Option<&Token>, value: &Expr) -> Type {
+        let value = value.accept(self);
+        match operator {
+            Some(operator) => self.binary_type(operator, Type::Any, value),
+            None => value,
+        }
+    }
+
+    fn binary_type(&mut self, operator: &Token, left: Type, right: Type) -> Type {
+        let mismatch = |checker: &mut Self| {
+            checker.error(
+                operator,
+                format!("Binary operator {} can not be applied to {} and {}", operator.lexeme, left, right)
+            );
+            Type::Any
+        };
+
+        match operator.token_type {
+            TokenType::EqualEqual | TokenType::BangEqual => Type::Boolean,
+            _ if left == Type::Any || right == Type::Any => match operator.token_type {
+                TokenType::Greater | TokenType::GreaterEqual | TokenType::Less | TokenType::LessEqual => Type::Boolean,
+                _ => Type::Any,
+            },
+            TokenType::Plus if left == Type::String && right == Type::String => Type::String,
+            TokenType::Plus | TokenType::Minus | TokenType::Star | TokenType::Percent | TokenType::TildeSlash if left.is_number() && right.is_number() => {
+                if left == Type::Int && right == Type::Int { Type::Int } else { Type::Float }
+            },
+            TokenType::Slash if left.is_number() && right.is_number() => Type::Float,
+            // A negative Int exponent gives a Float
+            TokenType::StarStar if left.is_number() && right.is_number() => {
+                if left == Type::Int && right == Type::Int { Type::Any } else { Type::Float }
+            },
+            TokenType::Greater | TokenType::GreaterEqual | TokenType::Less | TokenType::LessEqual
+                if (left.is_number() && right.is_number()) || (left == Type::String && right == Type::String) => Type::Boolean,
+            TokenType::Ampersand | TokenType::Pipe | TokenType::Caret | TokenType::LessLess | TokenType::GreaterGreater
+                if left == Type::Int && right == Type::Int => Type::Int,
+            _ => mismatch(self),
+        }
+    }
+}
+
+impl ExprVisitor<Type> for TypeChecker {
+    fn visit_binary(&mut self, left: &Expr, operator: &Token, right: &Expr) -> Type {
+        let left = left.accept(self);
+        let right = right.accept(self);
+
+        self.binary_type(operator, left, right)
+    }
+
+    fn visit_chain(&mut self, operands: &[Expr], operators: &[Token]) -> Type {
+        let types = operands.iter().map(|operand| operand.accept(self)).collect::<Vec<Type>>();
+        for (operator, pair) in operators.iter().zip(types.windows(2)) {
+            self.binary_type(operator, pair[0], pair[1]);
+        }
+
+        Type::Boolean
+    }
+
+    fn visit_grouping(&mut self, expression: &Expr) -> Type {
+        expression.accept(self)
+    }
+
+    fn visit_call(&mut self, callee: &Expr, _paren: &Token, arguments: &[Expr]) -> Type {
+        callee.accept(self);
+        for argument in arguments {
+            argument.accept(self);
+        }
+
+        Type::Any
+    }
+
+    fn visit_literal(&mut self, value: &LiteralValue) -> Type {
+        Type::of_value(value)
+    }
+
+    // `and` and `or` return one of their operands
+    fn visit_logical(&mut self, left: &Expr, operator: &Token, right: &Expr) -> Type {
+        let left = left.accept(self);
+        let right = right.accept(self);
+
+        match (operator.token_type, left) {
+            (TokenType::QuestionQuestion, Type::Null) => right,
+            (_, left) if left == right => left,
+            _ => Type::Any,
+        }
+    }
+
+    fn visit_unary(&mut self, operator: &Token, right: &Expr) -> Type {
+        let right = right.accept(self);
+
+        match (operator.token_type, right) {
+            (TokenType::Bang, _) => Type::Boolean,
+            (_, Type::Any) => Type::Any,
+            (TokenType::Minus, right) if right.is_number() => right,
+            (TokenType::Tilde, Type::Int) => Type::Int,
+            (_, right) => {
+                self.error(operator, format!("Unary operator {} can not be applied to {}", operator.lexeme, right));
+                Type::Any
+            }
+        }
+    }
+
+    fn visit_variable(&mut self, name: &Token, _depth: &Depth) -> Type {
+        self.lookup(name)
+    }
+
+    fn visit_assign(&mut self, name: &Token, _depth: &Depth, value: &Expr) -> Type {
+        let actual = value.accept(self);
+        let expected = self.lookup(name);
+        self.check_assignable(name, expected, actual);
+
+        actual
+    }
+
+    fn visit_interpolation(&mut self, parts: &[Expr]) -> Type {
+        for part in parts {
+            part.accept(self);
+        }
+
+        Type::String
+    }
+
+    fn visit_array(&mut self, elements: &[Expr]) -> Type {
+        for element in elements {
+            element.accept(self);
+        }
+
+        Type::Array
+    }
+
+    // Stands for any number of values, so it has no type of its own
+    fn visit_spread(&mut self, _ellipsis: &Token, inner: &Expr) -> Type {
+        inner.accept(self);
+
+        Type::Any
+    }
+
+    fn visit_tuple(&mut self, elements: &[Expr]) -> Type {
+        for element in elements {
+            element.accept(self);
+        }
+
+        Type::Tuple
+    }
+
+    fn visit_destructure(&mut self, _pattern: &Pattern, _equals: &Token, value: &Expr) -> Type {
+        value.accept(self)
+    }
+
+    fn visit_map(&mut self, _brace: &Token, entries: &[(Expr, Expr)]) -> Type {
+        for (key, value) in entries {
+            key.accept(self);
+            value.accept(self);
+        }
+
+        Type::Map
+    }
+
+    fn visit_index(&mut self, object: &Expr, _bracket: &Token, index: &Expr) -> Type {
+        let object = object.accept(self);
+        index.accept(self);
+
+        // Indexing a string gives a one character string
+        if object == Type::String { Type::String } else { Type::Any }
+    }
+
+    // A slice is a copy of the same kind of object
+    fn visit_slice(&mut self, object: &Expr, _bracket: &Token, start: Option<&Expr>, end: Option<&Expr>) -> Type {
+        let object = object.accept(self);
+        for bound in start.into_iter().chain(end) {
+            bound.accept(self);
+        }
+
+        if object == Type::String || object == Type::Array { object } else { Type::Any }
+    }
+
+    fn visit_index_set(&mut self, object: &Expr, _bracket: &Token, index: &Expr, operator: Option<&Token>, value: &Expr) -> Type {
+        object.accept(self);
+        index.accept(self);
+
+        self.assigned_type(operator, value)
+    }
+
+    fn visit_ternary(&mut self, condition: &Expr, then_expr: &Expr, else_expr: &Expr) -> Type {
+        condition.accept(self);
+        let then_type = then_expr.accept(self);
+        let else_type = else_expr.accept(self);
+
+        if then_type == else_type { then_type } else { Type::Any }
+    }
+
+    fn visit_get(&mut self, object: &Expr, _name: &Token) -> Type {
+        object.accept(self);
+
+        Type::Any
+    }
+
+    fn visit_set(&mut self, object: &Expr, _name: &Token, operator: Option<&Token>, value: &Expr) -> Type {
+        object.accept(self);
+
+        self.assigned_type(operator, value)
+    }
+
+    fn visit_self(&mut self, _keyword: &Token) -> Type {
+        Type::Any
+    }
+
+    fn visit_super(&mut self, _keyword: &Token, _method: &Token) -> Type {
+        Type::Any
+    }
+
+    fn visit_lambda(&mut self, _keyword: &Token, params: &[Token], rest: Option<&Token>, body: &[Stmt]) -> Type {
+        self.scoped(|checker| {
+            for param in params {
+                checker.declare(param, Type::Any);
+            }
+            if let Some(rest) = rest {
+                checker.declare(rest, Type::Array);
+            }
+            for stmt in body {
+                stmt.accept(checker);
+            }
+        });
+
+        Type::Any
+    }
+}
+
+impl StmtVisitor<()> for TypeChecker {
+    fn visit_expression(&mut self, expression: &Expr) {
+        expression.accept(self);
+    }
+
+    fn visit_echo(&mut self, expressions: &[Expr]) {
+        for expression in expressions {
+            expression.accept(self);
+        }
+    }
+
+    fn visit_print(&mut self, expression: &Expr, _newline: bool) {
+        expression.accept(self);
+    }
+
+    fn visit_let(&mut self, bindings: &[Binding]) {
+        for binding in bindings {
+            let actual = match &binding.initializer {
+                Some(initializer) => initializer.accept(self),
+                None => Type::Null,
+            };
+
+            let declared = match &binding.annotation {
+                Some(annotation) => match Type::from_annotation(annotation) {
+                    Some(declared) => declared,
+                    None => {
+                        self.error(annotation, format!("Unknown type '{}'", annotation.lexeme));
+                        Type::Any
+                    }
+                },
+                None => Type::Any,
+            };
+
+            self.check_assignable(&binding.name, declared, actual);
+            self.declare(&binding.name, declared);
+        }
+    }
+
+    fn visit_let_destructure(&mut self, pattern: &Pattern, _equals: &Token, initializer: &Expr) {
+        initializer.accept(self);
+        self.declare_pattern(pattern);
+    }
+
+    fn visit_const(&mut self, name: &Token, initializer: &Expr) {
+        initializer.accept(self);
+        self.declare(name, Type::Any);
+    }
+
+    fn visit_block(&mut self, statements: &[Stmt]) {
+        self.scoped(|checker| {
+            for stmt in statements {
+                stmt.accept(checker);
+            }
+        });
+    }
+
+    fn visit_if(&mut self, predicate: &Expr, then: &Stmt, otherwise: Option<&Stmt>) {
+        predicate.accept(self);
+        then.accept(self);
+        if let Some(otherwise) = otherwise {
+            otherwise.accept(self);
+        }
+    }
+
+    fn visit_match(&mut self, subject: &Expr, arms: &[(Vec<Expr>, Stmt)], default: Option<&Stmt>) {
+        subject.accept(self);
+        for (patterns, body) in arms {
+            for pattern in patterns {
+                pattern.accept(self);
+            }
+            body.accept(self);
+        }
+        if let Some(default) = default {
+            default.accept(self);
+        }
+    }
+
+    fn visit_while(&mut self, condition: &Expr, body: &Stmt, increment: Option<&Expr>) {
+        condition.accept(self);
+        body.accept(self);
+        if let Some(increment) = increment {
+            increment.accept(self);
+        }
+    }
+
+    fn visit_do_while(&mut self, body: &Stmt, condition: &Expr) {
+        body.accept(self);
+        condition.accept(self);
+    }
+
+    fn visit_for_in(&mut self, key: Option<&Token>, item: &Token, iterable: &Expr, body: &Stmt) {
+        iterable.accept(self);
+
+        self.scoped(|checker| {
+            if let Some(key) = key {
+                checker.declare(key, Type::Any);
+            }
+            checker.declare(item, Type::Any);
+            body.accept(checker);
+        });
+    }
+
+    fn visit_function(&mut self, name: &Token, params: &[Token], rest: Option<&Token>, body: &[Stmt]) {
+        self.declare(name, Type::Any);
+
+        self.scoped(|checker| {
+            for param in params {
+                checker.declare(param, Type::Any);
+            }
+            if let Some(rest) = rest {
+                checker.declare(rest, Type::Array);
+            }
+            for stmt in body {
+                stmt.accept(checker);
+            }
+        });
+    }
+
+    fn visit_class(&mut self, name: &Token, superclass: Option<&Expr>, methods: &[Stmt]) {
+        if let Some(superclass) = superclass {
+            superclass.accept(self);
+        }
+        self.declare(name, Type::Any);
+
+        self.scoped(|checker| {
+            for method in methods {
+                method.accept(checker);
+            }
+        });
+    }
+
+    fn visit_return(&mut self, _keyword: &Token, value: Option<&Expr>) {
+        if let Some(value) = value {
+            value.accept(self);
+        }
+    }
+
+    fn visit_break(&mut self) {}
+
+    fn visit_continue(&mut self) {}
+
+    fn visit_assert(&mut self, _keyword: &Token, condition: &Expr, message: Option<&Expr>) {
+        condition.accept(self);
+        if let Some(message) = message {
+            message.accept(self);
+        }
+    }
+
+    fn visit_throw(&mut self, _keyword: &Token, value: &Expr) {
+        value.accept(self);
+    }
+
+    fn visit_try(&mut self, body: &Stmt, name: &Token, handler: &Stmt) {
+        body.accept(self);
+
+        self.scoped(|checker| {
+            checker.declare(name, Type::Any);
+            handler.accept(checker);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tokenizer::Tokenizer;
+    use crate::parser::Parser;
+
+    fn check(source: &str) -> Result<(), Vec<CorrodeError>> {
+        let tokens = Tokenizer::new(source).tokenize().unwrap();
+        let stmts = Parser::new(tokens).parse().unwrap();
+
+        TypeChecker::new().check(&stmts)
+    }
+
+    #[test]
+    fn clean_program() {
+        let source = "
+            let x: int = 3, y = \"any\", z: float = x;
+            let s: string = \"a\" + \"b\";
+            x = x * 2 + 1;
+            fn f(a) { let n: int = a; return n; }
+            y = 1;
+            let nothing: map;
+            let fallback: int = null ?? 5;
+            let half: int = 7 ~/ 2;
+        ";
+
+        assert_eq!(check(source), Ok(()));
+    }
+
+    #[test]
+    fn assign_mismatch() {
+        let source = "let x: int = 3;\nx = \"three\";";
+
+        assert_eq!(check(source), Err(vec![CorrodeError::type_error(2, String::from("Can not assign String to 'x' of type Int")).with_column(1)]));
+    }
+
+    #[test]
+    fn initializer_and_operand_mismatches() {
+        let source = "let b: bool = 1;\nlet s = \"a\" - 2;\nlet t: wat = 0;\nlet h = \"a\" ~/ 2;";
+
+        assert_eq!(check(source), Err(vec![
+            CorrodeError::type_error(1, String::from("Can not assign Int to 'b' of type Boolean")).with_column(5),
+            CorrodeError::type_error(2, String::from("Binary operator - can not be applied to String and Int")).with_column(13),
+            CorrodeError::type_error(3, String::from("Unknown type 'wat'")).with_column(8),
+            CorrodeError::type_error(4, String::from("Binary operator ~/ can not be applied to String and Int")).with_column(13),
+        ]));
+    }
+
+    #[test]
+    fn chain_operand_mismatch() {
+        let source = "echo 0 < 1 < \"a\";";
+
+        assert_eq!(check(source), Err(vec![CorrodeError::type_error(1, String::from("Binary operator < can not be applied to Int and String")).with_column(12)]));
+    }
+
+    #[test]
+    fn shadowing_resets_type() {
+        let source = "let x: int = 1; { let x = \"a\"; x = \"b\"; } fn f(x) { x = \"c\"; }";
+
+        assert_eq!(check(source), Ok(()));
+    }
+}
@@ -1,57 +1,206 @@
-use std::collections::HashMap;
-use std::cell::RefCell;
-use crate::expr::LiteralValue;
-use std::rc::Rc;
-
-pub struct Environment {
-    values: HashMap<String, LiteralValue>,
-    pub enclosing: Option<Rc<RefCell<Environment>>>,
-}
-
-impl Environment {
-    pub fn new() -> Self {
-        Self {
-            values: HashMap::new(),
-            enclosing: None,
-        }
-    }
-
-    pub fn define(&mut self, name: String, value: LiteralValue) {
-        self.values.insert(name, value);
-    }
-
-    pub fn get(&self, name: &str) -> Option<LiteralValue> {
-        let value = self.values.get(name);
-
-        match (value, &self.enclosing) {
-            (Some(val), _) => Some(val.clone()),
-            (None, Some(env)) => env.borrow().get(name),
-            (None, None) => None
-        }
-    }
-
-    pub fn assign(&mut self, name: &str, value: LiteralValue) -> bool {
-        let old_value = self.values.get(name);
-
-        match  (old_value, &self.enclosing) {
-            (Some(_), _) => {
-                self.values.insert(name.to_string(), value);
-                true
-            },
-            (None, Some(env)) => {
-                (env.borrow_mut()).assign(name, value)
-            },
-            (None, None) => false
-        }
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn try_unit() {
-        let _environment = Environment::new();
-    }
-}
\ No newline at end of file
+use std::collections::{HashMap, HashSet};
+use std::cell::RefCell;
+use crate::expr::LiteralValue;
+use crate::interner::Symbol;
+use std::rc::Rc;
+
+pub struct Environment {
+    values: HashMap<Symbol, LiteralValue>,
+    // Names in `values` that were declared with const
+    constants: HashSet<Symbol>,
+    // Names in `values` declared without an initializer and not assigned yet
+    uninitialized: HashSet<Symbol>,
+    pub enclosing: Option<Rc<RefCell<Environment>>>,
+}
+
+impl Default for Environment {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Environment {
+    pub fn new() -> Self {
+        Self {
+            values: HashMap::new(),
+            constants: HashSet::new(),
+            uninitialized: HashSet::new(),
+            enclosing: None,
+        }
+    }
+
+    pub fn define(&mut self, name: Symbol, value: LiteralValue) {
+        self.constants.remove(&name);
+        self.uninitialized.remove(&name);
+        self.values.insert(name, value);
+    }
+
+    pub fn define_const(&mut self, name: Symbol, value: LiteralValue) {
+        self.uninitialized.remove(&name);
+        self.values.insert(name, value);
+        self.constants.insert(name);
+    }
+
+    // Defines a name that has no value until it is assigned
+    pub fn declare(&mut self, name: Symbol) {
+        self.define(name, LiteralValue::Null);
+        self.uninitialized.insert(name);
+    }
+
+    // None when the name is not in this scope
+    fn get_local(&self, name: Symbol) -> Option<Result<LiteralValue, String>> {
+        let value = self.values.get(&name)?;
+
+        if self.uninitialized.contains(&name) {
+            Some(Err(format!("Variable '{}' used before assignment", name)))
+        } else {
+            Some(Ok(value.clone()))
+        }
+    }
+
+    fn assign_local(&mut self, name: Symbol, value: LiteralValue) -> Option<Result<(), String>> {
+        if !self.values.contains_key(&name) {
+            return None;
+        }
+        if self.constants.contains(&name) {
+            return Some(Err(format!("Cannot assign to constant '{}'", name)));
+        }
+
+        self.uninitialized.remove(&name);
+        self.values.insert(name, value);
+        Some(Ok(()))
+    }
+
+    pub fn get(&self, name: Symbol) -> Result<LiteralValue, String> {
+        match (self.get_local(name), &self.enclosing) {
+            (Some(result), _) => result,
+            (None, Some(env)) => env.borrow().get(name),
+            (None, None) => Err(format!("Undefined variable '{}'", name))
+        }
+    }
+
+    // Resolved code assigns with assign_at
+    pub fn assign(&mut self, name: Symbol, value: LiteralValue) -> Result<(), String> {
+        match self.assign_local(name, value.clone()) {
+            Some(result) => result,
+            None => match &self.enclosing {
+                Some(env) => (env.borrow_mut()).assign(name, value),
+                None => Err(format!("Undefined variable '{}'", name))
+            },
+        }
+    }
+
+    // Looks only in the scope `depth` environments out, or in the globals for None, as worked out by the Resolver
+    pub fn get_at(&self, depth: Option<usize>, name: Symbol) -> Result<LiteralValue, String> {
+        match (depth, &self.enclosing) {
+            (Some(depth), Some(env)) if depth > 0 => env.borrow().get_at(Some(depth - 1), name),
+            (None, Some(env)) => env.borrow().get_at(None, name),
+            _ => self.get_local(name).unwrap_or_else(|| Err(format!("Undefined variable '{}'", name))),
+        }
+    }
+
+    pub fn assign_at(&mut self, depth: Option<usize>, name: Symbol, value: LiteralValue) -> Result<(), String> {
+        match (depth, &self.enclosing) {
+            (Some(depth), Some(env)) if depth > 0 => env.borrow_mut().assign_at(Some(depth - 1), name, value),
+            (None, Some(env)) => env.borrow_mut().assign_at(None, name, value),
+            _ => self.assign_local(name, value).unwrap_or_else(|| Err(format!("Undefined variable '{}'", name))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn x() -> Symbol {
+        Symbol::intern("x")
+    }
+
+    #[test]
+    fn try_unit() {
+        let _environment = Environment::new();
+    }
+
+    #[test]
+    fn define_and_get() {
+        let mut environment = Environment::new();
+        environment.define(x(), LiteralValue::IntValue(1));
+
+        assert_eq!(environment.get(x()), Ok(LiteralValue::IntValue(1)));
+    }
+
+    #[test]
+    fn get_undefined() {
+        let environment = Environment::new();
+
+        assert_eq!(environment.get(x()), Err(String::from("Undefined variable 'x'")));
+    }
+
+    #[test]
+    fn assign_defined() {
+        let mut environment = Environment::new();
+        environment.define(x(), LiteralValue::IntValue(1));
+
+        assert_eq!(environment.assign(x(), LiteralValue::IntValue(2)), Ok(()));
+        assert_eq!(environment.get(x()), Ok(LiteralValue::IntValue(2)));
+    }
+
+    #[test]
+    fn assign_undefined() {
+        let mut environment = Environment::new();
+
+        assert_eq!(
+            environment.assign(x(), LiteralValue::IntValue(2)),
+            Err(String::from("Undefined variable 'x'"))
+        );
+        assert!(environment.get(x()).is_err());
+    }
+
+    #[test]
+    fn enclosed_get_and_assign() {
+        let outer = Rc::new(RefCell::new(Environment::new()));
+        outer.borrow_mut().define(x(), LiteralValue::IntValue(1));
+
+        let mut inner = Environment::new();
+        inner.enclosing = Some(outer.clone());
+
+        assert_eq!(inner.get(x()), Ok(LiteralValue::IntValue(1)));
+        assert_eq!(inner.assign(x(), LiteralValue::IntValue(2)), Ok(()));
+        assert_eq!(outer.borrow().get(x()), Ok(LiteralValue::IntValue(2)));
+    }
+
+    #[test]
+    fn assign_constant() {
+        let mut environment = Environment::new();
+        environment.define_const(x(), LiteralValue::IntValue(1));
+
+        assert_eq!(
+            environment.assign(x(), LiteralValue::IntValue(2)),
+            Err(String::from("Cannot assign to constant 'x'"))
+        );
+        assert_eq!(environment.get(x()), Ok(LiteralValue::IntValue(1)));
+    }
+
+    #[test]
+    fn declared_is_unset_until_assigned() {
+        let mut environment = Environment::new();
+        environment.declare(x());
+
+        assert_eq!(environment.get(x()), Err(String::from("Variable 'x' used before assignment")));
+        assert_eq!(environment.assign(x(), LiteralValue::Null), Ok(()));
+        assert_eq!(environment.get(x()), Ok(LiteralValue::Null));
+    }
+
+    #[test]
+    fn enclosed_define_shadows() {
+        let outer = Rc::new(RefCell::new(Environment::new()));
+        outer.borrow_mut().define(x(), LiteralValue::IntValue(1));
+
+        let mut inner = Environment::new();
+        inner.enclosing = Some(outer.clone());
+        inner.define(x(), LiteralValue::IntValue(2));
+
+        assert_eq!(inner.get(x()), Ok(LiteralValue::IntValue(2)));
+        assert_eq!(outer.borrow().get(x()), Ok(LiteralValue::IntValue(1)));
+    }
+}
@@ -0,0 +1,168 @@
+use crate::expr::LiteralValue;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CorrodeError {
+    Lex { message: String, line: usize, column: usize },
+    Parse { message: String, line: usize, column: usize },
+    Type { message: String, line: usize, column: usize },
+    Resolve { message: String, line: usize, column: usize },
+    Runtime { message: String, line: usize, column: usize },
+    // A value raised by `throw`, carried out of function calls until a `try` catches it
+    Thrown { value: LiteralValue, message: String, line: usize, column: usize },
+}
+
+impl CorrodeError {
+    pub fn lex(line: usize, message: String) -> Self {
+        CorrodeError::Lex { message, line, column: 0 }
+    }
+
+    pub fn parse(line: usize, message: String) -> Self {
+        CorrodeError::Parse { message, line, column: 0 }
+    }
+
+    pub fn type_error(line: usize, message: String) -> Self {
+        CorrodeError::Type { message, line, column: 0 }
+    }
+
+    pub fn resolve(line: usize, message: String) -> Self {
+        CorrodeError::Resolve { message, line, column: 0 }
+    }
+
+    pub fn runtime(line: usize, message: String) -> Self {
+        CorrodeError::Runtime { message, line, column: 0 }
+    }
+
+    pub fn thrown(line: usize, value: LiteralValue) -> Self {
+        let message = format!("Uncaught throw: {}", value.to_string());
+        CorrodeError::Thrown { value, message, line, column: 0 }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            CorrodeError::Lex { message, .. } => message,
+            CorrodeError::Parse { message, .. } => message,
+            CorrodeError::Type { message, .. } => message,
+            CorrodeError::Resolve { message, .. } => message,
+            CorrodeError::Runtime { message, .. } => message,
+            CorrodeError::Thrown { message, .. } => message,
+        }
+    }
+
+    pub fn line(&self) -> usize {
+        match self {
+            CorrodeError::Lex { line, .. } => *line,
+            CorrodeError::Parse { line, .. } => *line,
+            CorrodeError::Type { line, .. } => *line,
+            CorrodeError::Resolve { line, .. } => *line,
+            CorrodeError::Runtime { line, .. } => *line,
+            CorrodeError::Thrown { line, .. } => *line,
+        }
+    }
+
+    // The 1-based column the error points at, 0 when it is not known
+    pub fn column(&self) -> usize {
+        match self {
+            CorrodeError::Lex { column, .. } => *column,
+            CorrodeError::Parse { column, .. } => *column,
+            CorrodeError::Type { column, .. } => *column,
+            CorrodeError::Resolve { column, .. } => *column,
+            CorrodeError::Runtime { column, .. } => *column,
+            CorrodeError::Thrown { column, .. } => *column,
+        }
+    }
+
+    pub fn with_column(mut self, at: usize) -> Self {
+        match &mut self {
+            CorrodeError::Lex { column, .. } => *column = at,
+            CorrodeError::Parse { column, .. } => *column = at,
+            CorrodeError::Type { column, .. } => *column = at,
+            CorrodeError::Resolve { column, .. } => *column = at,
+            CorrodeError::Runtime { column, .. } => *column = at,
+            CorrodeError::Thrown { column, .. } => *column = at,
+        }
+
+        self
+    }
+
+    // The message followed by the offending line of `source` and a '^' under the column, like
+    //   [line 1] Expected expression, found ';'
+    //   1 | 1 +;
+    //     |    ^
+    // Without a known column or line only the message is returned
+    pub fn display_with_source(&self, source: &str) -> String {
+        let text = match (self.column(), source.lines().nth(self.line().wrapping_sub(1))) {
+            (0, _) | (_, None) => return self.to_string(),
+            (_, Some(text)) => text,
+        };
+
+        // Columns count bytes, the caret is indented by characters
+        let before = text.get(..self.column() - 1).unwrap_or(text);
+        let number = self.line().to_string();
+        let gutter = " ".repeat(number.len());
+
+        format!("{}\n{} | {}\n{} | {}^", self, number, text, gutter, " ".repeat(before.chars().count()))
+    }
+}
+
+impl std::fmt::Display for CorrodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        // Errors that do not come from a line of source, like the step limit, are shown without one
+        if self.line() == 0 {
+            return write!(f, "{}", self.message());
+        }
+
+        match self {
+            CorrodeError::Lex { message, line, .. } => write!(f, "[line {}] {}", line, message),
+            CorrodeError::Parse { message, line, .. } => write!(f, "[line {}] {}", line, message),
+            CorrodeError::Type { message, line, .. } => write!(f, "[line {}] {}", line, message),
+            CorrodeError::Resolve { message, line, .. } => write!(f, "[line {}] {}", line, message),
+            CorrodeError::Runtime { message, line, .. } => write!(f, "[line {}] {}", line, message),
+            CorrodeError::Thrown { message, line, .. } => write!(f, "[line {}] {}", line, message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_includes_line() {
+        let error = CorrodeError::runtime(4, String::from("Operands must be numbers"));
+
+        assert_eq!(error.to_string(), "[line 4] Operands must be numbers");
+        assert_eq!(error.line(), 4);
+        assert_eq!(error.message(), "Operands must be numbers");
+
+        let error = CorrodeError::runtime(0, String::from("Execution step limit exceeded"));
+        assert_eq!(error.to_string(), "Execution step limit exceeded");
+    }
+
+    #[test]
+    fn display_with_source_points_at_column() {
+        let tokens = crate::tokenizer::Tokenizer::new("echo 0;\n1 +;").tokenize().unwrap();
+        let error = crate::parser::Parser::new(tokens).parse().err().unwrap().remove(0);
+
+        assert_eq!(
+            error.display_with_source("echo 0;\n1 +;"),
+            "[line 2] Expected expression, found ';'\n2 | 1 +;\n  |    ^"
+        );
+    }
+
+    #[test]
+    fn display_with_source_without_column() {
+        let error = CorrodeError::runtime(1, String::from("Integer overflow"));
+
+        assert_eq!(error.display_with_source("echo 1;"), "[line 1] Integer overflow");
+        assert_eq!(error.with_column(3).display_with_source(""), "[line 1] Integer overflow");
+    }
+
+    #[test]
+    fn kinds_are_distinct() {
+        let lex = CorrodeError::lex(1, String::from("msg"));
+        let parse = CorrodeError::parse(1, String::from("msg"));
+
+        assert_ne!(lex, parse);
+        assert_eq!(lex.to_string(), parse.to_string());
+    }
+}
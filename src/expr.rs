@@ -1,390 +1,1859 @@
-// 12 43
-
-/*
-For future:
-
-make so in this case it will write String("a")
->>> true * "a"
-Binary operator Star cannot be applied for operands True, StringValue("a")
-*/
-
-use crate::tokenizer::{Token, TokenType};
-use crate::tokenizer;
-use crate::environment::Environment;
-use std::rc::Rc;
-use std::cell::RefCell;
-
-#[derive(Clone)]
-pub enum LiteralValue {
-    Number(f64),
-    StringValue(String),
-    True,
-    False,
-    Null,
-    Callable { 
-        name: String,
-        arity: usize,
-        fn_: Rc<dyn Fn(&Vec<LiteralValue>) -> LiteralValue>,
-    },
-}
-use LiteralValue::*;
-
-impl std::fmt::Debug for LiteralValue {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "{}", self.to_string())
-    }
-}
-
-impl PartialEq for LiteralValue {
-    fn eq(&self, other: &Self) -> bool {
-        match (self, other) {
-            (Number(x), Number(y)) => x == y,
-            (
-                Callable {
-                    name,
-                    arity,
-                    fn_: _
-                },
-                Callable {
-                    name: name2,
-                    arity: arity2,
-                    fn_: _
-                }
-            ) => name == name2 && arity == arity2,
-            (StringValue(s1), StringValue(s2)) => s1 == s2,
-            (True, True) => true,
-            (False, False) => true,
-            (Null, Null) => true,
-            _ => false,
-        }
-    }
-}
-
-fn unwrap_as_f64(literal: Option<tokenizer::LiteralValue>) -> f64 {
-    match literal {
-        Some(tokenizer::LiteralValue::FValue(x)) => x as f64,
-        _ => panic!("Could not unwrap as f64")
-    }
-}
-
-fn unwrap_as_string(literal: Option<tokenizer::LiteralValue>) -> String {
-    match literal {
-        Some(tokenizer::LiteralValue::StringValue(s)) => s.clone(),
-        _ => panic!("Could not unwrap as string")
-    }
-}
-
-impl LiteralValue {
-    pub fn to_string(&self) -> String {
-        match self {
-            Number(x) => x.to_string(),
-            StringValue(s) => s.clone(),
-            True => String::from("true"),
-            False => String::from("false"),
-            Null => String::from("null"),
-            Callable { name, arity , fn_: _ } => format!("{}/{}", name, arity)
-        }
-    }
-
-    pub fn to_type(&self) -> &str {
-        match self {
-            Number(_) => "Number",
-            StringValue(_) => "String",
-            True => "Boolean",
-            False => "Boolean",
-            Null => "Null",
-            Callable { name: _, arity: _ , fn_: _ } => "Callable"
-        }
-    }
-
-    pub fn from_token(token: Token) -> Self {
-        match token.token_type {
-            TokenType::Number => Self::Number(unwrap_as_f64(token.literal)),
-            TokenType::StringLit => Self::StringValue(unwrap_as_string(token.literal)),
-            TokenType::True => Self::True,
-            TokenType::False => Self::False,
-            TokenType::Null => Self::Null,
-            _ => panic!("Could not create LiteralValue from {:?}", token)
-        }
-    }
-
-    pub fn is_falsy(&self) -> LiteralValue {
-        match self {
-            Self::Number(x) => if *x == 0.0 { Self::True } else { Self::False },
-            Self::StringValue(s) => if s.len() == 0 { Self::True } else { Self::False },
-            Self::True => Self::False,
-            Self::False => Self::True,
-            Self::Null => Self::True,
-            Self::Callable { name: _, arity: _ , fn_: _ } => Self::False
-        }
-    }
-
-    pub fn is_truthy(&self) -> LiteralValue {
-        match self {
-            Self::Number(x) => if *x == 0.0 { Self::False } else { Self::True },
-            Self::StringValue(s) => if s.len() == 0 { Self::False } else { Self::True },
-            Self::True => Self::True,
-            Self::False => Self::False,
-            Self::Null => Self::False,
-            Self::Callable { name: _, arity: _, fn_: _ } => Self::True
-        }
-    }
-
-    pub fn from_bool(b: bool) -> LiteralValue {
-        if b { True } else { False }
-    }
-}
-
-pub enum Expr {
-    Binary {
-        left: Box<Expr>,
-        operator: Token,
-        right: Box<Expr>
-    },
-    Grouping {
-        expression: Box<Expr>
-    },
-    Call {
-        callee: Box<Expr>,
-        paren: Token,
-        arguments: Vec<Expr>,
-    },
-    Literal {
-        value: LiteralValue
-    },
-    Logical {
-        left: Box<Expr>,
-        operator: Token,
-        right: Box<Expr>
-    },
-    Unary {
-        operator: Token,
-        right: Box<Expr>
-    },
-    Variable {
-        name: Token
-    },
-    Assign {
-        name: Token,
-        value: Box<Expr>
-    },
-}
-
-impl std::fmt::Debug for Expr {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.to_string())
-    }
-}
-
-impl Expr {
-    #[allow(dead_code)]
-    pub fn to_string(&self) -> String {
-        match self {
-            Expr::Binary {
-                left,
-                operator,
-                right
-            } => format!(
-                "({} {} {})",
-                operator.lexeme,
-                left.to_string(),
-                right.to_string()
-            ),
-            Expr::Call { callee, paren: _, arguments} => format!(
-                "({} {:?})",
-                (*callee).to_string(),
-                arguments
-            ),
-            Expr::Grouping { expression } => format!(
-                "(group {})",
-                expression.to_string()
-            ),
-            Expr::Literal { value } => format!(
-                "{}",
-                value.to_string()
-            ),
-            Expr::Logical { left, operator, right } => format!(
-                "({} {} {})",
-                operator.lexeme,
-                left.to_string(),
-                right.to_string()
-            ),
-            Expr::Unary { operator, right} => {
-                let operator_str = operator.lexeme.clone();
-                let right_str = right.to_string();
-                format!("({} {})", operator_str, right_str)
-            },
-            Expr::Variable { name } => format!(
-                "var {}",
-                name.lexeme
-            ),
-            Expr::Assign { name, value } => format!(
-                "{} = {}",
-                name.lexeme, value.to_string()
-            ),
-        }
-    }
-
-    pub fn evaluate(&self, environment: Rc<RefCell<Environment>>) -> Result<LiteralValue, String> {
-        match self {
-            Expr::Assign { name, value } => {
-                let new_value = (*value).evaluate(environment.clone())?;
-                let assign_success = environment.borrow_mut().assign(&name.lexeme, new_value.clone());
-
-                if assign_success {
-                    Ok(new_value)
-                } else {
-                    Err(format!("{} was not declared in this scope", name.lexeme))
-                }
-            },
-            Expr::Call { callee, paren, arguments } => {
-                let callable = (*callee).evaluate(environment.clone())?;
-                match callable {
-                    Callable { name, arity, fn_} => {
-                        if arguments.len() != arity {
-                            return Err(format!(
-                                "Callable {} expected {} arguments but {} were given",
-                                name,
-                                arity,
-                                arguments.len()
-                            ));
-                        }
-
-                        let mut arg_vals = Vec::new();
-                        for arg in arguments {
-                            let val = arg.evaluate(environment.clone())?;
-                            arg_vals.push(val);
-                        }
-                        
-                        Ok(fn_(&arg_vals))
-                    },
-                    other => Err(format!("{} is not callable", other.to_string()))
-                }
-            },
-            Expr::Variable { name } => {
-                match environment.borrow().get(&name.lexeme) {
-                    Some(value) => Ok(value.clone()),
-                    None => Err(format!("{} was not declared in this scope", name.lexeme))
-                }
-            },
-            Expr::Literal { value } => Ok(value.clone()),
-            Expr::Logical { left, operator, right } => {
-                match operator.token_type {
-                    TokenType::Or => {
-                        let lhs_value = left.evaluate(environment.clone())?;
-                        let lhs_true = lhs_value.is_truthy();
-                        if lhs_true == True {
-                            return Ok(lhs_value);
-                        } else {
-                            return right.evaluate(environment.clone());
-                        }
-                    },
-                    TokenType::And => {
-                        let lhs_value = left.evaluate(environment.clone())?;
-                        let lhs_true = lhs_value.is_truthy();
-                        if lhs_true == False {
-                            return Ok(lhs_value);
-                        } else {
-                            return right.evaluate(environment.clone());
-                        }
-                    },
-                    token_type => return Err(format!("Invalid token in logical expression: {}", token_type)),
-                }
-            },
-            Expr::Grouping { expression } => expression.evaluate(environment.clone()),
-            Expr::Unary {operator, right} => {
-                let right = right.evaluate(environment.clone())?;
-
-                match (&right, operator.token_type) {
-                    (Number(x), TokenType::Minus) => Ok(Number(-x)),
-                    (_, TokenType::Minus) => Err(format!("Minus not implemented for {}", right.to_type())),
-                    (any, TokenType::Bang) => Ok(any.is_falsy()),
-                    (_, token_type) => Err(format!("{} is not a valid unary operator", token_type))
-                }
-            },
-            Expr::Binary { left, operator, right} => {
-                let left = left.evaluate(environment.clone())?;
-                let right = right.evaluate(environment.clone())?;
-
-                match (&left, operator.token_type, &right) {
-                    //expreimental
-                    //(Number(x), TokenType::Slash, Number(0.0)) => Err(format!("Binary operator Slash cannot be applied for operands {:?}, Number(0.0)", Number(*x))),
-
-                    (Number(x), TokenType::Plus, Number(y)) => Ok(Number(x + y)),
-                    (Number(x), TokenType::Minus, Number(y)) => Ok(Number(x - y)),
-                    (Number(x), TokenType::Star, Number(y)) => Ok(Number(x * y)),
-                    (Number(x), TokenType::Slash, Number(y)) => Ok(Number(x / y)),
-
-                    (Number(x), TokenType::Greater, Number(y)) => Ok(LiteralValue::from_bool(x > y)),
-                    (Number(x), TokenType::GreaterEqual, Number(y)) => Ok(LiteralValue::from_bool(x >= y)),
-                    (Number(x), TokenType::Less, Number(y)) => Ok(LiteralValue::from_bool(x < y)),
-                    (Number(x), TokenType::LessEqual, Number(y)) => Ok(LiteralValue::from_bool(x <= y)),
-
-                    (StringValue(s), operator, Number(x)) => Err(format!("Binary operator {} cannot be applied for operands {:?}, {:?}", operator, StringValue(String::from(s)), Number(*x))),
-                    (Number(x), operator, StringValue(s)) => Err(format!("Binary operator {} cannot be applied for operands {:?}, {:?}", operator, Number(*x), StringValue(String::from(s)))),
-
-                    (StringValue(s1), TokenType::Plus, StringValue(s2)) => Ok(StringValue(format!("{}{}", s1, s2))),
-
-                    (a, TokenType::BangEqual, b) => Ok(LiteralValue::from_bool(a != b)),
-                    (a, TokenType::EqualEqual, b) => Ok(LiteralValue::from_bool(a == b)),
-
-                    (StringValue(s1), TokenType::Greater, StringValue(s2)) => Ok(LiteralValue::from_bool(s1 > s2)),
-                    (StringValue(s1), TokenType::GreaterEqual, StringValue(s2)) => Ok(LiteralValue::from_bool(s1 >= s2)),
-                    (StringValue(s1), TokenType::Less, StringValue(s2)) => Ok(LiteralValue::from_bool(s1 < s2)),
-                    (StringValue(s1), TokenType::LessEqual, StringValue(s2)) => Ok(LiteralValue::from_bool(s1 <= s2)),
-
-                    (a, token_type, b) => Err(format!("Binary operator {} cannot be applied for operands {:?}, {:?}", token_type, a, b)),
-                }
-            }
-        }
-    }
-
-    #[allow(dead_code)]
-    pub fn print(&self) {
-        println!("{}", self.to_string());
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn pretty_print_ast() {
-        let minus_token = Token {
-            token_type: TokenType::Minus,
-            lexeme: String::from("-"),
-            literal: None,
-            line_number: 0
-        };
-
-        let ott = Expr::Literal { // 123
-            value: Number(123.0)
-        };
-
-        let group = Expr::Grouping {
-            expression: Box::from(Expr::Literal {
-                value: Number(45.67)
-            })
-        };
-
-        let mul = Token {
-            token_type: TokenType::Star,
-            lexeme: String::from("*"),
-            literal: None,
-            line_number: 0
-        };
-
-        let ast = Expr::Binary {
-            left: Box::from(Expr::Unary {
-                operator: minus_token,
-                right: Box::from(ott)
-            }),
-            operator: mul,
-            right: Box::from(group)
-        };
-
-        let result = ast.to_string();
-        assert_eq!(result, "(* (- 123) (group 45.67))");
-    }
-}
\ No newline at end of file
+use crate::tokenizer::{Span, Token, TokenType};
+use crate::tokenizer;
+use crate::environment::Environment;
+use crate::error::CorrodeError;
+use crate::interner::Symbol;
+use crate::interpreter::{Config, Interpreter, IntegerOverflow, MissingKey, Unwind};
+use crate::stmt::Stmt;
+use std::rc::Rc;
+use std::cell::{Cell, RefCell};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+// How many scopes out from the innermost one a name was declared in, set by the Resolver.
+// None is a global, looked up in the outermost environment
+pub type Depth = Cell<Option<usize>>;
+
+pub type NativeFn = Rc<dyn Fn(&[LiteralValue]) -> Result<LiteralValue, String>>;
+
+#[derive(Clone)]
+pub enum LiteralValue {
+    IntValue(i64),
+    FloatValue(f64),
+    StringValue(String),
+    True,
+    False,
+    Null,
+    Callable { 
+        name: String,
+        arity: usize,
+        // How many of the last arguments may be left out
+        optional: usize,
+        // Any number of arguments may follow the first `arity`
+        variadic: bool,
+        fn_: NativeFn,
+    },
+    Function(Rc<Function>),
+    Class(Rc<Class>),
+    Instance(Rc<RefCell<Instance>>),
+    Array(Rc<RefCell<Vec<LiteralValue>>>),
+    // Tuples can not be changed after they are made, so they are not behind a RefCell
+    Tuple(Rc<Vec<LiteralValue>>),
+    Map(Rc<RefCell<HashMap<MapKey, LiteralValue>>>),
+}
+use LiteralValue::*;
+
+// The values allowed as map keys, since floats and objects can not be hashed
+#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum MapKey {
+    Int(i64),
+    String(String),
+}
+
+impl MapKey {
+    pub fn from_value(value: &LiteralValue) -> Result<MapKey, String> {
+        match value {
+            IntValue(x) => Ok(MapKey::Int(*x)),
+            StringValue(s) => Ok(MapKey::String(s.clone())),
+            other => Err(format!("Map keys must be Strings or Ints, got {}", other.to_type())),
+        }
+    }
+
+    pub fn to_string(&self) -> String {
+        match self {
+            MapKey::Int(x) => x.to_string(),
+            MapKey::String(s) => s.clone(),
+        }
+    }
+
+    pub fn to_value(&self) -> LiteralValue {
+        match self {
+            MapKey::Int(x) => IntValue(*x),
+            MapKey::String(s) => StringValue(s.clone()),
+        }
+    }
+}
+
+pub struct Class {
+    pub name: String,
+    pub superclass: Option<Rc<Class>>,
+    pub methods: HashMap<String, Rc<Function>>,
+}
+
+impl Class {
+    pub fn find_method(&self, name: &str) -> Option<Rc<Function>> {
+        match self.methods.get(name) {
+            Some(method) => Some(method.clone()),
+            None => match &self.superclass {
+                Some(superclass) => superclass.find_method(name),
+                None => None,
+            },
+        }
+    }
+}
+
+pub struct Instance {
+    pub class: Rc<Class>,
+    pub fields: HashMap<String, LiteralValue>,
+}
+
+impl Instance {
+    pub fn get(instance: &Rc<RefCell<Instance>>, name: &Token) -> Result<LiteralValue, CorrodeError> {
+        if let Some(value) = instance.borrow().fields.get(&name.lexeme) {
+            return Ok(value.clone());
+        }
+
+        let class = instance.borrow().class.clone();
+        match class.find_method(&name.lexeme) {
+            Some(method) => Ok(Function(Rc::new(method.bind(instance.clone())))),
+            None => Err(CorrodeError::runtime(name.line_number, format!("Undefined property '{}'", name.lexeme)).with_column(name.column)),
+        }
+    }
+}
+
+pub struct Function {
+    pub name: Token,
+    pub params: Vec<Token>,
+    // Bound to an Array of the arguments after `params`
+    pub rest: Option<Token>,
+    pub body: Rc<Vec<Stmt>>,
+    // The environment the function was declared in, shared by reference so the function sees later changes.
+    // A function stored in its own closure (any named function, or one captured by a variable it can reach)
+    // forms an Rc cycle that is never freed; there is no cycle collector, so such environments live until exit
+    pub closure: Rc<RefCell<Environment>>,
+    pub is_initializer: bool,
+    // The settings of the interpreter that declared the function, `echo` inside the body writes to its output
+    pub config: Rc<Config>,
+}
+
+impl Function {
+    pub fn bind(&self, instance: Rc<RefCell<Instance>>) -> Function {
+        let mut environment = Environment::new();
+        environment.enclosing = Some(self.closure.clone());
+        environment.define(Symbol::intern("self"), Instance(instance));
+
+        Function {
+            name: self.name.clone(),
+            params: self.params.clone(),
+            rest: self.rest.clone(),
+            body: self.body.clone(),
+            closure: Rc::new(RefCell::new(environment)),
+            is_initializer: self.is_initializer,
+            config: self.config.clone(),
+        }
+    }
+
+    // `paren` is the call's closing parenthesis, where an arity error points
+    pub fn check_arity(&self, count: usize, paren: &Token) -> Result<(), CorrodeError> {
+        let arity = self.params.len();
+        match &self.rest {
+            None if count != arity => Err(CorrodeError::runtime(paren.line_number, format!(
+                "Expected {} arguments but got {}",
+                arity,
+                count
+            )).with_column(paren.column)),
+            Some(_) if count < arity => Err(CorrodeError::runtime(paren.line_number, format!(
+                "Expected at least {} arguments but got {}",
+                arity,
+                count
+            )).with_column(paren.column)),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn call(&self, mut arguments: Vec<LiteralValue>) -> Result<LiteralValue, CorrodeError> {
+        let mut environment = Environment::new();
+        environment.enclosing = Some(self.closure.clone());
+
+        let extra = arguments.split_off(self.params.len().min(arguments.len()));
+        for (param, argument) in self.params.iter().zip(arguments) {
+            environment.define(param.symbol(), argument);
+        }
+        if let Some(rest) = &self.rest {
+            environment.define(rest.symbol(), Array(Rc::new(RefCell::new(extra))));
+        }
+
+        let mut interpreter = Interpreter::for_closure(Rc::new(RefCell::new(environment)), self.config.clone());
+        let result = match interpreter.execute(self.body.iter().collect()) {
+            Ok(()) => Null,
+            Err(Unwind::Return(value)) => value,
+            Err(Unwind::Error(error)) => return Err(error),
+            Err(Unwind::Throw(value, line)) => return Err(CorrodeError::thrown(line, value)),
+            Err(Unwind::Break | Unwind::Continue) => Null,
+        };
+
+        // An initializer always returns the instance it was bound to
+        if self.is_initializer {
+            return self.closure.borrow().get(Symbol::intern("self"))
+                .map_err(|msg| CorrodeError::runtime(self.name.line_number, msg).with_column(self.name.column));
+        }
+
+        Ok(result)
+    }
+}
+
+// Plain values map to the matching JSON type, runtime objects that can not appear in source are written as their string form
+#[cfg(feature = "serde")]
+impl serde::Serialize for LiteralValue {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::{SerializeMap, SerializeSeq};
+
+        match self {
+            IntValue(x) => serializer.serialize_i64(*x),
+            FloatValue(x) => serializer.serialize_f64(*x),
+            StringValue(s) => serializer.serialize_str(s),
+            True => serializer.serialize_bool(true),
+            False => serializer.serialize_bool(false),
+            Null => serializer.serialize_unit(),
+            Array(elements) => {
+                let elements = elements.borrow();
+                let mut seq = serializer.serialize_seq(Some(elements.len()))?;
+                for element in elements.iter() {
+                    seq.serialize_element(element)?;
+                }
+                seq.end()
+            },
+            Tuple(elements) => {
+                let mut seq = serializer.serialize_seq(Some(elements.len()))?;
+                for element in elements.iter() {
+                    seq.serialize_element(element)?;
+                }
+                seq.end()
+            },
+            Map(entries) => {
+                let entries = entries.borrow();
+                let mut keys = entries.keys().collect::<Vec<&MapKey>>();
+                keys.sort();
+
+                let mut map = serializer.serialize_map(Some(keys.len()))?;
+                for key in keys {
+                    map.serialize_entry(&key.to_string(), &entries[key])?;
+                }
+                map.end()
+            },
+            other => serializer.serialize_str(&other.to_string()),
+        }
+    }
+}
+
+// Only the plain values that can appear as literals in source are read back
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for LiteralValue {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct LiteralVisitor;
+
+        impl serde::de::Visitor<'_> for LiteralVisitor {
+            type Value = LiteralValue;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                write!(f, "a number, string, boolean or null")
+            }
+
+            fn visit_i64<E: serde::de::Error>(self, x: i64) -> Result<LiteralValue, E> {
+                Ok(IntValue(x))
+            }
+
+            fn visit_u64<E: serde::de::Error>(self, x: u64) -> Result<LiteralValue, E> {
+                i64::try_from(x).map(IntValue).map_err(|_| E::custom("integer literal does not fit in an Int"))
+            }
+
+            fn visit_f64<E: serde::de::Error>(self, x: f64) -> Result<LiteralValue, E> {
+                Ok(FloatValue(x))
+            }
+
+            fn visit_str<E: serde::de::Error>(self, s: &str) -> Result<LiteralValue, E> {
+                Ok(StringValue(s.to_string()))
+            }
+
+            fn visit_bool<E: serde::de::Error>(self, b: bool) -> Result<LiteralValue, E> {
+                Ok(LiteralValue::from_bool(b))
+            }
+
+            fn visit_unit<E: serde::de::Error>(self) -> Result<LiteralValue, E> {
+                Ok(Null)
+            }
+        }
+
+        deserializer.deserialize_any(LiteralVisitor)
+    }
+}
+
+impl std::fmt::Debug for LiteralValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.to_string())
+    }
+}
+
+impl PartialEq for LiteralValue {
+    fn eq(&self, other: &Self) -> bool {
+        self.equals(other)
+    }
+}
+
+impl LiteralValue {
+    // What == and != mean. Values of different types are never equal, except an Int and a Float with the
+    // same value. Arrays, tuples and maps compare their elements, functions, classes and instances their identity
+    pub fn equals(&self, other: &LiteralValue) -> bool {
+        match (self, other) {
+            (IntValue(x), IntValue(y)) => x == y,
+            (FloatValue(x), FloatValue(y)) => x == y,
+            (IntValue(x), FloatValue(y)) => (*x as f64) == *y,
+            (FloatValue(x), IntValue(y)) => *x == (*y as f64),
+            (
+                Callable {
+                    name,
+                    arity,
+                    optional: _,
+                    variadic: _,
+                    fn_: _
+                },
+                Callable {
+                    name: name2,
+                    arity: arity2,
+                    optional: _,
+                    variadic: _,
+                    fn_: _
+                }
+            ) => name == name2 && arity == arity2,
+            (Function(f1), Function(f2)) => Rc::ptr_eq(f1, f2),
+            (Class(c1), Class(c2)) => Rc::ptr_eq(c1, c2),
+            (Instance(i1), Instance(i2)) => Rc::ptr_eq(i1, i2),
+            (Array(a1), Array(a2)) => *a1.borrow() == *a2.borrow(),
+            (Tuple(t1), Tuple(t2)) => t1 == t2,
+            (Map(m1), Map(m2)) => *m1.borrow() == *m2.borrow(),
+            (StringValue(s1), StringValue(s2)) => s1 == s2,
+            (True, True) => true,
+            (False, False) => true,
+            (Null, Null) => true,
+            _ => false,
+        }
+    }
+}
+
+fn unwrap_as_number(literal: Option<tokenizer::LiteralValue>) -> LiteralValue {
+    match literal {
+        Some(tokenizer::LiteralValue::IntValue(x)) => IntValue(x),
+        Some(tokenizer::LiteralValue::FloatValue(x)) => FloatValue(x),
+        _ => panic!("Could not unwrap as number")
+    }
+}
+
+fn unwrap_as_string(literal: Option<tokenizer::LiteralValue>) -> String {
+    match literal {
+        Some(tokenizer::LiteralValue::StringValue(s)) => s.clone(),
+        _ => panic!("Could not unwrap as string")
+    }
+}
+
+// Checks an index value against the length of the indexed array or string.
+// A negative index counts from the end, -1 is the last element
+fn to_index(index: LiteralValue, length: usize, bracket: &Token) -> Result<usize, CorrodeError> {
+    let index = match index {
+        IntValue(index) => index,
+        other => return Err(CorrodeError::runtime(bracket.line_number, format!("Index must be an Int, got {}", other.to_type())).with_column(bracket.column)),
+    };
+
+    let adjusted = if index < 0 { index.checked_add(length as i64) } else { Some(index) };
+    match adjusted {
+        Some(adjusted) if adjusted >= 0 && (adjusted as usize) < length => Ok(adjusted as usize),
+        _ => Err(CorrodeError::runtime(bracket.line_number, format!("Index {} out of bounds for length {}", index, length)).with_column(bracket.column)),
+    }
+}
+
+// Evaluates array elements or call arguments in order, splicing in the elements of every spread one
+fn evaluate_elements(elements: &[Expr], environment: &Rc<RefCell<Environment>>, config: &Rc<Config>) -> Result<Vec<LiteralValue>, CorrodeError> {
+    let mut values = Vec::new();
+
+    for element in elements {
+        match element {
+            Expr::Spread { ellipsis, inner, .. } => match inner.evaluate(environment.clone(), config)? {
+                Array(spread) => values.extend(spread.borrow().iter().cloned()),
+                Tuple(spread) => values.extend(spread.iter().cloned()),
+                other => return Err(CorrodeError::runtime(
+                    ellipsis.line_number,
+                    format!("Can only spread Arrays and Tuples, got {}", other.to_type())
+                ).with_column(ellipsis.column)),
+            },
+            element => values.push(element.evaluate(environment.clone(), config)?),
+        }
+    }
+
+    Ok(values)
+}
+
+// Slice bounds count from the end when negative like indices do, but are clamped to the object
+// instead of being an error. A start past the end gives an empty slice
+fn to_slice_range(
+    start: Option<LiteralValue>,
+    end: Option<LiteralValue>,
+    length: usize,
+    bracket: &Token
+) -> Result<(usize, usize), CorrodeError> {
+    let bound = |bound: Option<LiteralValue>, default: usize| match bound {
+        None => Ok(default),
+        Some(IntValue(x)) if x < 0 => Ok(length.saturating_sub(x.unsigned_abs() as usize)),
+        Some(IntValue(x)) => Ok((x as usize).min(length)),
+        Some(other) => Err(CorrodeError::runtime(bracket.line_number, format!("Slice bounds must be Ints, got {}", other.to_type())).with_column(bracket.column)),
+    };
+
+    let start = bound(start, 0)?;
+    let end = bound(end, length)?;
+
+    Ok((start, end.max(start)))
+}
+
+// Follows Lox: a whole Float prints without its ".0", so 1.0 prints as 1 just like the Int 1.
+// Anything else is the shortest form that reads back as the same Float, 0.1 + 0.2 prints as
+// 0.30000000000000004. Very large and very small magnitudes use an exponent, 1e300 and 1.5e-7
+fn float_to_string(x: f64) -> String {
+    let magnitude = x.abs();
+
+    if x.is_finite() && magnitude != 0.0 && !(1e-5..1e16).contains(&magnitude) {
+        format!("{:e}", x)
+    } else {
+        x.to_string()
+    }
+}
+
+impl LiteralValue {
+    pub fn to_string(&self) -> String {
+        match self {
+            IntValue(x) => x.to_string(),
+            FloatValue(x) => float_to_string(*x),
+            StringValue(s) => s.clone(),
+            True => String::from("true"),
+            False => String::from("false"),
+            Null => String::from("null"),
+            Callable { name, arity, optional: _, variadic: _, fn_: _ } => format!("{}/{}", name, arity),
+            Function(function) => format!("{}/{}", function.name.lexeme, function.params.len()),
+            Class(class) => class.name.clone(),
+            Instance(instance) => format!("{} instance", instance.borrow().class.name),
+            Array(elements) => format!(
+                "[{}]",
+                elements.borrow().iter().map(|element| element.to_string()).collect::<Vec<String>>().join(", ")
+            ),
+            Tuple(elements) => format!(
+                "({})",
+                elements.iter().map(|element| element.to_string()).collect::<Vec<String>>().join(", ")
+            ),
+            Map(entries) => {
+                // Sorted by key so printing a map is deterministic
+                let entries = entries.borrow();
+                let mut keys = entries.keys().collect::<Vec<&MapKey>>();
+                keys.sort();
+
+                format!(
+                    "{{{}}}",
+                    keys.iter().map(|key| format!("{}: {}", key.to_string(), entries[*key].to_string())).collect::<Vec<String>>().join(", ")
+                )
+            }
+        }
+    }
+
+    pub fn to_type(&self) -> &str {
+        match self {
+            IntValue(_) => "Int",
+            FloatValue(_) => "Float",
+            StringValue(_) => "String",
+            True => "Boolean",
+            False => "Boolean",
+            Null => "Null",
+            Callable { name: _, arity: _, optional: _, variadic: _, fn_: _ } => "Callable",
+            Function(_) => "Callable",
+            Class(_) => "Class",
+            Instance(_) => "Instance",
+            Array(_) => "Array",
+            Tuple(_) => "Tuple",
+            Map(_) => "Map"
+        }
+    }
+
+    pub fn from_token(token: Token) -> Self {
+        match token.token_type {
+            TokenType::Number => unwrap_as_number(token.literal),
+            TokenType::StringLit => Self::StringValue(unwrap_as_string(token.literal)),
+            TokenType::True => Self::True,
+            TokenType::False => Self::False,
+            TokenType::Null => Self::Null,
+            _ => panic!("Could not create LiteralValue from {:?}", token)
+        }
+    }
+
+    // Only false and null are falsy, every other value (including 0 and "") is truthy
+    pub fn is_truthy(&self) -> bool {
+        !matches!(self, Self::False | Self::Null)
+    }
+
+    pub fn from_bool(b: bool) -> LiteralValue {
+        if b { True } else { False }
+    }
+
+    // Both operands as Floats, None unless both are numbers
+    fn as_floats(&self, other: &LiteralValue) -> Option<(f64, f64)> {
+        match (self, other) {
+            (IntValue(x), IntValue(y)) => Some((*x as f64, *y as f64)),
+            (IntValue(x), FloatValue(y)) => Some((*x as f64, *y)),
+            (FloatValue(x), IntValue(y)) => Some((*x, *y as f64)),
+            (FloatValue(x), FloatValue(y)) => Some((*x, *y)),
+            _ => None,
+        }
+    }
+
+    // What < <= > >= compare. Numbers compare by value, an Int mixed with a Float is promoted, and Strings
+    // compare lexicographically. NaN is not ordered against anything, that is an error too
+    pub fn compare(&self, other: &LiteralValue) -> Result<Ordering, String> {
+        match (self, other) {
+            (IntValue(x), IntValue(y)) => Ok(x.cmp(y)),
+            (StringValue(s1), StringValue(s2)) => Ok(s1.cmp(s2)),
+            _ => match self.as_floats(other) {
+                Some((x, y)) => x.partial_cmp(&y).ok_or_else(|| String::from("NaN can not be compared")),
+                None => Err(String::from("Operands must be numbers")),
+            },
+        }
+    }
+
+    // An Int as a Float, anything else unchanged
+    fn promoted(&self) -> LiteralValue {
+        match self {
+            IntValue(x) => FloatValue(*x as f64),
+            other => other.clone(),
+        }
+    }
+
+    // The arithmetic below keeps two Ints as an Int and promotes an Int mixed with a Float to a Float.
+    // An Int result that does not fit is INTEGER_OVERFLOW, the caller decides whether to redo it on Floats
+    pub fn add(&self, other: &LiteralValue) -> Result<LiteralValue, String> {
+        match (self, other) {
+            (IntValue(x), IntValue(y)) => x.checked_add(*y).map(IntValue).ok_or_else(integer_overflow),
+            (StringValue(s1), StringValue(s2)) => Ok(StringValue(format!("{}{}", s1, s2))),
+            _ => match self.as_floats(other) {
+                Some((x, y)) => Ok(FloatValue(x + y)),
+                None => Err(String::from("Operands must be two numbers or two strings")),
+            },
+        }
+    }
+
+    pub fn sub(&self, other: &LiteralValue) -> Result<LiteralValue, String> {
+        match (self, other) {
+            (IntValue(x), IntValue(y)) => x.checked_sub(*y).map(IntValue).ok_or_else(integer_overflow),
+            _ => self.float_operation(other, |x, y| x - y),
+        }
+    }
+
+    pub fn mul(&self, other: &LiteralValue) -> Result<LiteralValue, String> {
+        match (self, other) {
+            (IntValue(x), IntValue(y)) => x.checked_mul(*y).map(IntValue).ok_or_else(integer_overflow),
+            _ => self.float_operation(other, |x, y| x * y),
+        }
+    }
+
+    // Always a Float, even for two Ints
+    pub fn div(&self, other: &LiteralValue) -> Result<LiteralValue, String> {
+        match self.as_floats(other) {
+            Some((_, 0.0)) => Err(String::from("Division by zero")),
+            _ => self.float_operation(other, |x, y| x / y),
+        }
+    }
+
+    // Rounds toward negative infinity, so -7 ~/ 2 is -4 where Int division would give -3
+    pub fn floor_div(&self, other: &LiteralValue) -> Result<LiteralValue, String> {
+        match (self, other) {
+            (IntValue(_), IntValue(0)) => Err(String::from("Division by zero")),
+            (IntValue(x), IntValue(y)) => {
+                let quotient = x.checked_div(*y).ok_or_else(integer_overflow)?;
+                let rounded_up = x % y != 0 && (*x < 0) != (*y < 0);
+
+                Ok(IntValue(if rounded_up { quotient - 1 } else { quotient }))
+            },
+            _ => match self.as_floats(other) {
+                Some((_, 0.0)) => Err(String::from("Division by zero")),
+                _ => self.float_operation(other, |x, y| (x / y).floor()),
+            },
+        }
+    }
+
+    pub fn rem(&self, other: &LiteralValue) -> Result<LiteralValue, String> {
+        match (self, other) {
+            (IntValue(_), IntValue(0)) => Err(String::from("Division by zero")),
+            (IntValue(x), IntValue(y)) => x.checked_rem(*y).map(IntValue).ok_or_else(integer_overflow),
+            (_, FloatValue(y)) if *y == 0.0 => Err(String::from("Division by zero")),
+            (FloatValue(_), IntValue(0)) => Err(String::from("Division by zero")),
+            _ => self.float_operation(other, |x, y| x % y),
+        }
+    }
+
+    // A negative Int exponent gives a Float. Zero to a negative power divides by zero
+    pub fn pow(&self, other: &LiteralValue) -> Result<LiteralValue, String> {
+        match (self, other) {
+            _ if self.as_floats(other).is_some_and(|(x, y)| x == 0.0 && y < 0.0) => Err(String::from("Division by zero")),
+            (IntValue(x), IntValue(y)) if *y >= 0 => u32::try_from(*y).ok()
+                .and_then(|y| x.checked_pow(y))
+                .map(IntValue)
+                .ok_or_else(integer_overflow),
+            _ => self.float_operation(other, f64::powf),
+        }
+    }
+
+    fn float_operation(&self, other: &LiteralValue, operation: fn(f64, f64) -> f64) -> Result<LiteralValue, String> {
+        match self.as_floats(other) {
+            Some((x, y)) => Ok(FloatValue(operation(x, y))),
+            None => Err(String::from("Operands must be numbers")),
+        }
+    }
+}
+
+const INTEGER_OVERFLOW: &str = "Integer overflow";
+
+type Arithmetic = fn(&LiteralValue, &LiteralValue) -> Result<LiteralValue, String>;
+
+fn integer_overflow() -> String {
+    String::from(INTEGER_OVERFLOW)
+}
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Expr {
+    Binary {
+        left: Box<Expr>,
+        operator: Token,
+        right: Box<Expr>,
+        span: Span
+    },
+    // `a < b <= c`, two or more comparisons with each operand between two of them shared by both
+    Chain {
+        operands: Vec<Expr>,
+        operators: Vec<Token>,
+        span: Span
+    },
+    Grouping {
+        expression: Box<Expr>,
+        span: Span
+    },
+    Call {
+        callee: Box<Expr>,
+        paren: Token,
+        arguments: Vec<Expr>,
+        span: Span
+    },
+    Literal {
+        value: LiteralValue,
+        span: Span
+    },
+    Logical {
+        left: Box<Expr>,
+        operator: Token,
+        right: Box<Expr>,
+        span: Span
+    },
+    Unary {
+        operator: Token,
+        right: Box<Expr>,
+        span: Span
+    },
+    Variable {
+        name: Token,
+        depth: Depth,
+        span: Span
+    },
+    Assign {
+        name: Token,
+        depth: Depth,
+        value: Box<Expr>,
+        span: Span
+    },
+    Interpolation {
+        parts: Vec<Expr>,
+        span: Span
+    },
+    Array {
+        elements: Vec<Expr>,
+        span: Span
+    },
+    // `...inner` as an array element or call argument, only the parser puts it there
+    Spread {
+        ellipsis: Token,
+        inner: Box<Expr>,
+        span: Span
+    },
+    Tuple {
+        elements: Vec<Expr>,
+        span: Span
+    },
+    Map {
+        brace: Token,
+        entries: Vec<(Expr, Expr)>,
+        span: Span
+    },
+    Index {
+        object: Box<Expr>,
+        bracket: Token,
+        index: Box<Expr>,
+        span: Span
+    },
+    // `operator` is the `+` of `a[i] += 1`, the element is combined with `value` before it is stored
+    IndexSet {
+        object: Box<Expr>,
+        bracket: Token,
+        index: Box<Expr>,
+        operator: Option<Token>,
+        value: Box<Expr>,
+        span: Span
+    },
+    // `a[start:end]`, an omitted bound is the start or the end of the object
+    Slice {
+        object: Box<Expr>,
+        bracket: Token,
+        start: Option<Box<Expr>>,
+        end: Option<Box<Expr>>,
+        span: Span
+    },
+    Ternary {
+        condition: Box<Expr>,
+        then_expr: Box<Expr>,
+        else_expr: Box<Expr>,
+        span: Span
+    },
+    Get {
+        object: Box<Expr>,
+        name: Token,
+        span: Span
+    },
+    // `operator` is set for a compound assignment like `a.n += 1`, as on IndexSet
+    Set {
+        object: Box<Expr>,
+        name: Token,
+        operator: Option<Token>,
+        value: Box<Expr>,
+        span: Span
+    },
+    Self_ {
+        keyword: Token,
+        span: Span
+    },
+    Super {
+        keyword: Token,
+        method: Token,
+        span: Span
+    },
+    // `(a, b) = value`, `equals` is kept for the line of an arity mismatch
+    Destructure {
+        pattern: Pattern,
+        equals: Token,
+        value: Box<Expr>,
+        span: Span
+    },
+    // `keyword` is the 'fn' token
+    Lambda {
+        keyword: Token,
+        params: Vec<Token>,
+        rest: Option<Token>,
+        body: Vec<Stmt>,
+        span: Span
+    },
+}
+
+// One method per Expr variant, so a pass over the tree does not have to match on Expr itself
+pub trait ExprVisitor<T> {
+    fn visit_binary(&mut self, left: &Expr, operator: &Token, right: &Expr) -> T;
+    fn visit_chain(&mut self, operands: &[Expr], operators: &[Token]) -> T;
+    fn visit_grouping(&mut self, expression: &Expr) -> T;
+    fn visit_tuple(&mut self, elements: &[Expr]) -> T;
+    fn visit_destructure(&mut self, pattern: &Pattern, equals: &Token, value: &Expr) -> T;
+    fn visit_call(&mut self, callee: &Expr, paren: &Token, arguments: &[Expr]) -> T;
+    fn visit_literal(&mut self, value: &LiteralValue) -> T;
+    fn visit_logical(&mut self, left: &Expr, operator: &Token, right: &Expr) -> T;
+    fn visit_unary(&mut self, operator: &Token, right: &Expr) -> T;
+    fn visit_variable(&mut self, name: &Token, depth: &Depth) -> T;
+    fn visit_assign(&mut self, name: &Token, depth: &Depth, value: &Expr) -> T;
+    fn visit_interpolation(&mut self, parts: &[Expr]) -> T;
+    fn visit_array(&mut self, elements: &[Expr]) -> T;
+    fn visit_spread(&mut self, ellipsis: &Token, inner: &Expr) -> T;
+    fn visit_map(&mut self, brace: &Token, entries: &[(Expr, Expr)]) -> T;
+    fn visit_index(&mut self, object: &Expr, bracket: &Token, index: &Expr) -> T;
+    fn visit_index_set(&mut self, object: &Expr, bracket: &Token, index: &Expr, operator: Option<&Token>, value: &Expr) -> T;
+    fn visit_slice(&mut self, object: &Expr, bracket: &Token, start: Option<&Expr>, end: Option<&Expr>) -> T;
+    fn visit_ternary(&mut self, condition: &Expr, then_expr: &Expr, else_expr: &Expr) -> T;
+    fn visit_get(&mut self, object: &Expr, name: &Token) -> T;
+    fn visit_set(&mut self, object: &Expr, name: &Token, operator: Option<&Token>, value: &Expr) -> T;
+    fn visit_self(&mut self, keyword: &Token) -> T;
+    fn visit_super(&mut self, keyword: &Token, method: &Token) -> T;
+    fn visit_lambda(&mut self, keyword: &Token, params: &[Token], rest: Option<&Token>, body: &[Stmt]) -> T;
+}
+
+// The names on the left of a destructuring `let` or assignment, tuples can nest
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Pattern {
+    Name(Token, Depth),
+    Tuple(Vec<Pattern>),
+}
+
+impl Pattern {
+    pub fn to_string(&self) -> String {
+        match self {
+            Pattern::Name(name, _) => name.lexeme.clone(),
+            Pattern::Tuple(elements) => format!(
+                "({})",
+                elements.iter().map(|element| element.to_string()).collect::<Vec<String>>().join(" ")
+            ),
+        }
+    }
+
+    // Calls `bind` for every name with its part of `value`, which must be a Tuple or Array of the same length
+    pub fn destructure(
+        &self,
+        value: LiteralValue,
+        equals: &Token,
+        bind: &mut dyn FnMut(&Token, &Depth, LiteralValue) -> Result<(), CorrodeError>
+    ) -> Result<(), CorrodeError> {
+        let elements = match self {
+            Pattern::Name(name, depth) => return bind(name, depth, value),
+            Pattern::Tuple(elements) => elements,
+        };
+
+        let values = match value {
+            Tuple(values) => values.as_ref().clone(),
+            Array(values) => values.borrow().clone(),
+            other => return Err(CorrodeError::runtime(
+                equals.line_number,
+                format!("Can only destructure Tuples and Arrays, got {}", other.to_type())
+            ).with_column(equals.column)),
+        };
+        if values.len() != elements.len() {
+            return Err(CorrodeError::runtime(equals.line_number, format!(
+                "Expected {} values to destructure but got {}",
+                elements.len(),
+                values.len()
+            )).with_column(equals.column));
+        }
+
+        for (element, value) in elements.iter().zip(values) {
+            element.destructure(value, equals, bind)?;
+        }
+
+        Ok(())
+    }
+}
+
+// Produces the compact S-expression form returned by to_string
+pub struct AstPrinter;
+
+// The rest parameter is written back with its `...`
+pub fn params_to_string(params: &[Token], rest: Option<&Token>, separator: &str) -> String {
+    params.iter()
+        .map(|param| param.lexeme.clone())
+        .chain(rest.map(|rest| format!("...{}", rest.lexeme)))
+        .collect::<Vec<String>>()
+        .join(separator)
+}
+
+impl AstPrinter {
+    pub fn join(&mut self, exprs: &[Expr]) -> String {
+        exprs.iter().map(|expr| expr.accept(self)).collect::<Vec<String>>().join(" ")
+    }
+}
+
+// The `+= ` of a compound Set or IndexSet, nothing for a plain one
+fn compound_operator(operator: Option<&Token>) -> String {
+    operator.map(|operator| format!("{}= ", operator.lexeme)).unwrap_or_default()
+}
+
+impl ExprVisitor<String> for AstPrinter {
+    fn visit_binary(&mut self, left: &Expr, operator: &Token, right: &Expr) -> String {
+        format!("({} {} {})", operator.lexeme, left.accept(self), right.accept(self))
+    }
+
+    fn visit_chain(&mut self, operands: &[Expr], operators: &[Token]) -> String {
+        let mut result = format!("(chain {}", operands[0].accept(self));
+        for (operator, operand) in operators.iter().zip(&operands[1..]) {
+            result.push_str(&format!(" {} {}", operator.lexeme, operand.accept(self)));
+        }
+        result + ")"
+    }
+
+    fn visit_grouping(&mut self, expression: &Expr) -> String {
+        format!("(group {})", expression.accept(self))
+    }
+
+    fn visit_tuple(&mut self, elements: &[Expr]) -> String {
+        format!("(tuple {})", self.join(elements))
+    }
+
+    fn visit_destructure(&mut self, pattern: &Pattern, _equals: &Token, value: &Expr) -> String {
+        format!("(= {} {})", pattern.to_string(), value.accept(self))
+    }
+
+    fn visit_call(&mut self, callee: &Expr, _paren: &Token, arguments: &[Expr]) -> String {
+        format!(
+            "({} [{}])",
+            callee.accept(self),
+            arguments.iter().map(|arg| arg.accept(self)).collect::<Vec<String>>().join(", ")
+        )
+    }
+
+    fn visit_literal(&mut self, value: &LiteralValue) -> String {
+        value.to_string()
+    }
+
+    fn visit_logical(&mut self, left: &Expr, operator: &Token, right: &Expr) -> String {
+        format!("({} {} {})", operator.lexeme, left.accept(self), right.accept(self))
+    }
+
+    fn visit_unary(&mut self, operator: &Token, right: &Expr) -> String {
+        format!("({} {})", operator.lexeme, right.accept(self))
+    }
+
+    fn visit_variable(&mut self, name: &Token, _depth: &Depth) -> String {
+        format!("var {}", name.lexeme)
+    }
+
+    fn visit_assign(&mut self, name: &Token, _depth: &Depth, value: &Expr) -> String {
+        format!("{} = {}", name.lexeme, value.accept(self))
+    }
+
+    fn visit_interpolation(&mut self, parts: &[Expr]) -> String {
+        format!("(interpolate {})", self.join(parts))
+    }
+
+    fn visit_array(&mut self, elements: &[Expr]) -> String {
+        format!("(array {})", self.join(elements))
+    }
+
+    fn visit_spread(&mut self, _ellipsis: &Token, inner: &Expr) -> String {
+        format!("(... {})", inner.accept(self))
+    }
+
+    fn visit_map(&mut self, _brace: &Token, entries: &[(Expr, Expr)]) -> String {
+        format!(
+            "(map {})",
+            entries.iter().map(|(key, value)| format!("({} {})", key.accept(self), value.accept(self))).collect::<Vec<String>>().join(" ")
+        )
+    }
+
+    fn visit_index(&mut self, object: &Expr, _bracket: &Token, index: &Expr) -> String {
+        format!("(index {} {})", object.accept(self), index.accept(self))
+    }
+
+    fn visit_index_set(&mut self, object: &Expr, _bracket: &Token, index: &Expr, operator: Option<&Token>, value: &Expr) -> String {
+        format!("(index-set {} {} {}{})", object.accept(self), index.accept(self), compound_operator(operator), value.accept(self))
+    }
+
+    fn visit_slice(&mut self, object: &Expr, _bracket: &Token, start: Option<&Expr>, end: Option<&Expr>) -> String {
+        let start = start.map(|start| start.accept(self)).unwrap_or_default();
+        let end = end.map(|end| end.accept(self)).unwrap_or_default();
+
+        format!("(slice {} {}:{})", object.accept(self), start, end)
+    }
+
+    fn visit_ternary(&mut self, condition: &Expr, then_expr: &Expr, else_expr: &Expr) -> String {
+        format!("(? {} {} {})", condition.accept(self), then_expr.accept(self), else_expr.accept(self))
+    }
+
+    fn visit_get(&mut self, object: &Expr, name: &Token) -> String {
+        format!("(get {} {})", object.accept(self), name.lexeme)
+    }
+
+    fn visit_set(&mut self, object: &Expr, name: &Token, operator: Option<&Token>, value: &Expr) -> String {
+        format!("(set {} {} {}{})", object.accept(self), name.lexeme, compound_operator(operator), value.accept(self))
+    }
+
+    fn visit_self(&mut self, _keyword: &Token) -> String {
+        String::from("self")
+    }
+
+    fn visit_super(&mut self, _keyword: &Token, method: &Token) -> String {
+        format!("(super {})", method.lexeme)
+    }
+
+    fn visit_lambda(&mut self, _keyword: &Token, params: &[Token], rest: Option<&Token>, body: &[Stmt]) -> String {
+        format!(
+            "(fn ({}) {})",
+            params_to_string(params, rest, " "),
+            body.iter().map(|stmt| stmt.accept(self)).collect::<String>()
+        )
+    }
+}
+
+impl std::fmt::Debug for Expr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_string())
+    }
+}
+
+// The result of a checked Int operation, or what the interpreter's overflow mode makes of a failed one
+fn checked_int(
+    config: &Config,
+    operator: &Token,
+    result: Option<i64>,
+    promoted: f64
+) -> Result<LiteralValue, CorrodeError> {
+    match (result, config.integer_overflow.get()) {
+        (Some(value), _) => Ok(LiteralValue::IntValue(value)),
+        (None, IntegerOverflow::Promote) => Ok(LiteralValue::FloatValue(promoted)),
+        (None, IntegerOverflow::Error) => Err(CorrodeError::runtime(operator.line_number, String::from("Integer overflow")).with_column(operator.column)),
+    }
+}
+
+// Applies one of the comparison operators, shared by a Binary comparison and each link of a Chain
+fn compare(left: &LiteralValue, operator: &Token, right: &LiteralValue) -> Result<bool, CorrodeError> {
+    let accept = match operator.token_type {
+        TokenType::Greater => Ordering::is_gt,
+        TokenType::GreaterEqual => Ordering::is_ge,
+        TokenType::Less => Ordering::is_lt,
+        _ => Ordering::is_le,
+    };
+
+    left.compare(right)
+        .map(accept)
+        .map_err(|msg| CorrodeError::runtime(operator.line_number, msg).with_column(operator.column))
+}
+
+// The "op:" line of a compound Set or IndexSet, nothing for a plain one
+fn pretty_operator(operator: Option<&Token>, indent: usize) -> String {
+    operator.map(|operator| format!("\n{}op: {}", " ".repeat(indent + 2), operator.lexeme)).unwrap_or_default()
+}
+
+// Applies a binary operator to its evaluated operands, shared by Binary and compound assignments
+fn binary(config: &Config, left: &LiteralValue, operator: &Token, right: &LiteralValue) -> Result<LiteralValue, CorrodeError> {
+    let arithmetic: Option<Arithmetic> = match operator.token_type {
+        TokenType::Plus => Some(LiteralValue::add),
+        TokenType::Minus => Some(LiteralValue::sub),
+        TokenType::Star => Some(LiteralValue::mul),
+        TokenType::Slash => Some(LiteralValue::div),
+        TokenType::Percent => Some(LiteralValue::rem),
+        TokenType::TildeSlash => Some(LiteralValue::floor_div),
+        TokenType::StarStar => Some(LiteralValue::pow),
+        _ => None,
+    };
+    match operator.token_type {
+        TokenType::EqualEqual => return Ok(LiteralValue::from_bool(left.equals(right))),
+        TokenType::BangEqual => return Ok(LiteralValue::from_bool(!left.equals(right))),
+        TokenType::Greater | TokenType::GreaterEqual | TokenType::Less | TokenType::LessEqual =>
+            return compare(left, operator, right).map(LiteralValue::from_bool),
+        _ => (),
+    }
+    if let Some(apply) = arithmetic {
+        let promote = config.integer_overflow.get() == IntegerOverflow::Promote;
+        return match apply(left, right) {
+            Err(msg) if msg == INTEGER_OVERFLOW && promote => apply(&left.promoted(), &right.promoted()),
+            result => result,
+        }.map_err(|msg| CorrodeError::runtime(operator.line_number, msg).with_column(operator.column));
+    }
+
+    match (left, operator.token_type, right) {
+        (IntValue(x), TokenType::Ampersand, IntValue(y)) => Ok(IntValue(x & y)),
+        (IntValue(x), TokenType::Pipe, IntValue(y)) => Ok(IntValue(x | y)),
+        (IntValue(x), TokenType::Caret, IntValue(y)) => Ok(IntValue(x ^ y)),
+        (IntValue(_), TokenType::LessLess | TokenType::GreaterGreater, IntValue(y)) if *y < 0 => Err(CorrodeError::runtime(operator.line_number, String::from("Shift amount must not be negative")).with_column(operator.column)),
+        (IntValue(_), TokenType::LessLess | TokenType::GreaterGreater, IntValue(y)) if *y >= 64 => Err(CorrodeError::runtime(operator.line_number, String::from("Shift amount must be less than 64")).with_column(operator.column)),
+        (IntValue(x), TokenType::LessLess, IntValue(y)) => Ok(IntValue(x << y)),
+        (IntValue(x), TokenType::GreaterGreater, IntValue(y)) => Ok(IntValue(x >> y)),
+
+        (_, TokenType::Ampersand | TokenType::Pipe | TokenType::Caret | TokenType::LessLess | TokenType::GreaterGreater, _) => Err(CorrodeError::runtime(operator.line_number, String::from("Operands must be integers")).with_column(operator.column)),
+
+        (a, token_type, b) => Err(CorrodeError::runtime(operator.line_number, format!("Binary operator {} cannot be applied for operands {:?}, {:?}", token_type, a, b)).with_column(operator.column)),
+    }
+}
+
+// The value an IndexSet or Set stores. A compound one reads the `current` value first and combines it with
+// `value`, so the object and index are evaluated only once
+fn assigned_value(
+    value: &Expr,
+    operator: Option<&Token>,
+    current: impl FnOnce() -> Result<LiteralValue, CorrodeError>,
+    environment: &Rc<RefCell<Environment>>,
+    config: &Rc<Config>
+) -> Result<LiteralValue, CorrodeError> {
+    let current = operator.map(|_| current()).transpose()?;
+    let value = value.evaluate(environment.clone(), config)?;
+
+    match (operator, current) {
+        (Some(operator), Some(current)) => binary(config, &current, operator, &value),
+        _ => Ok(value),
+    }
+}
+
+// A missing key reads as null unless the interpreter is set to MissingKey::Error
+fn map_get(entries: &HashMap<MapKey, LiteralValue>, key: &MapKey, bracket: &Token, config: &Config) -> Result<LiteralValue, CorrodeError> {
+    match (entries.get(key), config.missing_key.get()) {
+        (Some(value), _) => Ok(value.clone()),
+        (None, MissingKey::Null) => Ok(Null),
+        (None, MissingKey::Error) => Err(CorrodeError::runtime(
+            bracket.line_number,
+            format!("Map has no key '{}'", key.to_string())
+        ).with_column(bracket.column)),
+    }
+}
+
+// Renders a labelled list of already pretty printed children, one "- item" per line
+pub fn pretty_list(label: &str, items: Vec<String>, indent: usize) -> String {
+    let pad = " ".repeat(indent + 2);
+    if items.is_empty() {
+        return format!("\n{}{}: []", pad, label);
+    }
+
+    let item_pad = " ".repeat(indent + 4);
+    format!(
+        "\n{}{}:{}",
+        pad,
+        label,
+        items.iter().map(|item| format!("\n{}- {}", item_pad, item)).collect::<String>()
+    )
+}
+
+impl Expr {
+    // Multi-line tree dump for debugging. The first line is not indented so it can follow a label,
+    // children are indented by `indent` + 2
+    pub fn pretty(&self, indent: usize) -> String {
+        let pad = " ".repeat(indent + 2);
+        let child = indent + 2;
+
+        match self {
+            Expr::Binary { left, operator, right, .. } => format!(
+                "Binary\n{pad}op: {}\n{pad}left: {}\n{pad}right: {}",
+                operator.lexeme,
+                left.pretty(child),
+                right.pretty(child)
+            ),
+            Expr::Chain { operands, operators, .. } => format!(
+                "Chain\n{pad}ops: {}{}",
+                operators.iter().map(|operator| operator.lexeme.as_str()).collect::<Vec<&str>>().join(" "),
+                pretty_list("operands", operands.iter().map(|operand| operand.pretty(child + 2)).collect(), indent)
+            ),
+            Expr::Logical { left, operator, right, .. } => format!(
+                "Logical\n{pad}op: {}\n{pad}left: {}\n{pad}right: {}",
+                operator.lexeme,
+                left.pretty(child),
+                right.pretty(child)
+            ),
+            Expr::Grouping { expression, .. } => format!("Grouping\n{pad}expression: {}", expression.pretty(child)),
+            Expr::Call { callee, paren: _, arguments, .. } => format!(
+                "Call\n{pad}callee: {}{}",
+                callee.pretty(child),
+                pretty_list("arguments", arguments.iter().map(|arg| arg.pretty(child + 2)).collect(), indent)
+            ),
+            Expr::Literal { value, .. } => match value {
+                StringValue(s) => format!("Literal {:?}", s),
+                value => format!("Literal {}", value.to_string()),
+            },
+            Expr::Unary { operator, right, .. } => format!(
+                "Unary\n{pad}op: {}\n{pad}right: {}",
+                operator.lexeme,
+                right.pretty(child)
+            ),
+            Expr::Variable { name, depth: _, .. } => format!("Variable {}", name.lexeme),
+            Expr::Assign { name, depth: _, value, .. } => format!(
+                "Assign\n{pad}name: {}\n{pad}value: {}",
+                name.lexeme,
+                value.pretty(child)
+            ),
+            Expr::Interpolation { parts, .. } => format!(
+                "Interpolation{}",
+                pretty_list("parts", parts.iter().map(|part| part.pretty(child + 2)).collect(), indent)
+            ),
+            Expr::Array { elements, .. } => format!(
+                "Array{}",
+                pretty_list("elements", elements.iter().map(|element| element.pretty(child + 2)).collect(), indent)
+            ),
+            Expr::Spread { ellipsis: _, inner, .. } => format!("Spread\n{pad}inner: {}", inner.pretty(child)),
+            Expr::Tuple { elements, .. } => format!(
+                "Tuple{}",
+                pretty_list("elements", elements.iter().map(|element| element.pretty(child + 2)).collect(), indent)
+            ),
+            Expr::Destructure { pattern, equals: _, value, .. } => format!(
+                "Destructure\n{pad}pattern: {}\n{pad}value: {}",
+                pattern.to_string(),
+                value.pretty(child)
+            ),
+            Expr::Map { brace: _, entries, .. } => format!(
+                "Map{}",
+                pretty_list("entries", entries.iter().map(|(key, value)| format!(
+                    "Entry\n{pad}key: {}\n{pad}value: {}",
+                    key.pretty(child + 4),
+                    value.pretty(child + 4),
+                    pad = " ".repeat(child + 4)
+                )).collect(), indent)
+            ),
+            Expr::Index { object, bracket: _, index, .. } => format!(
+                "Index\n{pad}object: {}\n{pad}index: {}",
+                object.pretty(child),
+                index.pretty(child)
+            ),
+            Expr::IndexSet { object, bracket: _, index, operator, value, .. } => format!(
+                "IndexSet\n{pad}object: {}\n{pad}index: {}{}\n{pad}value: {}",
+                object.pretty(child),
+                index.pretty(child),
+                pretty_operator(operator.as_ref(), indent),
+                value.pretty(child)
+            ),
+            Expr::Slice { object, bracket: _, start, end, .. } => format!(
+                "Slice\n{pad}object: {}{}{}",
+                object.pretty(child),
+                start.as_ref().map(|start| format!("\n{pad}start: {}", start.pretty(child))).unwrap_or_default(),
+                end.as_ref().map(|end| format!("\n{pad}end: {}", end.pretty(child))).unwrap_or_default()
+            ),
+            Expr::Ternary { condition, then_expr, else_expr, .. } => format!(
+                "Ternary\n{pad}condition: {}\n{pad}then: {}\n{pad}else: {}",
+                condition.pretty(child),
+                then_expr.pretty(child),
+                else_expr.pretty(child)
+            ),
+            Expr::Get { object, name, .. } => format!(
+                "Get\n{pad}object: {}\n{pad}name: {}",
+                object.pretty(child),
+                name.lexeme
+            ),
+            Expr::Set { object, name, operator, value, .. } => format!(
+                "Set\n{pad}object: {}\n{pad}name: {}{}\n{pad}value: {}",
+                object.pretty(child),
+                name.lexeme,
+                pretty_operator(operator.as_ref(), indent),
+                value.pretty(child)
+            ),
+            Expr::Self_ { keyword: _, .. } => String::from("Self"),
+            Expr::Super { keyword: _, method, .. } => format!("Super {}", method.lexeme),
+            Expr::Lambda { keyword: _, params, rest, body, .. } => format!(
+                "Lambda\n{pad}params: ({}){}",
+                params_to_string(params, rest.as_ref(), ", "),
+                pretty_list("body", body.iter().map(|stmt| stmt.pretty(child + 2)).collect(), indent)
+            ),
+        }
+    }
+
+    // The source the expression was parsed from, for tooling and diagnostics
+    pub fn span(&self) -> Span {
+        match self {
+            Expr::Binary { span, .. } |
+            Expr::Chain { span, .. } |
+            Expr::Grouping { span, .. } |
+            Expr::Call { span, .. } |
+            Expr::Literal { span, .. } |
+            Expr::Logical { span, .. } |
+            Expr::Unary { span, .. } |
+            Expr::Variable { span, .. } |
+            Expr::Assign { span, .. } |
+            Expr::Interpolation { span, .. } |
+            Expr::Array { span, .. } |
+            Expr::Spread { span, .. } |
+            Expr::Tuple { span, .. } |
+            Expr::Map { span, .. } |
+            Expr::Index { span, .. } |
+            Expr::IndexSet { span, .. } |
+            Expr::Slice { span, .. } |
+            Expr::Ternary { span, .. } |
+            Expr::Get { span, .. } |
+            Expr::Set { span, .. } |
+            Expr::Self_ { span, .. } |
+            Expr::Super { span, .. } |
+            Expr::Destructure { span, .. } |
+            Expr::Lambda { span, .. } => *span,
+        }
+    }
+
+    pub fn accept<T, V: ExprVisitor<T>>(&self, visitor: &mut V) -> T {
+        match self {
+            Expr::Binary { left, operator, right, .. } => visitor.visit_binary(left, operator, right),
+            Expr::Chain { operands, operators, .. } => visitor.visit_chain(operands, operators),
+            Expr::Grouping { expression, .. } => visitor.visit_grouping(expression),
+            Expr::Call { callee, paren, arguments, .. } => visitor.visit_call(callee, paren, arguments),
+            Expr::Literal { value, .. } => visitor.visit_literal(value),
+            Expr::Logical { left, operator, right, .. } => visitor.visit_logical(left, operator, right),
+            Expr::Unary { operator, right, .. } => visitor.visit_unary(operator, right),
+            Expr::Variable { name, depth, .. } => visitor.visit_variable(name, depth),
+            Expr::Assign { name, depth, value, .. } => visitor.visit_assign(name, depth, value),
+            Expr::Interpolation { parts, .. } => visitor.visit_interpolation(parts),
+            Expr::Array { elements, .. } => visitor.visit_array(elements),
+            Expr::Spread { ellipsis, inner, .. } => visitor.visit_spread(ellipsis, inner),
+            Expr::Tuple { elements, .. } => visitor.visit_tuple(elements),
+            Expr::Destructure { pattern, equals, value, .. } => visitor.visit_destructure(pattern, equals, value),
+            Expr::Map { brace, entries, .. } => visitor.visit_map(brace, entries),
+            Expr::Index { object, bracket, index, .. } => visitor.visit_index(object, bracket, index),
+            Expr::Slice { object, bracket, start, end, .. } => visitor.visit_slice(object, bracket, start.as_deref(), end.as_deref()),
+            Expr::IndexSet { object, bracket, index, operator, value, .. } => visitor.visit_index_set(object, bracket, index, operator.as_ref(), value),
+            Expr::Ternary { condition, then_expr, else_expr, .. } => visitor.visit_ternary(condition, then_expr, else_expr),
+            Expr::Get { object, name, .. } => visitor.visit_get(object, name),
+            Expr::Set { object, name, operator, value, .. } => visitor.visit_set(object, name, operator.as_ref(), value),
+            Expr::Self_ { keyword, .. } => visitor.visit_self(keyword),
+            Expr::Super { keyword, method, .. } => visitor.visit_super(keyword, method),
+            Expr::Lambda { keyword, params, rest, body, .. } => visitor.visit_lambda(keyword, params, rest.as_ref(), body),
+        }
+    }
+
+    pub fn to_string(&self) -> String {
+        self.accept(&mut AstPrinter)
+    }
+
+    pub fn evaluate(&self, environment: Rc<RefCell<Environment>>, config: &Rc<Config>) -> Result<LiteralValue, CorrodeError> {
+        config.steps.take()?;
+
+        match self {
+            Expr::Assign { name, depth, value, .. } => {
+                let new_value = (*value).evaluate(environment.clone(), config)?;
+                environment.borrow_mut()
+                    .assign_at(depth.get(), name.symbol(), new_value.clone())
+                    .map_err(|msg| CorrodeError::runtime(name.line_number, msg).with_column(name.column))?;
+
+                Ok(new_value)
+            },
+            // Arguments are evaluated before the arity check, a spread argument only has its length then
+            Expr::Call { callee, paren, arguments, .. } => {
+                let callable = (*callee).evaluate(environment.clone(), config)?;
+                let arg_vals = evaluate_elements(arguments, &environment, config)?;
+
+                match callable {
+                    Callable { name: _, arity, optional: _, variadic: true, fn_: _ } if arg_vals.len() < arity => {
+                        return Err(CorrodeError::runtime(paren.line_number, format!(
+                            "Expected at least {} arguments but got {}",
+                            arity,
+                            arg_vals.len()
+                        )).with_column(paren.column));
+                    },
+                    Callable { name: _, arity, optional, variadic, fn_} => {
+                        if (arg_vals.len() > arity && !variadic) || arg_vals.len() + optional < arity {
+                            let expected = if optional == 0 {
+                                arity.to_string()
+                            } else {
+                                format!("{} to {}", arity - optional, arity)
+                            };
+
+                            return Err(CorrodeError::runtime(paren.line_number, format!(
+                                "Expected {} arguments but got {}",
+                                expected,
+                                arg_vals.len()
+                            )).with_column(paren.column));
+                        }
+
+                        fn_(&arg_vals).map_err(|msg| CorrodeError::runtime(paren.line_number, msg).with_column(paren.column))
+                    },
+                    Function(function) => {
+                        function.check_arity(arg_vals.len(), paren)?;
+
+                        function.call(arg_vals)
+                    },
+                    Class(class) => {
+                        let initializer = class.find_method("init");
+                        match &initializer {
+                            Some(init) => init.check_arity(arg_vals.len(), paren)?,
+                            None if !arg_vals.is_empty() => {
+                                return Err(CorrodeError::runtime(paren.line_number, format!(
+                                    "Expected 0 arguments but got {}",
+                                    arg_vals.len()
+                                )).with_column(paren.column));
+                            },
+                            None => {},
+                        }
+
+                        let instance = Rc::new(RefCell::new(self::Instance {
+                            class: class.clone(),
+                            fields: HashMap::new(),
+                        }));
+
+                        if let Some(initializer) = initializer {
+                            initializer.bind(instance.clone()).call(arg_vals)?;
+                        }
+
+                        Ok(Instance(instance))
+                    },
+                    other => Err(CorrodeError::runtime(paren.line_number, format!("{} is not callable", other.to_string())).with_column(paren.column))
+                }
+            },
+            Expr::Variable { name, depth, .. } => {
+                environment.borrow()
+                    .get_at(depth.get(), name.symbol())
+                    .map_err(|msg| CorrodeError::runtime(name.line_number, msg).with_column(name.column))
+            },
+            Expr::Literal { value, .. } => Ok(value.clone()),
+            Expr::Interpolation { parts, .. } => {
+                let mut result = String::new();
+                for part in parts {
+                    result.push_str(&part.evaluate(environment.clone(), config)?.to_string());
+                }
+
+                Ok(StringValue(result))
+            },
+            Expr::Array { elements, .. } => Ok(Array(Rc::new(RefCell::new(evaluate_elements(elements, &environment, config)?)))),
+            Expr::Spread { ellipsis, inner: _, .. } => Err(CorrodeError::runtime(
+                ellipsis.line_number,
+                String::from("'...' can only be used in arrays and call arguments")
+            ).with_column(ellipsis.column)),
+            Expr::Tuple { elements, .. } => {
+                let mut values = Vec::new();
+                for element in elements {
+                    values.push(element.evaluate(environment.clone(), config)?);
+                }
+
+                Ok(Tuple(Rc::new(values)))
+            },
+            Expr::Destructure { pattern, equals, value, .. } => {
+                let new_value = value.evaluate(environment.clone(), config)?;
+                pattern.destructure(new_value.clone(), equals, &mut |name, depth, value| {
+                    environment.borrow_mut()
+                        .assign_at(depth.get(), name.symbol(), value)
+                        .map_err(|msg| CorrodeError::runtime(name.line_number, msg).with_column(name.column))
+                })?;
+
+                Ok(new_value)
+            },
+            Expr::Map { brace, entries, .. } => {
+                let mut map = HashMap::new();
+                for (key, value) in entries {
+                    let key_value = key.evaluate(environment.clone(), config)?;
+                    let map_key = MapKey::from_value(&key_value)
+                        .map_err(|msg| CorrodeError::runtime(brace.line_number, msg).with_column(brace.column))?;
+                    map.insert(map_key, value.evaluate(environment.clone(), config)?);
+                }
+
+                Ok(Map(Rc::new(RefCell::new(map))))
+            },
+            Expr::Logical { left, operator, right, .. } => {
+                match operator.token_type {
+                    TokenType::Or => {
+                        let lhs_value = left.evaluate(environment.clone(), config)?;
+                        if lhs_value.is_truthy() {
+                            return Ok(lhs_value);
+                        } else {
+                            return right.evaluate(environment.clone(), config);
+                        }
+                    },
+                    TokenType::And => {
+                        let lhs_value = left.evaluate(environment.clone(), config)?;
+                        if !lhs_value.is_truthy() {
+                            return Ok(lhs_value);
+                        } else {
+                            return right.evaluate(environment.clone(), config);
+                        }
+                    },
+                    TokenType::QuestionQuestion => {
+                        let lhs_value = left.evaluate(environment.clone(), config)?;
+                        if matches!(lhs_value, Null) {
+                            return right.evaluate(environment.clone(), config);
+                        } else {
+                            return Ok(lhs_value);
+                        }
+                    },
+                    token_type => return Err(CorrodeError::runtime(operator.line_number, format!("Invalid token in logical expression: {}", token_type)).with_column(operator.column)),
+                }
+            },
+            Expr::Self_ { keyword, .. } => {
+                environment.borrow()
+                    .get(Symbol::intern("self"))
+                    .map_err(|msg| CorrodeError::runtime(keyword.line_number, msg).with_column(keyword.column))
+            },
+            Expr::Super { keyword, method, .. } => {
+                let superclass = match environment.borrow().get(Symbol::intern("super")) {
+                    Ok(Class(superclass)) => superclass,
+                    _ => return Err(CorrodeError::runtime(keyword.line_number, String::from("Can not use 'super' in a class with no superclass")).with_column(keyword.column)),
+                };
+                let instance = match environment.borrow().get(Symbol::intern("self")) {
+                    Ok(Instance(instance)) => instance,
+                    _ => return Err(CorrodeError::runtime(keyword.line_number, String::from("Can not use 'super' outside of a method")).with_column(keyword.column)),
+                };
+
+                match superclass.find_method(&method.lexeme) {
+                    Some(function) => Ok(Function(Rc::new(function.bind(instance)))),
+                    None => Err(CorrodeError::runtime(method.line_number, format!("Undefined property '{}'", method.lexeme)).with_column(method.column)),
+                }
+            },
+            Expr::Lambda { keyword, params, rest, body, .. } => {
+                let mut name = keyword.clone();
+                name.lexeme = String::from("lambda");
+
+                Ok(Function(Rc::new(Function {
+                    name: name,
+                    params: params.clone(),
+                    rest: rest.clone(),
+                    body: Rc::new(body.clone()),
+                    closure: environment.clone(),
+                    is_initializer: false,
+                    config: config.clone(),
+                })))
+            },
+            Expr::Get { object, name, .. } => {
+                match object.evaluate(environment.clone(), config)? {
+                    Instance(instance) => self::Instance::get(&instance, name),
+                    _ => Err(CorrodeError::runtime(name.line_number, String::from("Only instances have properties")).with_column(name.column)),
+                }
+            },
+            Expr::Set { object, name, operator, value, .. } => {
+                match object.evaluate(environment.clone(), config)? {
+                    Instance(instance) => {
+                        let value = assigned_value(value, operator.as_ref(), || self::Instance::get(&instance, name), &environment, config)?;
+                        instance.borrow_mut().fields.insert(name.lexeme.clone(), value.clone());
+
+                        Ok(value)
+                    },
+                    _ => Err(CorrodeError::runtime(name.line_number, String::from("Only instances have fields")).with_column(name.column)),
+                }
+            },
+            Expr::Index { object, bracket, index, .. } => {
+                let object = object.evaluate(environment.clone(), config)?;
+                let index = index.evaluate(environment.clone(), config)?;
+
+                match object {
+                    Array(elements) => {
+                        let elements = elements.borrow();
+                        let index = to_index(index, elements.len(), bracket)?;
+                        Ok(elements[index].clone())
+                    },
+                    Map(entries) => {
+                        let key = MapKey::from_value(&index)
+                            .map_err(|msg| CorrodeError::runtime(bracket.line_number, msg).with_column(bracket.column))?;
+                        map_get(&entries.borrow(), &key, bracket, config)
+                    },
+                    StringValue(s) => {
+                        let index = to_index(index, s.chars().count(), bracket)?;
+                        Ok(StringValue(s.chars().nth(index).unwrap().to_string()))
+                    },
+                    other => Err(CorrodeError::runtime(bracket.line_number, format!("Can not index into {}", other.to_type())).with_column(bracket.column)),
+                }
+            },
+            Expr::Slice { object, bracket, start, end, .. } => {
+                let object = object.evaluate(environment.clone(), config)?;
+                let start = start.as_ref().map(|start| start.evaluate(environment.clone(), config)).transpose()?;
+                let end = end.as_ref().map(|end| end.evaluate(environment.clone(), config)).transpose()?;
+
+                match object {
+                    Array(elements) => {
+                        let elements = elements.borrow();
+                        let (start, end) = to_slice_range(start, end, elements.len(), bracket)?;
+                        Ok(Array(Rc::new(RefCell::new(elements[start..end].to_vec()))))
+                    },
+                    StringValue(s) => {
+                        let (start, end) = to_slice_range(start, end, s.chars().count(), bracket)?;
+                        Ok(StringValue(s.chars().skip(start).take(end - start).collect()))
+                    },
+                    other => Err(CorrodeError::runtime(bracket.line_number, format!("Can not slice {}", other.to_type())).with_column(bracket.column)),
+                }
+            },
+            Expr::IndexSet { object, bracket, index, operator, value, .. } => {
+                let object = object.evaluate(environment.clone(), config)?;
+                let index = index.evaluate(environment.clone(), config)?;
+
+                match object {
+                    Array(elements) => {
+                        let current = || {
+                            let elements = elements.borrow();
+                            Ok(elements[to_index(index.clone(), elements.len(), bracket)?].clone())
+                        };
+                        let value = assigned_value(value, operator.as_ref(), current, &environment, config)?;
+                        let mut elements = elements.borrow_mut();
+                        let index = to_index(index, elements.len(), bracket)?;
+                        elements[index] = value.clone();
+
+                        Ok(value)
+                    },
+                    Map(entries) => {
+                        let key = MapKey::from_value(&index)
+                            .map_err(|msg| CorrodeError::runtime(bracket.line_number, msg).with_column(bracket.column))?;
+                        let current = || map_get(&entries.borrow(), &key, bracket, config);
+                        let value = assigned_value(value, operator.as_ref(), current, &environment, config)?;
+                        entries.borrow_mut().insert(key, value.clone());
+
+                        Ok(value)
+                    },
+                    other => Err(CorrodeError::runtime(bracket.line_number, format!("Can not assign to an index of {}", other.to_type())).with_column(bracket.column)),
+                }
+            },
+            Expr::Ternary { condition, then_expr, else_expr, .. } => {
+                if condition.evaluate(environment.clone(), config)?.is_truthy() {
+                    then_expr.evaluate(environment.clone(), config)
+                } else {
+                    else_expr.evaluate(environment.clone(), config)
+                }
+            },
+            Expr::Grouping { expression, .. } => expression.evaluate(environment.clone(), config),
+            Expr::Unary { operator, right, .. } => {
+                let right = right.evaluate(environment.clone(), config)?;
+
+                match (&right, operator.token_type) {
+                    (IntValue(x), TokenType::Minus) => checked_int(config, operator, x.checked_neg(), -(*x as f64)),
+                    (FloatValue(x), TokenType::Minus) => Ok(FloatValue(-x)),
+                    (_, TokenType::Minus) => Err(CorrodeError::runtime(operator.line_number, format!("Minus not implemented for {}", right.to_type())).with_column(operator.column)),
+                    (any, TokenType::Bang) => Ok(LiteralValue::from_bool(!any.is_truthy())),
+                    (IntValue(x), TokenType::Tilde) => Ok(IntValue(!x)),
+                    (_, TokenType::Tilde) => Err(CorrodeError::runtime(operator.line_number, format!("Bitwise not not implemented for {}", right.to_type())).with_column(operator.column)),
+                    (_, token_type) => Err(CorrodeError::runtime(operator.line_number, format!("{} is not a valid unary operator", token_type)).with_column(operator.column))
+                }
+            },
+            // Each operand is evaluated once, and the first false comparison skips the operands after it
+            Expr::Chain { operands, operators, .. } => {
+                let mut left = operands[0].evaluate(environment.clone(), config)?;
+                for (operator, operand) in operators.iter().zip(&operands[1..]) {
+                    let right = operand.evaluate(environment.clone(), config)?;
+                    if !compare(&left, operator, &right)? {
+                        return Ok(LiteralValue::from_bool(false));
+                    }
+                    left = right;
+                }
+
+                Ok(LiteralValue::from_bool(true))
+            },
+            Expr::Binary { left, operator, right, .. } => {
+                let left = left.evaluate(environment.clone(), config)?;
+                let right = right.evaluate(environment.clone(), config)?;
+
+                binary(config, &left, operator, &right)
+            }
+        }
+    }
+
+    pub fn print(&self) {
+        println!("{}", self.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pretty_print_ast() {
+        let minus_token = Token::new(TokenType::Minus, "-", 0);
+
+        let ott = Expr::Literal { // 123
+            value: IntValue(123),
+            span: Span::default()
+        };
+
+        let group = Expr::Grouping {
+            expression: Box::from(Expr::Literal {
+                value: FloatValue(45.67),
+                span: Span::default()
+            }),
+            span: Span::default()
+        };
+
+        let mul = Token::new(TokenType::Star, "*", 0);
+
+        let ast = Expr::Binary {
+            left: Box::from(Expr::Unary {
+                operator: minus_token,
+                right: Box::from(ott),
+                span: Span::default()
+            }),
+            operator: mul,
+            right: Box::from(group),
+            span: Span::default()
+        };
+
+        let result = ast.to_string();
+        assert_eq!(result, "(* (- 123) (group 45.67))");
+    }
+
+    #[test]
+    fn float_display() {
+        let shown = [1.0, -2.0, 0.5, 0.1 + 0.2, 1.0 / 3.0, 123456.789, 1e15, 1e16, 1e300, 1e-5, 1.5e-7, 0.0, -0.0, f64::INFINITY, f64::NAN]
+            .map(|x| FloatValue(x).to_string());
+
+        assert_eq!(shown, [
+            "1", "-2", "0.5", "0.30000000000000004", "0.3333333333333333", "123456.789", "1000000000000000", "1e16",
+            "1e300", "0.00001", "1.5e-7", "0", "-0", "inf", "NaN"
+        ]);
+        assert_eq!(IntValue(1).to_string(), FloatValue(1.0).to_string());
+    }
+
+    #[test]
+    fn runtime_error_has_line() {
+        let minus_token = Token::new(TokenType::Minus, "-", 3);
+
+        let ast = Expr::Unary {
+            operator: minus_token,
+            right: Box::from(Expr::Literal {
+                value: StringValue(String::from("a")),
+                span: Span::default()
+            }),
+            span: Span::default()
+        };
+
+        let result = ast.evaluate(Rc::new(RefCell::new(Environment::new())), &Rc::new(Config::default()));
+        assert_eq!(result.err().unwrap().to_string(), "[line 3] Minus not implemented for String");
+    }
+
+    #[test]
+    fn undefined_variable_has_line() {
+        let name = Token::new(TokenType::Identifier, "x", 7);
+
+        let ast = Expr::Variable { name, depth: Depth::default(), span: Span::default() };
+
+        let result = ast.evaluate(Rc::new(RefCell::new(Environment::new())), &Rc::new(Config::default()));
+        assert_eq!(result.err().unwrap().to_string(), "[line 7] Undefined variable 'x'");
+    }
+
+    #[test]
+    fn division_by_zero() {
+        let slash = Token::new(TokenType::Slash, "/", 2);
+
+        let ast = Expr::Binary {
+            left: Box::from(Expr::Literal { value: IntValue(1), span: Span::default() }),
+            operator: slash,
+            right: Box::from(Expr::Literal { value: IntValue(0), span: Span::default() }),
+            span: Span::default()
+        };
+
+        let result = ast.evaluate(Rc::new(RefCell::new(Environment::new())), &Rc::new(Config::default()));
+        assert_eq!(result.err().unwrap().to_string(), "[line 2] Division by zero");
+    }
+
+    fn binary(left: LiteralValue, token_type: TokenType, right: LiteralValue) -> Result<LiteralValue, CorrodeError> {
+        let operator = Token::new(token_type, &token_type.to_string(), 1);
+
+        let ast = Expr::Binary {
+            left: Box::from(Expr::Literal { value: left, span: Span::default() }),
+            operator: operator,
+            right: Box::from(Expr::Literal { value: right, span: Span::default() }),
+            span: Span::default()
+        };
+
+        ast.evaluate(Rc::new(RefCell::new(Environment::new())), &Rc::new(Config::default()))
+    }
+
+    #[test]
+    fn arithmetic_promotes_mixed_numbers() {
+        assert_eq!(IntValue(2).add(&IntValue(3)), Ok(IntValue(5)));
+        assert_eq!(IntValue(2).add(&FloatValue(0.5)), Ok(FloatValue(2.5)));
+        assert_eq!(FloatValue(2.5).sub(&IntValue(1)), Ok(FloatValue(1.5)));
+        assert_eq!(IntValue(4).mul(&IntValue(-3)), Ok(IntValue(-12)));
+        assert_eq!(FloatValue(1.5).mul(&FloatValue(2.0)), Ok(FloatValue(3.0)));
+        assert_eq!(IntValue(7).div(&IntValue(2)), Ok(FloatValue(3.5)));
+        assert_eq!(IntValue(7).rem(&IntValue(-2)), Ok(IntValue(1)));
+        assert_eq!(FloatValue(7.5).rem(&IntValue(2)), Ok(FloatValue(1.5)));
+        assert_eq!(IntValue(7).floor_div(&IntValue(2)), Ok(IntValue(3)));
+        assert_eq!(IntValue(-7).floor_div(&IntValue(2)), Ok(IntValue(-4)));
+        assert_eq!(IntValue(7).floor_div(&IntValue(-2)), Ok(IntValue(-4)));
+        assert_eq!(IntValue(-8).floor_div(&IntValue(2)), Ok(IntValue(-4)));
+        assert_eq!(FloatValue(-7.5).floor_div(&IntValue(2)), Ok(FloatValue(-4.0)));
+        assert_eq!(IntValue(2).pow(&IntValue(10)), Ok(IntValue(1024)));
+        assert_eq!(IntValue(2).pow(&IntValue(-1)), Ok(FloatValue(0.5)));
+        assert_eq!(
+            StringValue(String::from("ab")).add(&StringValue(String::from("c"))),
+            Ok(StringValue(String::from("abc")))
+        );
+    }
+
+    #[test]
+    fn arithmetic_errors() {
+        assert_eq!(IntValue(i64::MAX).add(&IntValue(1)), Err(String::from(INTEGER_OVERFLOW)));
+        assert_eq!(IntValue(i64::MIN).rem(&IntValue(-1)), Err(String::from(INTEGER_OVERFLOW)));
+        assert_eq!(IntValue(1).div(&IntValue(0)), Err(String::from("Division by zero")));
+        assert_eq!(FloatValue(1.0).div(&FloatValue(0.0)), Err(String::from("Division by zero")));
+        assert_eq!(FloatValue(1.0).rem(&IntValue(0)), Err(String::from("Division by zero")));
+        assert_eq!(IntValue(1).floor_div(&IntValue(0)), Err(String::from("Division by zero")));
+        assert_eq!(FloatValue(1.0).floor_div(&FloatValue(0.0)), Err(String::from("Division by zero")));
+        assert_eq!(IntValue(i64::MIN).floor_div(&IntValue(-1)), Err(String::from(INTEGER_OVERFLOW)));
+        assert_eq!(IntValue(0).pow(&IntValue(-1)), Err(String::from("Division by zero")));
+        assert_eq!(FloatValue(0.0).pow(&FloatValue(-0.5)), Err(String::from("Division by zero")));
+        assert_eq!(StringValue(String::from("a")).add(&IntValue(1)), Err(String::from("Operands must be two numbers or two strings")));
+        assert_eq!(True.mul(&StringValue(String::from("a"))), Err(String::from("Operands must be numbers")));
+    }
+
+    #[test]
+    fn equality_across_types() {
+        let array = |elements: Vec<LiteralValue>| Array(Rc::new(RefCell::new(elements)));
+        let map = |key: &str, value: LiteralValue| Map(Rc::new(RefCell::new(HashMap::from([(MapKey::String(key.to_string()), value)]))));
+
+        assert_eq!(binary(Null, TokenType::EqualEqual, Null), Ok(True));
+        assert_eq!(binary(Null, TokenType::EqualEqual, IntValue(0)), Ok(False));
+        assert_eq!(binary(Null, TokenType::EqualEqual, False), Ok(False));
+        assert_eq!(binary(IntValue(1), TokenType::EqualEqual, FloatValue(1.0)), Ok(True));
+        assert_eq!(binary(StringValue(String::from("1")), TokenType::EqualEqual, IntValue(1)), Ok(False));
+        assert_eq!(binary(StringValue(String::from("1")), TokenType::BangEqual, IntValue(1)), Ok(True));
+        assert_eq!(binary(array(vec![IntValue(1), Null]), TokenType::EqualEqual, array(vec![FloatValue(1.0), Null])), Ok(True));
+        assert_eq!(binary(array(vec![IntValue(1)]), TokenType::EqualEqual, array(vec![IntValue(1), IntValue(2)])), Ok(False));
+        assert_eq!(binary(map("a", IntValue(1)), TokenType::EqualEqual, map("a", IntValue(1))), Ok(True));
+        assert_eq!(binary(map("a", IntValue(1)), TokenType::BangEqual, map("b", IntValue(1))), Ok(True));
+        assert!(!FloatValue(f64::NAN).equals(&FloatValue(f64::NAN)));
+    }
+
+    #[test]
+    fn bitwise_operators() {
+        assert_eq!(binary(IntValue(6), TokenType::Ampersand, IntValue(3)), Ok(IntValue(2)));
+        assert_eq!(binary(IntValue(6), TokenType::Pipe, IntValue(3)), Ok(IntValue(7)));
+        assert_eq!(binary(IntValue(6), TokenType::Caret, IntValue(3)), Ok(IntValue(5)));
+        assert_eq!(binary(IntValue(1), TokenType::LessLess, IntValue(4)), Ok(IntValue(16)));
+        assert_eq!(binary(IntValue(-16), TokenType::GreaterGreater, IntValue(2)), Ok(IntValue(-4)));
+    }
+
+    #[test]
+    fn bitwise_errors() {
+        assert_eq!(
+            binary(IntValue(1), TokenType::LessLess, IntValue(-1)),
+            Err(CorrodeError::runtime(1, String::from("Shift amount must not be negative")))
+        );
+        assert_eq!(
+            binary(IntValue(1), TokenType::Ampersand, FloatValue(1.0)),
+            Err(CorrodeError::runtime(1, String::from("Operands must be integers")))
+        );
+    }
+
+    #[test]
+    fn compare_numbers() {
+        assert_eq!(binary(IntValue(1), TokenType::Less, IntValue(2)), Ok(True));
+        assert_eq!(binary(IntValue(1), TokenType::Less, FloatValue(1.5)), Ok(True));
+        assert_eq!(binary(FloatValue(2.5), TokenType::GreaterEqual, IntValue(3)), Ok(False));
+        assert_eq!(binary(StringValue(String::from("a")), TokenType::Less, StringValue(String::from("b"))), Ok(True));
+    }
+
+    #[test]
+    fn compare_orders_values() {
+        assert_eq!(IntValue(1).compare(&FloatValue(1.5)), Ok(Ordering::Less));
+        assert_eq!(FloatValue(2.0).compare(&IntValue(2)), Ok(Ordering::Equal));
+        assert_eq!(IntValue(i64::MAX).compare(&IntValue(i64::MAX - 1)), Ok(Ordering::Greater));
+        assert_eq!(StringValue(String::from("ab")).compare(&StringValue(String::from("b"))), Ok(Ordering::Less));
+        assert_eq!(StringValue(String::from("1")).compare(&IntValue(1)), Err(String::from("Operands must be numbers")));
+        assert_eq!(FloatValue(f64::NAN).compare(&IntValue(1)), Err(String::from("NaN can not be compared")));
+    }
+
+    #[test]
+    fn compare_rejects_non_numbers() {
+        let result = binary(StringValue(String::from("a")), TokenType::Less, IntValue(1));
+        assert_eq!(result.err().unwrap().to_string(), "[line 1] Operands must be numbers");
+
+        let result = binary(True, TokenType::Greater, False);
+        assert_eq!(result.err().unwrap().to_string(), "[line 1] Operands must be numbers");
+    }
+
+    #[test]
+    fn concatenate_strings() {
+        let result = binary(StringValue(String::from("foo")), TokenType::Plus, StringValue(String::from("bar")));
+        assert_eq!(result, Ok(StringValue(String::from("foobar"))));
+    }
+
+    #[test]
+    fn concatenate_rejects_mixed_types() {
+        let result = binary(StringValue(String::from("foo")), TokenType::Plus, IntValue(1));
+        assert_eq!(result.err().unwrap().to_string(), "[line 1] Operands must be two numbers or two strings");
+
+        let result = binary(Null, TokenType::Plus, StringValue(String::from("foo")));
+        assert_eq!(result.err().unwrap().to_string(), "[line 1] Operands must be two numbers or two strings");
+    }
+
+    #[test]
+    fn truthiness() {
+        assert!(!Null.is_truthy());
+        assert!(!False.is_truthy());
+        assert!(True.is_truthy());
+        assert!(IntValue(0).is_truthy());
+        assert!(FloatValue(0.0).is_truthy());
+        assert!(StringValue(String::new()).is_truthy());
+    }
+
+    #[test]
+    fn bang_uses_truthiness() {
+        let bang = Token::new(TokenType::Bang, "!", 1);
+        let environment = Rc::new(RefCell::new(Environment::new()));
+
+        let not_null = Expr::Unary {
+            operator: bang.clone(),
+            right: Box::from(Expr::Literal { value: Null, span: Span::default() }),
+            span: Span::default()
+        };
+        assert_eq!(not_null.evaluate(environment.clone(), &Rc::new(Config::default())), Ok(True));
+
+        let not_zero = Expr::Unary {
+            operator: bang,
+            right: Box::from(Expr::Literal { value: IntValue(0), span: Span::default() }),
+            span: Span::default()
+        };
+        assert_eq!(not_zero.evaluate(environment.clone(), &Rc::new(Config::default())), Ok(False));
+    }
+}
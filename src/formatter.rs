@@ -0,0 +1,480 @@
+use crate::expr::{Depth, Expr, ExprVisitor, LiteralValue, Pattern, params_to_string};
+use crate::stmt::{Binding, Stmt, StmtVisitor};
+use crate::tokenizer::{Token, TokenType};
+
+const DEFAULT_INDENT: usize = 2;
+
+// Writes a parsed program back out as source, one statement per line with blocks indented.
+// Parsing the output gives the same tree again, the parser already keeps every parenthesis as a Grouping.
+// Comments are not part of the tree, so formatting drops every `//` and `/* */` comment
+pub struct Formatter {
+    // Spaces per block level
+    indent: usize,
+    level: usize,
+}
+
+pub fn format_program(stmts: &[Stmt]) -> String {
+    Formatter::new().format(stmts)
+}
+
+impl Default for Formatter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Formatter {
+    pub fn new() -> Self {
+        Self {
+            indent: DEFAULT_INDENT,
+            level: 0,
+        }
+    }
+
+    pub fn set_indent(&mut self, indent: usize) {
+        self.indent = indent;
+    }
+
+    pub fn format(&mut self, stmts: &[Stmt]) -> String {
+        stmts.iter().map(|stmt| format!("{}{}\n", self.pad(), stmt.accept(self))).collect()
+    }
+
+    fn pad(&self) -> String {
+        " ".repeat(self.indent * self.level)
+    }
+
+    fn join(&mut self, exprs: &[Expr]) -> String {
+        exprs.iter().map(|expr| expr.accept(self)).collect::<Vec<String>>().join(", ")
+    }
+
+    // `{`, the statements one level deeper and the closing `}` at the current level
+    fn block(&mut self, stmts: &[Stmt]) -> String {
+        if stmts.is_empty() {
+            return String::from("{}");
+        }
+
+        self.level += 1;
+        let body = self.format(stmts);
+        self.level -= 1;
+
+        format!("{{\n{}{}}}", body, self.pad())
+    }
+
+    // The body of an if or a loop stays on the line of its header
+    fn body(&mut self, body: &Stmt) -> String {
+        format!(" {}", body.accept(self))
+    }
+
+    // A variable's `a += 1` is parsed as `a = a + 1` with the binary starting where the target does,
+    // written back in the short form since `a *= 1 + 2` has no parentheses to keep
+    fn compound(&mut self, target: String, start: usize, value: &Expr) -> Option<String> {
+        match value {
+            Expr::Binary { left, operator, right, span }
+                if span.start == start && left.accept(self) == target &&
+                    matches!(operator.token_type, TokenType::Plus | TokenType::Minus | TokenType::Star | TokenType::Slash) =>
+                Some(format!("{} {}= {}", target, operator.lexeme, right.accept(self))),
+            _ => None,
+        }
+    }
+
+    fn assignment(&mut self, target: String, start: usize, value: &Expr) -> String {
+        match self.compound(target.clone(), start, value) {
+            Some(compound) => compound,
+            None => format!("{} = {}", target, value.accept(self)),
+        }
+    }
+
+    // A Set or IndexSet keeps the operator of a compound assignment itself
+    fn set(&mut self, target: String, operator: Option<&Token>, value: &Expr) -> String {
+        match operator {
+            Some(operator) => format!("{} {}= {}", target, operator.lexeme, value.accept(self)),
+            None => format!("{} = {}", target, value.accept(self)),
+        }
+    }
+
+    fn pattern(pattern: &Pattern) -> String {
+        match pattern {
+            Pattern::Name(name, _) => name.lexeme.clone(),
+            Pattern::Tuple(elements) => format!(
+                "({})",
+                elements.iter().map(Self::pattern).collect::<Vec<String>>().join(", ")
+            ),
+        }
+    }
+
+    fn binding(&mut self, binding: &Binding) -> String {
+        let mut result = binding.name.lexeme.clone();
+        if let Some(annotation) = &binding.annotation {
+            result.push_str(&format!(": {}", annotation.lexeme));
+        }
+        if let Some(initializer) = &binding.initializer {
+            result.push_str(&format!(" = {}", initializer.accept(self)));
+        }
+        result
+    }
+
+    fn function(&mut self, name: &str, params: &[Token], rest: Option<&Token>, body: &[Stmt]) -> String {
+        format!("fn {}({}) {}", name, params_to_string(params, rest, ", "), self.block(body))
+    }
+}
+
+// The text of a string literal, `${` is escaped so it is not read back as interpolation
+fn escape(text: &str) -> String {
+    let mut result = String::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\n' => result.push_str("\\n"),
+            '\t' => result.push_str("\\t"),
+            '\r' => result.push_str("\\r"),
+            '\0' => result.push_str("\\0"),
+            '\\' => result.push_str("\\\\"),
+            '"' => result.push_str("\\\""),
+            '$' if chars.peek() == Some(&'{') => result.push_str("\\$"),
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+impl ExprVisitor<String> for Formatter {
+    fn visit_binary(&mut self, left: &Expr, operator: &Token, right: &Expr) -> String {
+        format!("{} {} {}", left.accept(self), operator.lexeme, right.accept(self))
+    }
+
+    fn visit_chain(&mut self, operands: &[Expr], operators: &[Token]) -> String {
+        let mut result = operands[0].accept(self);
+        for (operator, operand) in operators.iter().zip(&operands[1..]) {
+            result.push_str(&format!(" {} {}", operator.lexeme, operand.accept(self)));
+        }
+        result
+    }
+
+    fn visit_grouping(&mut self, expression: &Expr) -> String {
+        format!("({})", expression.accept(self))
+    }
+
+    fn visit_tuple(&mut self, elements: &[Expr]) -> String {
+        format!("({})", self.join(elements))
+    }
+
+    fn visit_destructure(&mut self, pattern: &Pattern, _equals: &Token, value: &Expr) -> String {
+        format!("{} = {}", Self::pattern(pattern), value.accept(self))
+    }
+
+    fn visit_call(&mut self, callee: &Expr, _paren: &Token, arguments: &[Expr]) -> String {
+        format!("{}({})", callee.accept(self), self.join(arguments))
+    }
+
+    // Floats use Rust's shortest round-tripping form, which keeps the '.0' of whole numbers
+    fn visit_literal(&mut self, value: &LiteralValue) -> String {
+        match value {
+            LiteralValue::FloatValue(x) => format!("{:?}", x),
+            LiteralValue::StringValue(text) => format!("\"{}\"", escape(text)),
+            value => value.to_string(),
+        }
+    }
+
+    fn visit_logical(&mut self, left: &Expr, operator: &Token, right: &Expr) -> String {
+        format!("{} {} {}", left.accept(self), operator.lexeme, right.accept(self))
+    }
+
+    fn visit_unary(&mut self, operator: &Token, right: &Expr) -> String {
+        format!("{}{}", operator.lexeme, right.accept(self))
+    }
+
+    fn visit_variable(&mut self, name: &Token, _depth: &Depth) -> String {
+        name.lexeme.clone()
+    }
+
+    fn visit_assign(&mut self, name: &Token, _depth: &Depth, value: &Expr) -> String {
+        self.assignment(name.lexeme.clone(), name.offset, value)
+    }
+
+    fn visit_interpolation(&mut self, parts: &[Expr]) -> String {
+        let parts = parts.iter().map(|part| match part {
+            Expr::Literal { value: LiteralValue::StringValue(text), .. } => escape(text),
+            part => format!("${{{}}}", part.accept(self)),
+        }).collect::<String>();
+
+        format!("\"{}\"", parts)
+    }
+
+    fn visit_array(&mut self, elements: &[Expr]) -> String {
+        format!("[{}]", self.join(elements))
+    }
+
+    fn visit_spread(&mut self, _ellipsis: &Token, inner: &Expr) -> String {
+        format!("...{}", inner.accept(self))
+    }
+
+    fn visit_map(&mut self, _brace: &Token, entries: &[(Expr, Expr)]) -> String {
+        let entries = entries.iter()
+            .map(|(key, value)| format!("{}: {}", key.accept(self), value.accept(self)))
+            .collect::<Vec<String>>();
+
+        format!("{{{}}}", entries.join(", "))
+    }
+
+    fn visit_index(&mut self, object: &Expr, _bracket: &Token, index: &Expr) -> String {
+        format!("{}[{}]", object.accept(self), index.accept(self))
+    }
+
+    fn visit_slice(&mut self, object: &Expr, _bracket: &Token, start: Option<&Expr>, end: Option<&Expr>) -> String {
+        let mut bound = |bound: Option<&Expr>| bound.map(|bound| bound.accept(self)).unwrap_or_default();
+        let (start, end) = (bound(start), bound(end));
+
+        format!("{}[{}:{}]", object.accept(self), start, end)
+    }
+
+    fn visit_index_set(&mut self, object: &Expr, _bracket: &Token, index: &Expr, operator: Option<&Token>, value: &Expr) -> String {
+        let target = format!("{}[{}]", object.accept(self), index.accept(self));
+        self.set(target, operator, value)
+    }
+
+    fn visit_ternary(&mut self, condition: &Expr, then_expr: &Expr, else_expr: &Expr) -> String {
+        format!("{} ? {} : {}", condition.accept(self), then_expr.accept(self), else_expr.accept(self))
+    }
+
+    fn visit_get(&mut self, object: &Expr, name: &Token) -> String {
+        format!("{}.{}", object.accept(self), name.lexeme)
+    }
+
+    fn visit_set(&mut self, object: &Expr, name: &Token, operator: Option<&Token>, value: &Expr) -> String {
+        let target = format!("{}.{}", object.accept(self), name.lexeme);
+        self.set(target, operator, value)
+    }
+
+    fn visit_self(&mut self, _keyword: &Token) -> String {
+        String::from("self")
+    }
+
+    fn visit_super(&mut self, _keyword: &Token, method: &Token) -> String {
+        format!("super.{}", method.lexeme)
+    }
+
+    // An arrow body is a single return whose keyword is the '=>'
+    fn visit_lambda(&mut self, _keyword: &Token, params: &[Token], rest: Option<&Token>, body: &[Stmt]) -> String {
+        match body {
+            [Stmt::Return { keyword, value: Some(value), .. }] if keyword.token_type == TokenType::Arrow =>
+                format!("fn({}) => {}", params_to_string(params, rest, ", "), value.accept(self)),
+            _ => self.function("", params, rest, body).replacen("fn ", "fn", 1),
+        }
+    }
+}
+
+impl StmtVisitor<String> for Formatter {
+    fn visit_expression(&mut self, expression: &Expr) -> String {
+        format!("{};", expression.accept(self))
+    }
+
+    fn visit_echo(&mut self, expressions: &[Expr]) -> String {
+        format!("echo {};", self.join(expressions))
+    }
+
+    fn visit_print(&mut self, expression: &Expr, newline: bool) -> String {
+        let keyword = if newline { "println" } else { "print" };
+        format!("{} {};", keyword, expression.accept(self))
+    }
+
+    fn visit_let(&mut self, bindings: &[Binding]) -> String {
+        let bindings = bindings.iter().map(|binding| self.binding(binding)).collect::<Vec<String>>();
+        format!("let {};", bindings.join(", "))
+    }
+
+    fn visit_let_destructure(&mut self, pattern: &Pattern, _equals: &Token, initializer: &Expr) -> String {
+        format!("let {} = {};", Self::pattern(pattern), initializer.accept(self))
+    }
+
+    fn visit_const(&mut self, name: &Token, initializer: &Expr) -> String {
+        format!("const {} = {};", name.lexeme, initializer.accept(self))
+    }
+
+    // A for loop with an initializer is parsed into a block holding it and the loop
+    fn visit_block(&mut self, statements: &[Stmt]) -> String {
+        match statements {
+            [initializer @ (Stmt::Let { .. } | Stmt::Expression { .. }), Stmt::While { condition, body, increment: Some(increment), .. }] => {
+                let initializer = initializer.accept(self);
+                format!("for ({} {}; {}){}", initializer, condition.accept(self), increment.accept(self), self.body(body))
+            },
+            statements => self.block(statements),
+        }
+    }
+
+    fn visit_if(&mut self, predicate: &Expr, then: &Stmt, otherwise: Option<&Stmt>) -> String {
+        let mut result = format!("if ({}){}", predicate.accept(self), self.body(then));
+        if let Some(otherwise) = otherwise {
+            result.push_str(&format!(" else{}", self.body(otherwise)));
+        }
+        result
+    }
+
+    fn visit_match(&mut self, subject: &Expr, arms: &[(Vec<Expr>, Stmt)], default: Option<&Stmt>) -> String {
+        self.level += 1;
+        let mut lines = Vec::new();
+        for (patterns, body) in arms {
+            lines.push(format!("{}{} =>{}\n", self.pad(), self.join(patterns), self.body(body)));
+        }
+        if let Some(default) = default {
+            lines.push(format!("{}_ =>{}\n", self.pad(), self.body(default)));
+        }
+        self.level -= 1;
+
+        format!("match ({}) {{\n{}{}}}", subject.accept(self), lines.concat(), self.pad())
+    }
+
+    fn visit_while(&mut self, condition: &Expr, body: &Stmt, increment: Option<&Expr>) -> String {
+        match increment {
+            Some(increment) => format!("for (; {}; {}){}", condition.accept(self), increment.accept(self), self.body(body)),
+            None => format!("while ({}){}", condition.accept(self), self.body(body)),
+        }
+    }
+
+    fn visit_do_while(&mut self, body: &Stmt, condition: &Expr) -> String {
+        format!("do {} while ({});", body.accept(self), condition.accept(self))
+    }
+
+    fn visit_for_in(&mut self, key: Option<&Token>, item: &Token, iterable: &Expr, body: &Stmt) -> String {
+        let names = match key {
+            Some(key) => format!("{}, {}", key.lexeme, item.lexeme),
+            None => item.lexeme.clone(),
+        };
+
+        format!("for ({} in {}){}", names, iterable.accept(self), self.body(body))
+    }
+
+    fn visit_function(&mut self, name: &Token, params: &[Token], rest: Option<&Token>, body: &[Stmt]) -> String {
+        self.function(&name.lexeme, params, rest, body)
+    }
+
+    fn visit_class(&mut self, name: &Token, superclass: Option<&Expr>, methods: &[Stmt]) -> String {
+        let header = match superclass {
+            Some(superclass) => format!("class {} < {}", name.lexeme, superclass.accept(self)),
+            None => format!("class {}", name.lexeme),
+        };
+
+        format!("{} {}", header, self.block(methods))
+    }
+
+    fn visit_return(&mut self, _keyword: &Token, value: Option<&Expr>) -> String {
+        match value {
+            Some(value) => format!("return {};", value.accept(self)),
+            None => String::from("return;"),
+        }
+    }
+
+    fn visit_break(&mut self) -> String {
+        String::from("break;")
+    }
+
+    fn visit_continue(&mut self) -> String {
+        String::from("continue;")
+    }
+
+    fn visit_assert(&mut self, _keyword: &Token, condition: &Expr, message: Option<&Expr>) -> String {
+        match message {
+            Some(message) => format!("assert {}, {};", condition.accept(self), message.accept(self)),
+            None => format!("assert {};", condition.accept(self)),
+        }
+    }
+
+    fn visit_throw(&mut self, _keyword: &Token, value: &Expr) -> String {
+        format!("throw {};", value.accept(self))
+    }
+
+    fn visit_try(&mut self, body: &Stmt, name: &Token, handler: &Stmt) -> String {
+        format!("try {} catch ({}) {}", body.accept(self), name.lexeme, handler.accept(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tokenizer::Tokenizer;
+    use crate::parser::Parser;
+
+    fn parse(source: &str) -> Vec<Stmt> {
+        let tokens = Tokenizer::new(source).tokenize().unwrap();
+        Parser::new(tokens).parse().unwrap()
+    }
+
+    fn tree(stmts: &[Stmt]) -> Vec<String> {
+        stmts.iter().map(Stmt::to_string).collect()
+    }
+
+    #[test]
+    fn format_nested_blocks() {
+        let source = "let i=0;while(i<3){if(i==1){echo \"one\";}else if (i>1) echo i  ; else{i+=1;continue;}i=i+1;}";
+
+        assert_eq!(format_program(&parse(source)), "\
+let i = 0;
+while (i < 3) {
+  if (i == 1) {
+    echo \"one\";
+  } else if (i > 1) echo i; else {
+    i += 1;
+    continue;
+  }
+  i = i + 1;
+}
+");
+    }
+
+    #[test]
+    fn format_drops_comments() {
+        let source = "// header\nlet a = 1; /* note */ echo a; // trailing";
+
+        assert_eq!(format_program(&parse(source)), "let a = 1;\necho a;\n");
+    }
+
+    #[test]
+    fn format_with_indent() {
+        let mut formatter = Formatter::new();
+        formatter.set_indent(4);
+
+        assert_eq!(formatter.format(&parse("fn f(a,...b){return a;}")), "fn f(a, ...b) {\n    return a;\n}\n");
+    }
+
+    #[test]
+    fn format_comparison_chains() {
+        assert_eq!(format_program(&parse("echo 0<x<=f(2)>1;")), "echo 0 < x <= f(2) > 1;\n");
+    }
+
+    #[test]
+    fn format_round_trips() {
+        let source = "
+            class B < A { fn init(x) { super.init(); self.x = x; } fn get() { return self.x; } }
+            let (a, (b, c)) = (1, (2.0, 1e20));
+            let s: string, t = \"q\\\"\\n\\\\ \\${x} ${a + 1}\";
+            for (let i = 0; i < 3; i += 1) { a *= 1 + 2; }
+            for (;;) break;
+            do { a -= 1; } while (a > 0);
+            print a; println(b);
+            for (k, v in {\"a\": [1, ...[2]], 2: null}) match (k) { \"a\", 1 => echo v; _ => {} }
+            let f = fn(x) => x ?? -x, g = fn() { return; };
+            try { throw f(1)[0:-1][:2]; } catch (e) { assert !e, \"msg\"; }
+            o.p[0] -= (a ? b : c) ** 2;
+            const K = ~1 << 2 || true && false;
+            echo 0 < f(1) <= 2, a > b < c >= (1 < 2 < 3) > 0, 1 < 2;
+        ";
+        let stmts = parse(source);
+        let formatted = format_program(&stmts);
+
+        assert_eq!(tree(&parse(&formatted)), tree(&stmts), "{}", formatted);
+        assert_eq!(format_program(&parse(&formatted)), formatted);
+    }
+
+    #[test]
+    fn format_round_trips_test_cases() {
+        let cases = std::fs::read_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/src/tests/cases")).unwrap();
+        for case in cases {
+            let path = case.unwrap().path();
+            let source = std::fs::read_to_string(&path).unwrap();
+            let Ok(tokens) = Tokenizer::new(&source).tokenize() else { continue };
+            let Ok(stmts) = Parser::new(tokens).parse() else { continue };
+
+            let formatted = format_program(&stmts);
+            assert_eq!(tree(&parse(&formatted)), tree(&stmts), "{}", path.display());
+        }
+    }
+}
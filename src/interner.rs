@@ -0,0 +1,67 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+// An interned name, cheap to copy, hash and compare
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Symbol(u32);
+
+// Every distinct name is stored once, symbols index into `names`
+#[derive(Default)]
+struct Interner {
+    names: Vec<Rc<str>>,
+    symbols: HashMap<Rc<str>, Symbol>,
+}
+
+thread_local! {
+    static INTERNER: RefCell<Interner> = RefCell::new(Interner::default());
+}
+
+impl Symbol {
+    pub fn intern(name: &str) -> Self {
+        INTERNER.with(|interner| {
+            let mut interner = interner.borrow_mut();
+
+            if let Some(&symbol) = interner.symbols.get(name) {
+                return symbol;
+            }
+
+            let symbol = Symbol(interner.names.len() as u32);
+            let name: Rc<str> = Rc::from(name);
+            interner.names.push(name.clone());
+            interner.symbols.insert(name, symbol);
+
+            symbol
+        })
+    }
+
+    // Resolves the symbol back to its name, used for error messages
+    pub fn name(&self) -> Rc<str> {
+        INTERNER.with(|interner| interner.borrow().names[self.0 as usize].clone())
+    }
+}
+
+impl std::fmt::Display for Symbol {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_name_same_symbol() {
+        assert_eq!(Symbol::intern("foo"), Symbol::intern("foo"));
+        assert_ne!(Symbol::intern("foo"), Symbol::intern("bar"));
+    }
+
+    #[test]
+    fn resolve_name() {
+        let symbol = Symbol::intern("foo");
+
+        assert_eq!(&*symbol.name(), "foo");
+        assert_eq!(symbol.to_string(), "foo");
+    }
+}
@@ -1,133 +1,1725 @@
-use crate::expr::LiteralValue;
-use crate::stmt::Stmt;
-use crate::environment::Environment;
-use std::rc::Rc;
-use std::cell::RefCell;
-
-pub struct Interpreter {
-    environment: Rc<RefCell<Environment>>,
-    should_break: bool,
-}
-
-fn time_impl(_args: &Vec<LiteralValue>) -> LiteralValue {
-    let now = std::time::SystemTime::now()
-        .duration_since(std::time::SystemTime::UNIX_EPOCH)
-        .expect("Could not get system time")
-        .as_millis();
-
-    LiteralValue::Number(now as f64 / 1000.0)
-}
-
-fn print_impl(args: &Vec<LiteralValue>) -> LiteralValue {
-    print!("{}", args[0].to_string());
-
-    LiteralValue::Null
-}
-
-fn println_impl(args: &Vec<LiteralValue>) -> LiteralValue {
-    println!("{}", args[0].to_string());
-
-    LiteralValue::Null
-}
-
-impl Interpreter {
-    pub fn new() -> Self {
-        let mut globals = Environment::new();
-
-        globals.define(
-            String::from("time"), LiteralValue::Callable {
-            name: "time".to_string(),
-            arity: 0,
-            fn_: Rc::new(time_impl)
-        });
-
-        globals.define(
-            String::from("print"), LiteralValue::Callable {
-            name: "print".to_string(),
-            arity: 1,
-            fn_: Rc::new(print_impl)
-        });
-
-        globals.define(
-            String::from("println"), LiteralValue::Callable {
-            name: "println".to_string(),
-            arity: 1,
-            fn_: Rc::new(println_impl)
-        });
-
-        Self {
-            environment: Rc::new(RefCell::new(globals)),
-            should_break: false
-        }
-    }
-
-    pub fn interpret(&mut self, stmts: Vec<&Stmt>) -> Result<(), String> {
-        for stmt in stmts {
-            match stmt {
-                Stmt::Expression { expression } => {
-                    expression.evaluate(self.environment.clone())?;
-                },
-                Stmt::Echo { expression } => {
-                    let value = expression.evaluate(self.environment.clone())?;
-
-                    println!("{}", value.to_string());
-                },
-                Stmt::Let { name, initializer } => {
-                    let value = initializer.evaluate(self.environment.clone())?;
-
-                    self.environment.borrow_mut().define(name.lexeme.clone(), value)
-                },
-                Stmt::Block { statements } => {
-                    let mut new_environment = Environment::new();
-                    new_environment.enclosing = Some(self.environment.clone());
-
-                    let old_environment = self.environment.clone();
-                    self.environment = Rc::new(RefCell::new(new_environment));
-                    let block_result =  self.interpret((*statements).iter().map(|b| b.as_ref()).collect());
-                    self.environment = old_environment;
-
-                    block_result?;
-                    if self.should_break {
-                        return Ok(());
-                    }
-                },
-                Stmt::If { predicate, then, otherwise } => {
-                    let truth_value = predicate.evaluate(self.environment.clone())?;
-
-                    if truth_value.is_truthy() == LiteralValue::True {
-                        let statements = vec![then.as_ref()];
-                        self.interpret(statements)?;
-                    } else if let Some(else_stmt) = otherwise {
-                        let statements = vec![else_stmt.as_ref()];
-                        self.interpret(statements)?;
-                    }
-                    if self.should_break {
-                        return Ok(());
-                    }
-                },
-                Stmt::While { condition, body } => {
-                    let mut flag = condition.evaluate(self.environment.clone())?;
-
-                    while flag.is_truthy()  == LiteralValue::True {
-                        let statements = vec![body.as_ref()];
-                        self.interpret(statements)?;
-                        if self.should_break {
-                            self.should_break = false;
-                            break;
-                        }
-                        flag = condition.evaluate(self.environment.clone())?;
-                    }
-                },
-                Stmt::Break => {
-                    self.should_break = true;
-                    return Ok(());
-                },
-            };
-            
-            if self.should_break {
-                return Ok(());
-            }
-        }
-        Ok(())
-    }
-}
\ No newline at end of file
+use crate::expr::{LiteralValue, Function, Class, MapKey};
+use crate::stmt::Stmt;
+use crate::environment::Environment;
+use crate::error::CorrodeError;
+use crate::interner::Symbol;
+use crate::tokenizer::{self, Span, Token, TokenType, Tokenizer};
+use crate::parser::Parser;
+use crate::checker::TypeChecker;
+use crate::resolver::Resolver;
+use std::rc::Rc;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+
+// Signals that unwind through nested statements until something handles them
+pub enum Unwind {
+    Error(CorrodeError),
+    Return(LiteralValue),
+    Break,
+    Continue,
+    // The value of a `throw` and its line, caught by the nearest enclosing try
+    Throw(LiteralValue, usize),
+}
+
+// A value thrown inside a function call comes back out of the expression as CorrodeError::Thrown
+impl From<CorrodeError> for Unwind {
+    fn from(error: CorrodeError) -> Self {
+        match error {
+            CorrodeError::Thrown { value, line, .. } => Unwind::Throw(value, line),
+            error => Unwind::Error(error),
+        }
+    }
+}
+
+// Shared between an interpreter, the functions it declares and its printf and input natives
+pub type Output = Rc<RefCell<Box<dyn Write>>>;
+
+// Where input() reads lines from
+pub type Input = Rc<RefCell<Box<dyn BufRead>>>;
+
+// What Int + - * ** and negation do when the result does not fit an i64
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum IntegerOverflow {
+    // A runtime error, "Integer overflow"
+    #[default]
+    Error,
+    // The operation is redone with floats
+    Promote,
+}
+
+// What reading a key a map does not have gives
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum MissingKey {
+    // Null, has() tells it apart from a stored null
+    #[default]
+    Null,
+    // A runtime error, "Map has no key ..."
+    Error,
+}
+
+// How many statements and expressions may be evaluated, so an embedded script can not run forever
+#[derive(Default)]
+pub struct StepLimit {
+    // 0 means unlimited
+    limit: Cell<usize>,
+    taken: Cell<usize>,
+}
+
+impl StepLimit {
+    pub fn take(&self) -> Result<(), CorrodeError> {
+        let taken = self.taken.get() + 1;
+        self.taken.set(taken);
+
+        if self.limit.get() != 0 && taken > self.limit.get() {
+            return Err(CorrodeError::runtime(0, String::from("Execution step limit exceeded")));
+        }
+
+        Ok(())
+    }
+}
+
+// The settings of one interpreter, shared with every function it declares so their calls run with the same ones
+pub struct Config {
+    pub integer_overflow: Cell<IntegerOverflow>,
+    pub missing_key: Cell<MissingKey>,
+    pub output: Output,
+    pub steps: StepLimit,
+}
+
+impl Config {
+    pub fn new(output: Output) -> Self {
+        Self {
+            integer_overflow: Cell::new(IntegerOverflow::default()),
+            missing_key: Cell::new(MissingKey::default()),
+            output: output,
+            steps: StepLimit::default(),
+        }
+    }
+}
+
+// Writes to stdout, for evaluating expressions without an interpreter
+impl Default for Config {
+    fn default() -> Self {
+        Self::new(Rc::new(RefCell::new(Box::new(io::stdout()))))
+    }
+}
+
+pub struct Interpreter {
+    environment: Rc<RefCell<Environment>>,
+    config: Rc<Config>,
+}
+
+// Reads stdin one line at a time and only locks it while reading, so the REPL can still use it
+struct StdinLines {
+    line: Vec<u8>,
+    consumed: usize,
+}
+
+impl io::Read for StdinLines {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let amount = available.len().min(buf.len());
+        buf[..amount].copy_from_slice(&available[..amount]);
+        self.consume(amount);
+
+        Ok(amount)
+    }
+}
+
+impl BufRead for StdinLines {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.consumed >= self.line.len() {
+            self.line.clear();
+            self.consumed = 0;
+            io::stdin().lock().read_until(b'\n', &mut self.line)?;
+        }
+
+        Ok(&self.line[self.consumed..])
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.consumed += amount;
+    }
+}
+
+// A writer whose contents stay readable after the interpreter takes ownership of it
+#[derive(Clone)]
+struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// Fills each {} of the first argument with the next of the others, {{ and }} are literal braces
+fn format_args(name: &str, args: &[LiteralValue]) -> Result<String, String> {
+    let format = string_arg(name, &args[0])?;
+    let values = &args[1..];
+    let mut result = String::new();
+    let mut used = 0;
+    let mut chars = format.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('{', Some('{')) | ('}', Some('}')) => {
+                chars.next();
+                result.push(c);
+            },
+            ('{', Some('}')) => {
+                chars.next();
+                if let Some(value) = values.get(used) {
+                    result.push_str(&value.to_string());
+                }
+                used += 1;
+            },
+            ('{', _) => return Err(format!("{}() expects '}}' after '{{' in the format string", name)),
+            ('}', _) => return Err(format!("{}() found an unmatched '}}' in the format string", name)),
+            (c, _) => result.push(c),
+        }
+    }
+
+    if used != values.len() {
+        return Err(format!("{}() has {} placeholders but got {} values", name, used, values.len()));
+    }
+
+    Ok(result)
+}
+
+// A failed write to the output is a runtime error of the statement that wrote
+fn write_error(span: &Span) -> Unwind {
+    Unwind::Error(CorrodeError::runtime(span.line, String::from("Could not write output")))
+}
+
+fn printf_impl(output: &Output, args: &[LiteralValue]) -> Result<LiteralValue, String> {
+    let text = format_args("printf", args)?;
+    write!(output.borrow_mut(), "{}", text)
+        .map_err(|_| String::from("Could not write output"))?;
+
+    Ok(LiteralValue::Null)
+}
+
+fn time_impl(_args: &[LiteralValue]) -> Result<LiteralValue, String> {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::SystemTime::UNIX_EPOCH)
+        .map_err(|_| String::from("Could not get system time"))?
+        .as_millis();
+
+    Ok(LiteralValue::FloatValue(now as f64 / 1000.0))
+}
+
+// Reads one line without its line ending, null at the end of the input
+fn input_impl(input: &Input, output: &Output, args: &[LiteralValue]) -> Result<LiteralValue, String> {
+    if let Some(prompt) = args.first() {
+        let mut output = output.borrow_mut();
+        write!(output, "{}", prompt.to_string())
+            .and_then(|_| output.flush())
+            .map_err(|_| String::from("Could not write output"))?;
+    }
+
+    let mut line = String::new();
+    let read = input.borrow_mut().read_line(&mut line)
+        .map_err(|_| String::from("Could not read input"))?;
+    if read == 0 {
+        return Ok(LiteralValue::Null);
+    }
+
+    if line.ends_with('\n') {
+        line.pop();
+        if line.ends_with('\r') {
+            line.pop();
+        }
+    }
+
+    Ok(LiteralValue::StringValue(line))
+}
+
+// Maps do not keep their insertion order, so everything that lists a map's entries sorts them by key,
+// the same order echo prints them in
+fn sorted_entries(entries: &HashMap<MapKey, LiteralValue>) -> Vec<(LiteralValue, LiteralValue)> {
+    let mut keys = entries.keys().collect::<Vec<&MapKey>>();
+    keys.sort();
+
+    keys.into_iter().map(|key| (key.to_value(), entries[key].clone())).collect()
+}
+
+fn map_arg<'a>(name: &str, value: &'a LiteralValue) -> Result<&'a Rc<RefCell<HashMap<MapKey, LiteralValue>>>, String> {
+    match value {
+        LiteralValue::Map(entries) => Ok(entries),
+        other => Err(format!("{}() expects a Map, got {}", name, other.to_type())),
+    }
+}
+
+fn keys_impl(args: &[LiteralValue]) -> Result<LiteralValue, String> {
+    let keys = sorted_entries(&map_arg("keys", &args[0])?.borrow()).into_iter().map(|(key, _)| key).collect();
+
+    Ok(LiteralValue::Array(Rc::new(RefCell::new(keys))))
+}
+
+fn values_impl(args: &[LiteralValue]) -> Result<LiteralValue, String> {
+    let values = sorted_entries(&map_arg("values", &args[0])?.borrow()).into_iter().map(|(_, value)| value).collect();
+
+    Ok(LiteralValue::Array(Rc::new(RefCell::new(values))))
+}
+
+// Returns the removed value, null if the key was not there
+fn delete_impl(args: &[LiteralValue]) -> Result<LiteralValue, String> {
+    let key = MapKey::from_value(&args[1])?;
+
+    Ok(map_arg("delete", &args[0])?.borrow_mut().remove(&key).unwrap_or(LiteralValue::Null))
+}
+
+fn has_impl(args: &[LiteralValue]) -> Result<LiteralValue, String> {
+    let entries = map_arg("has", &args[0])?;
+    let key = MapKey::from_value(&args[1])?;
+
+    Ok(LiteralValue::from_bool(entries.borrow().contains_key(&key)))
+}
+
+fn number_arg(name: &str, value: &LiteralValue) -> Result<f64, String> {
+    match value {
+        LiteralValue::IntValue(x) => Ok(*x as f64),
+        LiteralValue::FloatValue(x) => Ok(*x),
+        other => Err(format!("{}() expects a number, got {}", name, other.to_type())),
+    }
+}
+
+// sqrt of a negative number is an error rather than NaN
+fn sqrt_impl(args: &[LiteralValue]) -> Result<LiteralValue, String> {
+    let x = number_arg("sqrt", &args[0])?;
+    if x < 0.0 {
+        return Err(format!("sqrt() of a negative number: {}", args[0].to_string()));
+    }
+
+    Ok(LiteralValue::FloatValue(x.sqrt()))
+}
+
+fn abs_impl(args: &[LiteralValue]) -> Result<LiteralValue, String> {
+    match &args[0] {
+        LiteralValue::IntValue(x) => x.checked_abs()
+            .map(LiteralValue::IntValue)
+            .ok_or_else(|| String::from("Integer overflow")),
+        other => Ok(LiteralValue::FloatValue(number_arg("abs", other)?.abs())),
+    }
+}
+
+// floor, ceil and round leave Ints as they are
+fn rounding_impl(name: &str, args: &[LiteralValue], round: fn(f64) -> f64) -> Result<LiteralValue, String> {
+    match &args[0] {
+        LiteralValue::IntValue(x) => Ok(LiteralValue::IntValue(*x)),
+        other => Ok(LiteralValue::FloatValue(round(number_arg(name, other)?))),
+    }
+}
+
+// Same as the ** operator, but overflow is always an error
+fn pow_impl(args: &[LiteralValue]) -> Result<LiteralValue, String> {
+    number_arg("pow", &args[0])?;
+    number_arg("pow", &args[1])?;
+
+    args[0].pow(&args[1])
+}
+
+// min and max return one of their arguments unchanged, so two Ints give an Int
+fn min_max_impl(name: &str, args: &[LiteralValue], pick_first: fn(f64, f64) -> bool) -> Result<LiteralValue, String> {
+    let a = number_arg(name, &args[0])?;
+    let b = number_arg(name, &args[1])?;
+
+    Ok(if pick_first(a, b) { args[0].clone() } else { args[1].clone() })
+}
+
+fn string_arg<'a>(name: &str, value: &'a LiteralValue) -> Result<&'a str, String> {
+    match value {
+        LiteralValue::StringValue(s) => Ok(s),
+        other => Err(format!("{}() expects a String, got {}", name, other.to_type())),
+    }
+}
+
+fn string_result(value: String) -> Result<LiteralValue, String> {
+    Ok(LiteralValue::StringValue(value))
+}
+
+fn array_arg<'a>(name: &str, value: &'a LiteralValue) -> Result<&'a Rc<RefCell<Vec<LiteralValue>>>, String> {
+    match value {
+        LiteralValue::Array(elements) => Ok(elements),
+        other => Err(format!("{}() expects an Array, got {}", name, other.to_type())),
+    }
+}
+
+// An Int index into an array of `length` elements, `past_end` also allows the length itself
+fn position_arg(name: &str, value: &LiteralValue, length: usize, past_end: bool) -> Result<usize, String> {
+    match value {
+        LiteralValue::IntValue(index) if *index >= 0 && ((*index as usize) < length || (past_end && *index as usize == length)) => {
+            Ok(*index as usize)
+        },
+        LiteralValue::IntValue(index) => Err(format!("{}() index {} out of bounds for length {}", name, index, length)),
+        other => Err(format!("{}() expects an Int index, got {}", name, other.to_type())),
+    }
+}
+
+// The array natives change the array in place, every binding of it sees the change
+fn push_impl(args: &[LiteralValue]) -> Result<LiteralValue, String> {
+    array_arg("push", &args[0])?.borrow_mut().push(args[1].clone());
+
+    Ok(LiteralValue::Null)
+}
+
+fn pop_impl(args: &[LiteralValue]) -> Result<LiteralValue, String> {
+    array_arg("pop", &args[0])?.borrow_mut().pop()
+        .ok_or_else(|| String::from("pop() on an empty Array"))
+}
+
+// The index may be the length, which appends
+fn insert_impl(args: &[LiteralValue]) -> Result<LiteralValue, String> {
+    let mut elements = array_arg("insert", &args[0])?.borrow_mut();
+    let index = position_arg("insert", &args[1], elements.len(), true)?;
+    elements.insert(index, args[2].clone());
+
+    Ok(LiteralValue::Null)
+}
+
+fn remove_impl(args: &[LiteralValue]) -> Result<LiteralValue, String> {
+    let mut elements = array_arg("remove", &args[0])?.borrow_mut();
+    let index = position_arg("remove", &args[1], elements.len(), false)?;
+
+    Ok(elements.remove(index))
+}
+
+// Counts characters, not bytes. Arrays and maps give their number of elements
+fn len_impl(args: &[LiteralValue]) -> Result<LiteralValue, String> {
+    let len = match &args[0] {
+        LiteralValue::StringValue(s) => s.chars().count(),
+        LiteralValue::Array(elements) => elements.borrow().len(),
+        LiteralValue::Map(entries) => entries.borrow().len(),
+        other => return Err(format!("len() expects a String, Array or Map, got {}", other.to_type())),
+    };
+
+    Ok(LiteralValue::IntValue(len as i64))
+}
+
+// An empty separator splits the string into its characters
+fn split_impl(args: &[LiteralValue]) -> Result<LiteralValue, String> {
+    let s = string_arg("split", &args[0])?;
+    let separator = string_arg("split", &args[1])?;
+
+    let parts: Vec<LiteralValue> = if separator.is_empty() {
+        s.chars().map(|c| LiteralValue::StringValue(c.to_string())).collect()
+    } else {
+        s.split(separator).map(|part| LiteralValue::StringValue(part.to_string())).collect()
+    };
+
+    Ok(LiteralValue::Array(Rc::new(RefCell::new(parts))))
+}
+
+// Works on characters. A range past the end of the string is clamped to it, negative bounds are an error
+fn substr_impl(args: &[LiteralValue]) -> Result<LiteralValue, String> {
+    let s = string_arg("substr", &args[0])?;
+    let (start, len) = match (&args[1], &args[2]) {
+        (LiteralValue::IntValue(start), LiteralValue::IntValue(len)) => (*start, *len),
+        (start, len) => return Err(format!("substr() expects Int bounds, got {} and {}", start.to_type(), len.to_type())),
+    };
+    if start < 0 || len < 0 {
+        return Err(String::from("substr() bounds must not be negative"));
+    }
+
+    string_result(s.chars().skip(start as usize).take(len as usize).collect())
+}
+
+// Numbers are returned unchanged. A String is read as a number literal with an optional sign, so it takes
+// the same forms as source does and a String without a fraction or exponent gives an Int
+fn num_impl(args: &[LiteralValue]) -> Result<LiteralValue, String> {
+    let s = match &args[0] {
+        LiteralValue::IntValue(_) | LiteralValue::FloatValue(_) => return Ok(args[0].clone()),
+        other => string_arg("num", other)?.trim(),
+    };
+    // i64::MIN has no positive literal to negate
+    if let Ok(value) = s.parse::<i64>() {
+        return Ok(LiteralValue::IntValue(value));
+    }
+
+    let (negative, literal) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s.strip_prefix('+').unwrap_or(s)),
+    };
+    let tokens = Tokenizer::new(literal).tokenize().unwrap_or_default();
+    let value = match tokens.as_slice() {
+        [number, _] if number.token_type == TokenType::Number => number.literal.clone(),
+        _ => None,
+    };
+
+    match value {
+        Some(tokenizer::LiteralValue::IntValue(x)) => Ok(LiteralValue::IntValue(if negative { -x } else { x })),
+        Some(tokenizer::LiteralValue::FloatValue(x)) if x.is_finite() => Ok(LiteralValue::FloatValue(if negative { -x } else { x })),
+        _ => Err(format!("num() could not parse \"{}\" as a number", s)),
+    }
+}
+
+// Lowercase names, the same ones type annotations use
+fn typeof_impl(args: &[LiteralValue]) -> Result<LiteralValue, String> {
+    let name = match &args[0] {
+        LiteralValue::IntValue(_) => "int",
+        LiteralValue::FloatValue(_) => "float",
+        LiteralValue::StringValue(_) => "string",
+        LiteralValue::True | LiteralValue::False => "bool",
+        LiteralValue::Null => "null",
+        LiteralValue::Array(_) => "array",
+        LiteralValue::Tuple(_) => "tuple",
+        LiteralValue::Map(_) => "map",
+        LiteralValue::Callable { .. } | LiteralValue::Function(_) => "function",
+        LiteralValue::Class(_) => "class",
+        LiteralValue::Instance(_) => "instance",
+    };
+
+    string_result(name.to_string())
+}
+
+fn define_native(
+    environment: &mut Environment,
+    name: &str,
+    arity: usize,
+    fn_: impl Fn(&[LiteralValue]) -> Result<LiteralValue, String> + 'static
+) {
+    define_native_optional(environment, name, arity, 0, fn_);
+}
+
+// Like define_native, but the last `optional` arguments may be left out
+fn define_native_optional(
+    environment: &mut Environment,
+    name: &str,
+    arity: usize,
+    optional: usize,
+    fn_: impl Fn(&[LiteralValue]) -> Result<LiteralValue, String> + 'static
+) {
+    environment.define(
+        Symbol::intern(name), LiteralValue::Callable {
+        name: name.to_string(),
+        arity: arity,
+        optional: optional,
+        variadic: false,
+        fn_: Rc::new(fn_)
+    });
+}
+
+// Like define_native, but any number of arguments may follow the first `arity`
+fn define_native_variadic(
+    environment: &mut Environment,
+    name: &str,
+    arity: usize,
+    fn_: impl Fn(&[LiteralValue]) -> Result<LiteralValue, String> + 'static
+) {
+    environment.define(
+        Symbol::intern(name), LiteralValue::Callable {
+        name: name.to_string(),
+        arity: arity,
+        optional: 0,
+        variadic: true,
+        fn_: Rc::new(fn_)
+    });
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        Self::with_writer(Box::new(io::stdout()))
+    }
+
+    pub fn with_writer(writer: Box<dyn Write>) -> Self {
+        Self::with_io(Box::new(StdinLines { line: Vec::new(), consumed: 0 }), writer)
+    }
+
+    pub fn with_io(reader: Box<dyn BufRead>, writer: Box<dyn Write>) -> Self {
+        let input: Input = Rc::new(RefCell::new(reader));
+        let output: Output = Rc::new(RefCell::new(writer));
+        let mut globals = Environment::new();
+
+        define_native(&mut globals, "time", 0, time_impl);
+        define_native(&mut globals, "clock", 0, time_impl);
+        define_native(&mut globals, "has", 2, has_impl);
+        define_native(&mut globals, "keys", 1, keys_impl);
+        define_native(&mut globals, "values", 1, values_impl);
+        define_native(&mut globals, "delete", 2, delete_impl);
+        define_native(&mut globals, "len", 1, len_impl);
+        define_native(&mut globals, "typeof", 1, typeof_impl);
+        define_native(&mut globals, "str", 1, |args| string_result(args[0].to_string()));
+        define_native(&mut globals, "num", 1, num_impl);
+        define_native(&mut globals, "upper", 1, |args| string_result(string_arg("upper", &args[0])?.to_uppercase()));
+        define_native(&mut globals, "lower", 1, |args| string_result(string_arg("lower", &args[0])?.to_lowercase()));
+        define_native(&mut globals, "trim", 1, |args| string_result(string_arg("trim", &args[0])?.trim().to_string()));
+        define_native(&mut globals, "split", 2, split_impl);
+        define_native(&mut globals, "push", 2, push_impl);
+        define_native(&mut globals, "pop", 1, pop_impl);
+        define_native(&mut globals, "insert", 3, insert_impl);
+        define_native(&mut globals, "remove", 2, remove_impl);
+        define_native(&mut globals, "substr", 3, substr_impl);
+        define_native(&mut globals, "sqrt", 1, sqrt_impl);
+        define_native(&mut globals, "abs", 1, abs_impl);
+        define_native(&mut globals, "floor", 1, |args| rounding_impl("floor", args, f64::floor));
+        define_native(&mut globals, "ceil", 1, |args| rounding_impl("ceil", args, f64::ceil));
+        define_native(&mut globals, "round", 1, |args| rounding_impl("round", args, f64::round));
+        define_native(&mut globals, "pow", 2, pow_impl);
+        define_native(&mut globals, "min", 2, |args| min_max_impl("min", args, |a, b| a <= b));
+        define_native(&mut globals, "max", 2, |args| min_max_impl("max", args, |a, b| a >= b));
+        define_native_variadic(&mut globals, "format", 1, |args| string_result(format_args("format", args)?));
+        let printf_output = output.clone();
+        define_native_variadic(&mut globals, "printf", 1, move |args| printf_impl(&printf_output, args));
+        let input_output = output.clone();
+        define_native_optional(&mut globals, "input", 1, 1, move |args| input_impl(&input, &input_output, args));
+
+        Self {
+            environment: Rc::new(RefCell::new(globals)),
+            config: Rc::new(Config::new(output))
+        }
+    }
+
+    // Also applies to the functions this interpreter has already declared, they share its Config
+    pub fn set_integer_overflow(&mut self, mode: IntegerOverflow) {
+        self.config.integer_overflow.set(mode);
+    }
+
+    pub fn set_missing_key(&mut self, mode: MissingKey) {
+        self.config.missing_key.set(mode);
+    }
+
+    // Steps are counted from here on, setting the limit again restarts the count. 0 removes the limit
+    pub fn set_step_limit(&mut self, limit: usize) {
+        self.config.steps.limit.set(limit);
+        self.config.steps.taken.set(0);
+    }
+
+    pub fn for_closure(environment: Rc<RefCell<Environment>>, config: Rc<Config>) -> Self {
+        Self {
+            environment: environment,
+            config: config
+        }
+    }
+
+    pub fn interpret(&mut self, stmts: Vec<&Stmt>) -> Result<(), CorrodeError> {
+        match self.execute(stmts) {
+            Ok(()) => Ok(()),
+            Err(Unwind::Error(error)) => Err(error),
+            Err(Unwind::Throw(value, line)) => Err(CorrodeError::thrown(line, value)),
+            // The parser rejects return, break and continue where nothing could handle them
+            Err(Unwind::Return(_) | Unwind::Break | Unwind::Continue) => Ok(()),
+        }
+    }
+
+    // Runs an already parsed program, for example one loaded from serialized JSON.
+    // Only the first resolution error is returned
+    pub fn run_ast(&mut self, stmts: Vec<Stmt>) -> Result<(), CorrodeError> {
+        Resolver::new().resolve(&stmts).map_err(|mut errors| errors.remove(0))?;
+
+        self.interpret(stmts.iter().collect())
+    }
+
+    // Runs a whole program on a fresh interpreter and returns everything it wrote.
+    // Only the first error of the stage that fails is returned
+    pub fn run_capturing(source: &str) -> Result<String, CorrodeError> {
+        let tokens = Tokenizer::new(source).collect::<Result<Vec<Token>, CorrodeError>>()?;
+        let stmts = Parser::new(tokens).parse().map_err(|mut errors| errors.remove(0))?;
+        TypeChecker::new().check(&stmts).map_err(|mut errors| errors.remove(0))?;
+
+        let buffer = SharedBuffer(Rc::new(RefCell::new(Vec::new())));
+        Interpreter::with_writer(Box::new(buffer.clone())).run_ast(stmts)?;
+
+        let output = buffer.0.borrow().clone();
+        Ok(String::from_utf8_lossy(&output).into_owned())
+    }
+
+    // Runs one line of REPL input against the persistent state, returning the value of a bare expression
+    pub fn run_line(&mut self, source: &str) -> Result<Option<LiteralValue>, String> {
+        let mut tokenizer = Tokenizer::new(source);
+        let tokens = tokenizer.tokenize()
+            .map_err(|errors| errors.iter().map(|err| err.to_string()).collect::<Vec<String>>().join("\n"))?;
+
+        let mut parser = Parser::new(tokens.clone());
+        let stmts = match parser.parse() {
+            Ok(stmts) => stmts,
+            Err(errors) => {
+                // Not valid statements, but maybe an expression missing its ';'
+                let mut parser = Parser::new(tokens);
+                let expr = parser.parse_expression()
+                    .map_err(|_| errors.iter().map(|err| err.to_string()).collect::<Vec<String>>().join("\n"))?;
+                Resolver::new().resolve_expr(&expr)
+                    .map_err(|errors| errors.iter().map(|err| err.to_string()).collect::<Vec<String>>().join("\n"))?;
+                let value = expr.evaluate(self.environment.clone(), &self.config).map_err(|err| err.to_string())?;
+
+                return Ok(Some(value));
+            }
+        };
+
+        TypeChecker::new().check(&stmts)
+            .map_err(|errors| errors.iter().map(|err| err.to_string()).collect::<Vec<String>>().join("\n"))?;
+        Resolver::new().resolve(&stmts)
+            .map_err(|errors| errors.iter().map(|err| err.to_string()).collect::<Vec<String>>().join("\n"))?;
+
+        self.interpret(stmts.iter().collect()).map_err(|err| err.to_string())?;
+
+        Ok(None)
+    }
+
+    pub fn execute(&mut self, stmts: Vec<&Stmt>) -> Result<(), Unwind> {
+        for stmt in stmts {
+            self.config.steps.take()?;
+
+            match stmt {
+                Stmt::Expression { expression, .. } => {
+                    expression.evaluate(self.environment.clone(), &self.config)?;
+                },
+                Stmt::Echo { expressions, span } => {
+                    let mut values = Vec::new();
+                    for expression in expressions {
+                        values.push(expression.evaluate(self.environment.clone(), &self.config)?.to_string());
+                    }
+
+                    writeln!(self.config.output.borrow_mut(), "{}", values.join(" "))
+                        .map_err(|_| write_error(span))?;
+                },
+                Stmt::Print { expression, newline, span } => {
+                    let value = expression.evaluate(self.environment.clone(), &self.config)?.to_string();
+                    let mut output = self.config.output.borrow_mut();
+                    if *newline {
+                        writeln!(output, "{}", value)
+                    } else {
+                        write!(output, "{}", value)
+                    }.map_err(|_| write_error(span))?;
+                },
+                Stmt::Let { bindings, .. } => {
+                    for binding in bindings {
+                        match &binding.initializer {
+                            Some(initializer) => {
+                                let value = initializer.evaluate(self.environment.clone(), &self.config)?;
+
+                                self.environment.borrow_mut().define(binding.name.symbol(), value);
+                            },
+                            None => self.environment.borrow_mut().declare(binding.name.symbol()),
+                        }
+                    }
+                },
+                Stmt::LetDestructure { pattern, equals, initializer, .. } => {
+                    let value = initializer.evaluate(self.environment.clone(), &self.config)?;
+
+                    pattern.destructure(value, equals, &mut |name, _depth, value| {
+                        self.environment.borrow_mut().define(name.symbol(), value);
+                        Ok(())
+                    })?;
+                },
+                Stmt::Const { name, initializer, .. } => {
+                    let value = initializer.evaluate(self.environment.clone(), &self.config)?;
+
+                    self.environment.borrow_mut().define_const(name.symbol(), value)
+                },
+                Stmt::Block { statements, .. } => {
+                    let mut new_environment = Environment::new();
+                    new_environment.enclosing = Some(self.environment.clone());
+
+                    let old_environment = self.environment.clone();
+                    self.environment = Rc::new(RefCell::new(new_environment));
+                    let block_result =  self.execute(statements.iter().collect());
+                    self.environment = old_environment;
+
+                    block_result?;
+                },
+                Stmt::If { predicate, then, otherwise, .. } => {
+                    let truth_value = predicate.evaluate(self.environment.clone(), &self.config)?;
+
+                    if truth_value.is_truthy() {
+                        let statements = vec![then.as_ref()];
+                        self.execute(statements)?;
+                    } else if let Some(else_stmt) = otherwise {
+                        let statements = vec![else_stmt.as_ref()];
+                        self.execute(statements)?;
+                    }
+                },
+                Stmt::Match { subject, arms, default, .. } => {
+                    let value = subject.evaluate(self.environment.clone(), &self.config)?;
+
+                    // Only the first matching arm runs, there is no fall through
+                    let mut matched = None;
+                    'arms: for (patterns, body) in arms {
+                        for pattern in patterns {
+                            if pattern.evaluate(self.environment.clone(), &self.config)? == value {
+                                matched = Some(body);
+                                break 'arms;
+                            }
+                        }
+                    }
+
+                    if let Some(body) = matched.or(default.as_deref()) {
+                        self.execute(vec![body])?;
+                    }
+                },
+                Stmt::While { condition, body, increment, .. } => {
+                    let mut flag = condition.evaluate(self.environment.clone(), &self.config)?;
+
+                    while flag.is_truthy() {
+                        let statements = vec![body.as_ref()];
+                        match self.execute(statements) {
+                            Ok(()) | Err(Unwind::Continue) => (),
+                            Err(Unwind::Break) => break,
+                            Err(signal) => return Err(signal),
+                        }
+
+                        if let Some(increment) = increment {
+                            increment.evaluate(self.environment.clone(), &self.config)?;
+                        }
+                        flag = condition.evaluate(self.environment.clone(), &self.config)?;
+                    }
+                },
+                Stmt::DoWhile { body, condition, .. } => {
+                    loop {
+                        let statements = vec![body.as_ref()];
+                        match self.execute(statements) {
+                            Ok(()) | Err(Unwind::Continue) => (),
+                            Err(Unwind::Break) => break,
+                            Err(signal) => return Err(signal),
+                        }
+
+                        if !condition.evaluate(self.environment.clone(), &self.config)?.is_truthy() {
+                            break;
+                        }
+                    }
+                },
+                Stmt::ForIn { key, item, iterable, body, .. } => {
+                    // Taken up front so the body can change the collection while it is being iterated
+                    let pairs = match iterable.evaluate(self.environment.clone(), &self.config)? {
+                        LiteralValue::Array(elements) => elements.borrow().iter()
+                            .enumerate()
+                            .map(|(index, element)| (LiteralValue::IntValue(index as i64), element.clone()))
+                            .collect::<Vec<(LiteralValue, LiteralValue)>>(),
+                        LiteralValue::Map(entries) => sorted_entries(&entries.borrow()),
+                        other => return Err(Unwind::Error(CorrodeError::runtime(
+                            item.line_number,
+                            format!("Can only iterate over Arrays and Maps, got {}", other.to_type())
+                        ).with_column(item.column))),
+                    };
+
+                    for (pair_key, pair_item) in pairs {
+                        let mut new_environment = Environment::new();
+                        new_environment.enclosing = Some(self.environment.clone());
+                        if let Some(key) = key {
+                            new_environment.define(key.symbol(), pair_key);
+                        }
+                        new_environment.define(item.symbol(), pair_item);
+
+                        let old_environment = self.environment.clone();
+                        self.environment = Rc::new(RefCell::new(new_environment));
+                        let body_result = self.execute(vec![body.as_ref()]);
+                        self.environment = old_environment;
+
+                        match body_result {
+                            Ok(()) | Err(Unwind::Continue) => (),
+                            Err(Unwind::Break) => break,
+                            Err(signal) => return Err(signal),
+                        }
+                    }
+                },
+                Stmt::Function { name, params, rest, body, .. } => {
+                    let function = LiteralValue::Function(Rc::new(Function {
+                        name: name.clone(),
+                        params: params.clone(),
+                        rest: rest.clone(),
+                        body: Rc::new(body.clone()),
+                        closure: self.environment.clone(),
+                        is_initializer: false,
+                        config: self.config.clone(),
+                    }));
+
+                    self.environment.borrow_mut().define(name.symbol(), function);
+                },
+                Stmt::Class { name, superclass, methods, .. } => {
+                    let superclass = match superclass {
+                        Some(expr) => match expr.evaluate(self.environment.clone(), &self.config)? {
+                            LiteralValue::Class(class) => Some(class),
+                            _ => return Err(Unwind::Error(CorrodeError::runtime(name.line_number, String::from("Superclass must be a class")).with_column(name.column))),
+                        },
+                        None => None,
+                    };
+
+                    // Methods of a subclass close over an extra scope holding `super`
+                    let closure = match &superclass {
+                        Some(class) => {
+                            let mut environment = Environment::new();
+                            environment.enclosing = Some(self.environment.clone());
+                            environment.define(Symbol::intern("super"), LiteralValue::Class(class.clone()));
+                            Rc::new(RefCell::new(environment))
+                        },
+                        None => self.environment.clone(),
+                    };
+
+                    let mut class_methods = HashMap::new();
+                    for method in methods {
+                        if let Stmt::Function { name, params, rest, body, .. } = method {
+                            class_methods.insert(name.lexeme.clone(), Rc::new(Function {
+                                name: name.clone(),
+                                params: params.clone(),
+                                rest: rest.clone(),
+                                body: Rc::new(body.clone()),
+                                closure: closure.clone(),
+                                is_initializer: name.lexeme == "init",
+                                config: self.config.clone(),
+                            }));
+                        }
+                    }
+
+                    let class = LiteralValue::Class(Rc::new(Class {
+                        name: name.lexeme.clone(),
+                        superclass: superclass,
+                        methods: class_methods,
+                    }));
+
+                    self.environment.borrow_mut().define(name.symbol(), class);
+                },
+                Stmt::Return { keyword: _, value, .. } => {
+                    let value = match value {
+                        Some(expr) => expr.evaluate(self.environment.clone(), &self.config)?,
+                        None => LiteralValue::Null,
+                    };
+
+                    return Err(Unwind::Return(value));
+                },
+                Stmt::Break { .. } => return Err(Unwind::Break),
+                Stmt::Continue { .. } => return Err(Unwind::Continue),
+                Stmt::Throw { keyword, value, .. } => {
+                    let value = value.evaluate(self.environment.clone(), &self.config)?;
+
+                    return Err(Unwind::Throw(value, keyword.line_number));
+                },
+                // Only thrown values are caught, runtime errors and the other signals pass through
+                Stmt::Try { body, name, handler, .. } => match self.execute(vec![body.as_ref()]) {
+                    Err(Unwind::Throw(value, _)) => {
+                        let mut new_environment = Environment::new();
+                        new_environment.enclosing = Some(self.environment.clone());
+                        new_environment.define(name.symbol(), value);
+
+                        let old_environment = self.environment.clone();
+                        self.environment = Rc::new(RefCell::new(new_environment));
+                        let handler_result = self.execute(vec![handler.as_ref()]);
+                        self.environment = old_environment;
+
+                        handler_result?;
+                    },
+                    result => result?,
+                },
+                Stmt::Assert { keyword, condition, message, .. } => {
+                    if !condition.evaluate(self.environment.clone(), &self.config)?.is_truthy() {
+                        let message = match message {
+                            Some(message) => format!("Assertion failed: {}", message.evaluate(self.environment.clone(), &self.config)?.to_string()),
+                            None => String::from("Assertion failed"),
+                        };
+
+                        return Err(Unwind::Error(CorrodeError::runtime(keyword.line_number, message).with_column(keyword.column)));
+                    }
+                },
+            };
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn capture(source: &str) -> String {
+        let buffer = SharedBuffer(Rc::new(RefCell::new(Vec::new())));
+        let mut interpreter = Interpreter::with_writer(Box::new(buffer.clone()));
+        interpreter.run_line(source).unwrap();
+
+        let output = buffer.0.borrow().clone();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn run_ast_from_json() {
+        let source = "
+            class Greeter {
+                fn init(name) { self.name = name; }
+                fn greet() { return \"hi ${self.name}\"; }
+            }
+            let items = [1, 2.0, true, null];
+            for (let i = 0; i < 4; i += 1) echo items[i];
+            echo Greeter(\"zawa\").greet(), { \"k\": 1 };
+        ";
+
+        let mut tokenizer = Tokenizer::new(source);
+        let mut parser = Parser::new(tokenizer.tokenize().unwrap());
+        let json = serde_json::to_string(&parser.parse().unwrap()).unwrap();
+        let stmts: Vec<Stmt> = serde_json::from_str(&json).unwrap();
+
+        let buffer = SharedBuffer(Rc::new(RefCell::new(Vec::new())));
+        let mut interpreter = Interpreter::with_writer(Box::new(buffer.clone()));
+        interpreter.run_ast(stmts).unwrap();
+
+        let output = String::from_utf8(buffer.0.borrow().clone()).unwrap();
+        assert_eq!(output, capture(source));
+        assert_eq!(output, "1\n2\ntrue\nnull\nhi zawa {k: 1}\n");
+    }
+
+    #[test]
+    fn interpret_parsed_program() {
+        let source = "let a = 1;\necho a;\necho a + \"b\";\necho 3;";
+
+        let mut tokenizer = Tokenizer::new(source);
+        let mut parser = Parser::new(tokenizer.tokenize().unwrap());
+        let stmts = parser.parse().unwrap();
+
+        let buffer = SharedBuffer(Rc::new(RefCell::new(Vec::new())));
+        let mut interpreter = Interpreter::with_writer(Box::new(buffer.clone()));
+        let result = interpreter.interpret(stmts.iter().collect());
+
+        // The statements after the failing one never run
+        assert_eq!(result.err().map(|err| err.line()), Some(3));
+        assert_eq!(String::from_utf8(buffer.0.borrow().clone()).unwrap(), "1\n");
+    }
+
+    #[test]
+    fn run_capturing_returns_output() {
+        assert_eq!(Interpreter::run_capturing("echo 1; echo 2;"), Ok(String::from("1\n2\n")));
+        assert_eq!(Interpreter::run_capturing("print(\"a\"); print(1 + 1);"), Ok(String::from("a2")));
+    }
+
+    #[test]
+    fn run_capturing_returns_first_error() {
+        assert_eq!(Interpreter::run_capturing("echo \"a"), Err(CorrodeError::lex(1, String::from("Unterminated string")).with_column(6)));
+        assert_eq!(Interpreter::run_capturing("echo 1 +;"), Err(CorrodeError::parse(1, String::from("Expected expression, found ';'")).with_column(9)));
+        assert_eq!(Interpreter::run_capturing("echo -true;"), Err(CorrodeError::type_error(1, String::from("Unary operator - can not be applied to Boolean")).with_column(6)));
+        assert_eq!(Interpreter::run_capturing("echo 1;\necho [1][3];"), Err(CorrodeError::runtime(2, String::from("Index 3 out of bounds for length 1")).with_column(11)));
+    }
+
+    #[test]
+    fn echo_writes_to_writer() {
+        assert_eq!(capture("echo 1 + 2;"), "3\n");
+    }
+
+    #[test]
+    fn echo_joins_values_with_spaces() {
+        assert_eq!(capture("let a = 1; echo a, \"b\", a + 2;"), "1 b 3\n");
+    }
+
+    #[test]
+    fn echo_array() {
+        assert_eq!(capture("let a = [1, \"two\", [3.5, null]]; echo a; echo [];"), "[1, two, [3.5, null]]\n[]\n");
+    }
+
+    struct FailingWriter;
+
+    impl Write for FailingWriter {
+        fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+            Err(io::Error::from(io::ErrorKind::BrokenPipe))
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn failed_writes_are_errors() {
+        let mut interpreter = Interpreter::with_writer(Box::new(FailingWriter));
+
+        assert_eq!(interpreter.run_line("echo 1;"), Err(String::from("[line 1] Could not write output")));
+        assert_eq!(interpreter.run_line("\nprintln 1;"), Err(String::from("[line 2] Could not write output")));
+        assert_eq!(interpreter.run_line("printf(\"a\");"), Err(String::from("[line 1] Could not write output")));
+    }
+
+    #[test]
+    fn deep_nesting_is_an_error() {
+        let blocks = format!("{}echo 1;{}", "{".repeat(50_000), "}".repeat(50_000));
+        let parens = format!("echo {}1{};", "(".repeat(50_000), ")".repeat(50_000));
+
+        assert!(Interpreter::new().run_line(&blocks).unwrap_err().contains("Too much nesting"));
+        assert!(Interpreter::new().run_line(&parens).unwrap_err().contains("Too much nesting"));
+        assert_eq!(Interpreter::run_capturing(&blocks).unwrap_err().to_string(), "[line 1] Too much nesting");
+        assert_eq!(Interpreter::run_capturing(&format!("{}echo 1;", "if (true) ".repeat(15))), Ok(String::from("1\n")));
+    }
+
+    #[test]
+    fn print_has_no_newline() {
+        assert_eq!(capture("print \"a\"; print 1 + 1; print [\"b\"];"), "a2[b]");
+        assert_eq!(capture("print(\"a\"); print(\"b\");"), "ab");
+    }
+
+    #[test]
+    fn println_ends_with_newline() {
+        assert_eq!(capture("println \"a\"; println 1 + 1;"), "a\n2\n");
+        assert_eq!(capture("print \"a\"; print \"b\"; println \"c\"; print \"d\";"), "abc\nd");
+    }
+
+    #[test]
+    fn natives_and_functions_write_to_writer() {
+        assert_eq!(capture("print(1); println(2); fn f() { echo 3; } f();"), "12\n3\n");
+    }
+
+    #[test]
+    fn run_line_keeps_state() {
+        let mut interpreter = Interpreter::new();
+
+        assert_eq!(interpreter.run_line("let x = 1;"), Ok(None));
+        assert_eq!(interpreter.run_line("x = x + 1;"), Ok(None));
+        assert_eq!(interpreter.run_line("x * 10"), Ok(Some(LiteralValue::IntValue(20))));
+    }
+
+    #[test]
+    fn map_insert_and_lookup() {
+        let mut interpreter = Interpreter::new();
+
+        assert_eq!(interpreter.run_line("let m = { \"a\": 1, 2: \"two\", };"), Ok(None));
+        assert_eq!(interpreter.run_line("m[\"c\"] = 3;"), Ok(None));
+        assert_eq!(interpreter.run_line("m[\"a\"] + m[\"c\"]"), Ok(Some(LiteralValue::IntValue(4))));
+        assert_eq!(interpreter.run_line("m[2]"), Ok(Some(LiteralValue::StringValue(String::from("two")))));
+        assert_eq!(interpreter.run_line("m"), Ok(Some(interpreter.run_line("{ 2: \"two\", \"c\": 3, \"a\": 1 }").unwrap().unwrap())));
+    }
+
+    #[test]
+    fn map_missing_key() {
+        let mut interpreter = Interpreter::new();
+
+        assert_eq!(interpreter.run_line("let m = { \"a\": null };"), Ok(None));
+        assert_eq!(interpreter.run_line("m[\"b\"]"), Ok(Some(LiteralValue::Null)));
+        assert_eq!(interpreter.run_line("has(m, \"a\")"), Ok(Some(LiteralValue::True)));
+        assert_eq!(interpreter.run_line("has(m, \"b\")"), Ok(Some(LiteralValue::False)));
+        assert_eq!(interpreter.run_line("m[1.5]"), Err(String::from("[line 1] Map keys must be Strings or Ints, got Float")));
+    }
+
+    #[test]
+    fn map_missing_key_can_error() {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_missing_key(MissingKey::Error);
+
+        assert_eq!(interpreter.run_line("let m = { \"a\": null, 1: 2 };"), Ok(None));
+        assert_eq!(interpreter.run_line("m[\"a\"]"), Ok(Some(LiteralValue::Null)));
+        assert_eq!(interpreter.run_line("m[\"b\"]"), Err(String::from("[line 1] Map has no key 'b'")));
+        assert_eq!(interpreter.run_line("fn get(k) { return m[k]; }"), Ok(None));
+        assert_eq!(interpreter.run_line("get(2)"), Err(String::from("[line 1] Map has no key '2'")));
+    }
+
+    #[test]
+    fn map_natives() {
+        let mut interpreter = Interpreter::new();
+
+        assert_eq!(interpreter.run_line("let m = { \"b\": 2, \"a\": 1, 3: null }; let alias = m;"), Ok(None));
+        assert_eq!(interpreter.run_line("str(keys(m)) + str(values(m))"), Ok(Some(LiteralValue::StringValue(String::from("[3, a, b][null, 1, 2]")))));
+        assert_eq!(interpreter.run_line("has(m, \"a\")"), Ok(Some(LiteralValue::True)));
+        assert_eq!(interpreter.run_line("delete(alias, \"a\")"), Ok(Some(LiteralValue::IntValue(1))));
+        assert_eq!(interpreter.run_line("has(m, \"a\")"), Ok(Some(LiteralValue::False)));
+        assert_eq!(interpreter.run_line("delete(m, \"a\")"), Ok(Some(LiteralValue::Null)));
+        assert_eq!(interpreter.run_line("len(m)"), Ok(Some(LiteralValue::IntValue(2))));
+        assert_eq!(interpreter.run_line("keys([1])"), Err(String::from("[line 1] keys() expects a Map, got Array")));
+        assert_eq!(interpreter.run_line("delete(m, 1.5)"), Err(String::from("[line 1] Map keys must be Strings or Ints, got Float")));
+    }
+
+    #[test]
+    fn echo_map_sorted() {
+        assert_eq!(capture("echo { \"b\": 2, \"a\": [1], 3: null };"), "{3: null, a: [1], b: 2}\n");
+    }
+
+    #[test]
+    fn compound_assignment() {
+        let mut interpreter = Interpreter::new();
+
+        assert_eq!(interpreter.run_line("let x = 3; x += 2;"), Ok(None));
+        assert_eq!(interpreter.run_line("x"), Ok(Some(LiteralValue::IntValue(5))));
+        assert_eq!(interpreter.run_line("let a = [10]; a[0] -= 4; a[0] *= 2; a[0] /= 4;"), Ok(None));
+        assert_eq!(interpreter.run_line("a[0]"), Ok(Some(LiteralValue::FloatValue(3.0))));
+    }
+
+    #[test]
+    fn compound_assignment_evaluates_target_once() {
+        let output = capture("
+            let a = [1, 2], m = {\"k\": 1};
+            fn idx() { echo \"idx\"; return 1; }
+            class Box { fn init() { self.n = 1; } }
+            let box = Box();
+            fn get() { echo \"get\"; return box; }
+            a[idx()] += 10;
+            get().n += 5;
+            m[\"k\"] *= 3;
+            echo a, box.n, m[\"k\"];
+        ");
+
+        assert_eq!(output, "idx\nget\n[1, 12] 6 3\n");
+    }
+
+    #[test]
+    fn negative_index_counts_from_end() {
+        let mut interpreter = Interpreter::new();
+
+        assert_eq!(interpreter.run_line("[1, 2, 3][-1]"), Ok(Some(LiteralValue::IntValue(3))));
+        assert_eq!(interpreter.run_line("[1, 2, 3][-3]"), Ok(Some(LiteralValue::IntValue(1))));
+        assert_eq!(interpreter.run_line("\"abc\"[-2]"), Ok(Some(LiteralValue::StringValue(String::from("b")))));
+        assert_eq!(interpreter.run_line("let a = [1, 2, 3]; a[-1] = 9; a[-2] += 10;"), Ok(None));
+        assert_eq!(interpreter.run_line("str(a)"), Ok(Some(LiteralValue::StringValue(String::from("[1, 12, 9]")))));
+        assert_eq!(interpreter.run_line("[1, 2, 3][-4]"), Err(String::from("[line 1] Index -4 out of bounds for length 3")));
+        assert_eq!(interpreter.run_line("[][-1]"), Err(String::from("[line 1] Index -1 out of bounds for length 0")));
+    }
+
+    #[test]
+    fn slice_copies_a_range() {
+        let mut interpreter = Interpreter::new();
+        let slice = |interpreter: &mut Interpreter, source: &str| interpreter.run_line(&format!("str({})", source)).unwrap().unwrap().to_string();
+
+        assert_eq!(slice(&mut interpreter, "[1, 2, 3, 4][1:3]"), "[2, 3]");
+        assert_eq!(slice(&mut interpreter, "[1, 2, 3][:2]"), "[1, 2]");
+        assert_eq!(slice(&mut interpreter, "[1, 2, 3][-2:]"), "[2, 3]");
+        assert_eq!(slice(&mut interpreter, "[1, 2, 3][-10:10]"), "[1, 2, 3]");
+        assert_eq!(slice(&mut interpreter, "[1, 2, 3][2:1]"), "[]");
+        assert_eq!(slice(&mut interpreter, "\"héllo\"[1:-1]"), "éll");
+
+        assert_eq!(interpreter.run_line("let a = [1, 2]; let b = a[:]; b[0] = 9;"), Ok(None));
+        assert_eq!(interpreter.run_line("a[0]"), Ok(Some(LiteralValue::IntValue(1))));
+        assert_eq!(interpreter.run_line("a[0.5:]"), Err(String::from("[line 1] Slice bounds must be Ints, got Float")));
+        assert_eq!(interpreter.run_line("{ \"a\": 1 }[:1]"), Err(String::from("[line 1] Can not slice Map")));
+    }
+
+    #[test]
+    fn index_must_be_valid() {
+        let mut interpreter = Interpreter::new();
+
+        assert_eq!(interpreter.run_line("[1, 2][2]"), Err(String::from("[line 1] Index 2 out of bounds for length 2")));
+        assert_eq!(interpreter.run_line("[1, 2][0.5]"), Err(String::from("[line 1] Index must be an Int, got Float")));
+        assert_eq!(interpreter.run_line("\"ab\"[0] = \"c\""), Err(String::from("[line 1] Can not assign to an index of String")));
+    }
+
+    #[test]
+    fn run_line_continues_after_errors() {
+        let mut interpreter = Interpreter::new();
+
+        assert_eq!(interpreter.run_line("let x = ;"), Err(String::from("[line 1] Expected expression, found ';'")));
+        assert_eq!(interpreter.run_line("y"), Err(String::from("[line 1] Undefined variable 'y'")));
+        assert_eq!(interpreter.run_line("let x = 2;"), Ok(None));
+        assert_eq!(interpreter.run_line("x"), Ok(Some(LiteralValue::IntValue(2))));
+    }
+
+    #[test]
+    fn assert_passes_silently() {
+        assert_eq!(capture("assert 1 < 2; assert true, \"not shown\"; let x = [1]; assert x[0] == 1;"), "");
+    }
+
+    #[test]
+    fn assert_fails_with_message() {
+        let mut interpreter = Interpreter::new();
+
+        assert_eq!(interpreter.run_line("assert 1 > 2;"), Err(String::from("[line 1] Assertion failed")));
+        assert_eq!(
+            interpreter.run_line("let n = 3;\nassert n == 4, \"n is ${n}\";\necho \"unreachable\";"),
+            Err(String::from("[line 2] Assertion failed: n is 3"))
+        );
+    }
+
+    #[test]
+    fn do_while_runs_body_before_condition() {
+        assert_eq!(capture("let i = 0; do { echo i; i += 1; } while (i > 10);"), "0\n");
+        assert_eq!(capture("let i = 0; do { i += 1; if (i == 2) continue; if (i == 4) break; echo i; } while (true);"), "1\n3\n");
+    }
+
+    #[test]
+    fn comparison_chains_evaluate_middle_once() {
+        let output = capture("
+            let calls = 0;
+            fn mid(x) { calls += 1; return x; }
+            echo 0 < mid(5) < 10, calls;
+            echo 0 < mid(5) <= mid(4) < 10, calls;
+            echo 3 < 1 < mid(0), calls;
+            echo 1 < 2 > 0, 1 <= 1 < 2 >= 3;
+        ");
+
+        assert_eq!(output, "true 1\nfalse 3\nfalse 3\ntrue false\n");
+    }
+
+    #[test]
+    fn try_catches_thrown_values() {
+        let output = capture("
+            fn check(n) {
+                if (n < 0) { throw {\"negative\": n}; }
+                return n;
+            }
+            try {
+                echo check(1);
+                echo check(-2);
+                echo \"unreachable\";
+            } catch (e) {
+                echo \"caught\", e[\"negative\"];
+            }
+            for (i in [1, 2, 3]) {
+                try { if (i == 2) { continue; } throw i; } catch (e) { echo e; }
+            }
+        ");
+
+        assert_eq!(output, "1\ncaught -2\n1\n3\n");
+    }
+
+    #[test]
+    fn throw_propagates_to_outer_try() {
+        let output = capture("
+            try {
+                try { throw \"inner\"; } catch (e) { throw e + \" again\"; }
+            } catch (e) {
+                echo e;
+            }
+        ");
+
+        assert_eq!(output, "inner again\n");
+
+        let mut interpreter = Interpreter::new();
+        assert_eq!(
+            interpreter.run_line("fn f() { throw \"boom\"; }\ntry { 1 / 0; } catch (e) { echo e; }"),
+            Err(String::from("[line 2] Division by zero"))
+        );
+        assert_eq!(interpreter.run_line("f();"), Err(String::from("[line 1] Uncaught throw: boom")));
+        assert_eq!(interpreter.run_line("f()"), Err(String::from("[line 1] Uncaught throw: boom")));
+    }
+
+    #[test]
+    fn input_reads_lines() {
+        let buffer = SharedBuffer(Rc::new(RefCell::new(Vec::new())));
+        let input = io::Cursor::new("Ada\r\nsecond line\nlast");
+        let mut interpreter = Interpreter::with_io(Box::new(input), Box::new(buffer.clone()));
+        let string = |s: &str| Ok(Some(LiteralValue::StringValue(String::from(s))));
+
+        assert_eq!(interpreter.run_line("input(\"name? \")"), string("Ada"));
+        assert_eq!(interpreter.run_line("input()"), string("second line"));
+        assert_eq!(interpreter.run_line("input()"), string("last"));
+        assert_eq!(interpreter.run_line("input()"), Ok(Some(LiteralValue::Null)));
+        assert_eq!(String::from_utf8(buffer.0.borrow().clone()).unwrap(), "name? ");
+
+        assert_eq!(interpreter.run_line("input(1, 2)"), Err(String::from("[line 1] Expected 0 to 1 arguments but got 2")));
+    }
+
+    #[test]
+    fn string_natives() {
+        let mut interpreter = Interpreter::new();
+        let string = |s: &str| Ok(Some(LiteralValue::StringValue(String::from(s))));
+
+        assert_eq!(interpreter.run_line("upper(\"hi\") == \"HI\""), Ok(Some(LiteralValue::True)));
+        assert_eq!(interpreter.run_line("lower(\"Hi There\")"), string("hi there"));
+        assert_eq!(interpreter.run_line("trim(\"  hi \")"), string("hi"));
+        assert_eq!(interpreter.run_line("len(\"hello\")"), Ok(Some(LiteralValue::IntValue(5))));
+        assert_eq!(interpreter.run_line("len([1, 2, 3])"), Ok(Some(LiteralValue::IntValue(3))));
+        assert_eq!(interpreter.run_line("split(\"a,b,,c\", \",\")"), interpreter.run_line("[\"a\", \"b\", \"\", \"c\"]"));
+        assert_eq!(interpreter.run_line("split(\"abc\", \"\")"), interpreter.run_line("[\"a\", \"b\", \"c\"]"));
+        assert_eq!(interpreter.run_line("substr(\"hello\", 1, 3)"), string("ell"));
+        assert_eq!(interpreter.run_line("substr(\"hello\", 3, 10)"), string("lo"));
+        assert_eq!(interpreter.run_line("substr(\"hello\", 9, 1)"), string(""));
+    }
+
+    #[test]
+    fn triple_quoted_string_prints_across_lines() {
+        assert_eq!(capture("echo \"\"\"first \"line\"\nsecond line\"\"\";"), "first \"line\"\nsecond line\n");
+    }
+
+    #[test]
+    fn unicode_variable_name() {
+        assert_eq!(capture("let α = 2; α = α * 3; echo α;"), "6\n");
+    }
+
+    #[test]
+    fn raw_string_keeps_escapes() {
+        let mut interpreter = Interpreter::new();
+
+        assert_eq!(interpreter.run_line("len(r\"\\n\")"), Ok(Some(LiteralValue::IntValue(2))));
+        assert_eq!(interpreter.run_line("len(\"\\n\")"), Ok(Some(LiteralValue::IntValue(1))));
+    }
+
+    #[test]
+    fn string_natives_validate_arguments() {
+        let mut interpreter = Interpreter::new();
+
+        assert_eq!(interpreter.run_line("len(5)"), Err(String::from("[line 1] len() expects a String, Array or Map, got Int")));
+        assert_eq!(interpreter.run_line("upper(5)"), Err(String::from("[line 1] upper() expects a String, got Int")));
+        assert_eq!(interpreter.run_line("split(\"a\", 1)"), Err(String::from("[line 1] split() expects a String, got Int")));
+        assert_eq!(interpreter.run_line("substr(\"a\", -1, 1)"), Err(String::from("[line 1] substr() bounds must not be negative")));
+        assert_eq!(interpreter.run_line("substr(\"a\", 0, 1.5)"), Err(String::from("[line 1] substr() expects Int bounds, got Int and Float")));
+    }
+
+    #[test]
+    fn format_fills_placeholders() {
+        let mut interpreter = Interpreter::new();
+
+        assert_eq!(interpreter.run_line("format(\"{} + {} = {}\", 1, 2, 3)"), Ok(Some(LiteralValue::StringValue(String::from("1 + 2 = 3")))));
+        assert_eq!(interpreter.run_line("format(\"{{{}}} {}\", [1], null)"), Ok(Some(LiteralValue::StringValue(String::from("{[1]} null")))));
+        assert_eq!(interpreter.run_line("format(\"plain\")"), Ok(Some(LiteralValue::StringValue(String::from("plain")))));
+        assert_eq!(capture("printf(\"{}-{}\", \"a\", 1.5); printf(\"!\");"), "a-1.5!");
+    }
+
+    #[test]
+    fn format_validates_placeholders() {
+        let mut interpreter = Interpreter::new();
+
+        assert_eq!(interpreter.run_line("format(\"{} {}\", 1)"), Err(String::from("[line 1] format() has 2 placeholders but got 1 values")));
+        assert_eq!(interpreter.run_line("format(\"{}\", 1, 2)"), Err(String::from("[line 1] format() has 1 placeholders but got 2 values")));
+        assert_eq!(interpreter.run_line("format(\"{x}\", 1)"), Err(String::from("[line 1] format() expects '}' after '{' in the format string")));
+        assert_eq!(interpreter.run_line("printf(\"}\")"), Err(String::from("[line 1] printf() found an unmatched '}' in the format string")));
+        assert_eq!(interpreter.run_line("format()"), Err(String::from("[line 1] Expected at least 1 arguments but got 0")));
+        assert_eq!(interpreter.run_line("format(1)"), Err(String::from("[line 1] format() expects a String, got Int")));
+    }
+
+    #[test]
+    fn array_natives_mutate_in_place() {
+        assert_eq!(capture(concat!(
+            "let a = [1, 2]; let alias = a;\n",
+            "push(alias, 3); echo a;\n",
+            "echo pop(a), alias;\n",
+            "insert(a, 0, 0); insert(a, 3, 9); echo alias;\n",
+            "echo remove(alias, 1), a;"
+        )), "[1, 2, 3]\n3 [1, 2]\n[0, 1, 2, 9]\n1 [0, 2, 9]\n");
+    }
+
+    #[test]
+    fn array_natives_validate_arguments() {
+        let mut interpreter = Interpreter::new();
+        interpreter.run_line("let a = [1];").unwrap();
+
+        assert_eq!(interpreter.run_line("insert(a, 2, 0)"), Err(String::from("[line 1] insert() index 2 out of bounds for length 1")));
+        assert_eq!(interpreter.run_line("remove(a, 1)"), Err(String::from("[line 1] remove() index 1 out of bounds for length 1")));
+        assert_eq!(interpreter.run_line("remove(a, -1)"), Err(String::from("[line 1] remove() index -1 out of bounds for length 1")));
+        assert_eq!(interpreter.run_line("remove(a, 0.5)"), Err(String::from("[line 1] remove() expects an Int index, got Float")));
+        assert_eq!(interpreter.run_line("push(\"a\", 1)"), Err(String::from("[line 1] push() expects an Array, got String")));
+        assert_eq!(interpreter.run_line("pop(a)"), Ok(Some(LiteralValue::IntValue(1))));
+        assert_eq!(interpreter.run_line("pop(a)"), Err(String::from("[line 1] pop() on an empty Array")));
+        assert_eq!(interpreter.run_line("remove(a, 0)"), Err(String::from("[line 1] remove() index 0 out of bounds for length 0")));
+    }
+
+    #[test]
+    fn typeof_names_value_types() {
+        let mut interpreter = Interpreter::new();
+
+        assert_eq!(capture(concat!(
+            "fn f() {}\n",
+            "echo typeof(1), typeof(1.5), typeof(\"a\"), typeof(true), typeof(null);\n",
+            "echo typeof([1]), typeof({\"a\": 1}), typeof(f), typeof(len), typeof(fn (x) => x);"
+        )), "int float string bool null\narray map function function function\n");
+        assert_eq!(interpreter.run_line("typeof()"), Err(String::from("[line 1] Expected 1 arguments but got 0")));
+        assert_eq!(interpreter.run_line("typeof(1, 2)"), Err(String::from("[line 1] Expected 1 arguments but got 2")));
+    }
+
+    #[test]
+    fn conversion_natives() {
+        let mut interpreter = Interpreter::new();
+
+        assert_eq!(interpreter.run_line("num(\"3.5\") + 1"), Ok(Some(LiteralValue::FloatValue(4.5))));
+        assert_eq!(interpreter.run_line("num(\" 12 \")"), Ok(Some(LiteralValue::IntValue(12))));
+        assert_eq!(interpreter.run_line("num(7)"), Ok(Some(LiteralValue::IntValue(7))));
+        assert_eq!(interpreter.run_line("num(\"0x10\")"), Ok(Some(LiteralValue::IntValue(16))));
+        assert_eq!(interpreter.run_line("num(\"-1_000\")"), Ok(Some(LiteralValue::IntValue(-1000))));
+        assert_eq!(interpreter.run_line("num(\"-2.5e1\")"), Ok(Some(LiteralValue::FloatValue(-25.0))));
+        assert_eq!(interpreter.run_line("num(\"-9223372036854775808\")"), Ok(Some(LiteralValue::IntValue(i64::MIN))));
+        assert_eq!(interpreter.run_line("str(42)"), Ok(Some(LiteralValue::StringValue(String::from("42")))));
+        assert_eq!(interpreter.run_line("str([1, \"a\"]) + \"!\""), Ok(Some(LiteralValue::StringValue(String::from("[1, a]!")))));
+        assert_eq!(interpreter.run_line("num(\"abc\")"), Err(String::from("[line 1] num() could not parse \"abc\" as a number")));
+        assert_eq!(interpreter.run_line("num(\"NaN\")"), Err(String::from("[line 1] num() could not parse \"NaN\" as a number")));
+        assert_eq!(interpreter.run_line("num(\"inf\")"), Err(String::from("[line 1] num() could not parse \"inf\" as a number")));
+        assert_eq!(interpreter.run_line("num(\"1e999\")"), Err(String::from("[line 1] num() could not parse \"1e999\" as a number")));
+        assert_eq!(interpreter.run_line("num(\"1 2\")"), Err(String::from("[line 1] num() could not parse \"1 2\" as a number")));
+        assert_eq!(interpreter.run_line("num(true)"), Err(String::from("[line 1] num() expects a String, got Boolean")));
+    }
+
+    #[test]
+    fn math_natives() {
+        let mut interpreter = Interpreter::new();
+
+        assert_eq!(interpreter.run_line("sqrt(9) == 3"), Ok(Some(LiteralValue::True)));
+        assert_eq!(interpreter.run_line("sqrt(2.25)"), Ok(Some(LiteralValue::FloatValue(1.5))));
+        assert_eq!(interpreter.run_line("abs(-4)"), Ok(Some(LiteralValue::IntValue(4))));
+        assert_eq!(interpreter.run_line("abs(-4.5)"), Ok(Some(LiteralValue::FloatValue(4.5))));
+        assert_eq!(interpreter.run_line("floor(2.7)"), Ok(Some(LiteralValue::FloatValue(2.0))));
+        assert_eq!(interpreter.run_line("ceil(2.2)"), Ok(Some(LiteralValue::FloatValue(3.0))));
+        assert_eq!(interpreter.run_line("round(-2.5)"), Ok(Some(LiteralValue::FloatValue(-3.0))));
+        assert_eq!(interpreter.run_line("round(7)"), Ok(Some(LiteralValue::IntValue(7))));
+        assert_eq!(interpreter.run_line("pow(2, 10)"), Ok(Some(LiteralValue::IntValue(1024))));
+        assert_eq!(interpreter.run_line("pow(4, 0.5)"), Ok(Some(LiteralValue::FloatValue(2.0))));
+        assert_eq!(interpreter.run_line("min(3, 2.5)"), Ok(Some(LiteralValue::FloatValue(2.5))));
+        assert_eq!(interpreter.run_line("max(3, 2.5)"), Ok(Some(LiteralValue::IntValue(3))));
+    }
+
+    #[test]
+    fn math_natives_validate_arguments() {
+        let mut interpreter = Interpreter::new();
+
+        assert_eq!(interpreter.run_line("abs()"), Err(String::from("[line 1] Expected 1 arguments but got 0")));
+        assert_eq!(interpreter.run_line("max(1)"), Err(String::from("[line 1] Expected 2 arguments but got 1")));
+        assert_eq!(interpreter.run_line("floor(\"a\")"), Err(String::from("[line 1] floor() expects a number, got String")));
+        assert_eq!(interpreter.run_line("sqrt(-1)"), Err(String::from("[line 1] sqrt() of a negative number: -1")));
+        assert_eq!(interpreter.run_line("pow(2, 64)"), Err(String::from("[line 1] Integer overflow")));
+        assert_eq!(interpreter.run_line("pow(0, -1)"), Err(String::from("[line 1] Division by zero")));
+    }
+
+    #[test]
+    fn step_limit_stops_infinite_loop() {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_step_limit(1000);
+
+        assert_eq!(interpreter.run_line("while (true) {}"), Err(String::from("Execution step limit exceeded")));
+
+        interpreter.set_step_limit(1000);
+        assert_eq!(interpreter.run_line("let total = 0; for (let i = 0; i < 10; i += 1) { total += i; }"), Ok(None));
+        assert_eq!(interpreter.run_line("total"), Ok(Some(LiteralValue::IntValue(45))));
+
+        interpreter.set_step_limit(0);
+        assert_eq!(interpreter.run_line("let i = 0; while (i < 1000) { i += 1; }"), Ok(None));
+    }
+
+    #[test]
+    fn floor_division_rounds_down() {
+        let mut interpreter = Interpreter::new();
+
+        assert_eq!(interpreter.run_line("7 ~/ 2"), Ok(Some(LiteralValue::IntValue(3))));
+        assert_eq!(interpreter.run_line("-7 ~/ 2"), Ok(Some(LiteralValue::IntValue(-4))));
+        assert_eq!(interpreter.run_line("7.5 ~/ 2"), Ok(Some(LiteralValue::FloatValue(3.0))));
+        assert_eq!(interpreter.run_line("1 ~/ 0"), Err(String::from("[line 1] Division by zero")));
+    }
+
+    #[test]
+    fn integer_overflow_is_an_error() {
+        let mut interpreter = Interpreter::new();
+
+        assert_eq!(interpreter.run_line("3037000500 * 3037000500"), Err(String::from("[line 1] Integer overflow")));
+        assert_eq!(interpreter.run_line("-9223372036854775807 - 2"), Err(String::from("[line 1] Integer overflow")));
+        assert_eq!(interpreter.run_line("2 ** 63"), Err(String::from("[line 1] Integer overflow")));
+        assert_eq!(interpreter.run_line("3037000499 * 3037000499"), Ok(Some(LiteralValue::IntValue(9223372030926249001))));
+    }
+
+    #[test]
+    fn integer_overflow_can_promote() {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_integer_overflow(IntegerOverflow::Promote);
+
+        assert_eq!(interpreter.run_line("fn square(x) { return x * x; }"), Ok(None));
+        assert_eq!(interpreter.run_line("square(3037000500)"), Ok(Some(LiteralValue::FloatValue(3037000500.0 * 3037000500.0))));
+        assert_eq!(interpreter.run_line("2 ** 64"), Ok(Some(LiteralValue::FloatValue(18446744073709551616.0))));
+    }
+
+    #[test]
+    fn let_declares_several_names() {
+        let mut interpreter = Interpreter::new();
+
+        assert_eq!(interpreter.run_line("let a = 1, b = a + 1, c;"), Ok(None));
+        assert_eq!(interpreter.run_line("a"), Ok(Some(LiteralValue::IntValue(1))));
+        assert_eq!(interpreter.run_line("b"), Ok(Some(LiteralValue::IntValue(2))));
+        assert_eq!(interpreter.run_line("c"), Err(String::from("[line 1] Variable 'c' used before assignment")));
+    }
+
+    #[test]
+    fn uninitialized_variable_read_before_assignment() {
+        let mut interpreter = Interpreter::new();
+
+        assert_eq!(interpreter.run_line("let x;"), Ok(None));
+        assert_eq!(interpreter.run_line("echo x;"), Err(String::from("[line 1] Variable 'x' used before assignment")));
+        assert_eq!(interpreter.run_line("fn f() { return x; } x = 1;"), Ok(None));
+        assert_eq!(interpreter.run_line("f()"), Ok(Some(LiteralValue::IntValue(1))));
+    }
+
+    #[test]
+    fn explicit_null_is_initialized() {
+        let mut interpreter = Interpreter::new();
+
+        assert_eq!(interpreter.run_line("let x = null;"), Ok(None));
+        assert_eq!(interpreter.run_line("x"), Ok(Some(LiteralValue::Null)));
+    }
+
+    #[test]
+    fn null_coalescing_short_circuits() {
+        let output = capture("
+            fn loud() { echo \"evaluated\"; return 5; }
+            let m = {\"a\": 1};
+            echo null ?? 5, 0 ?? loud(), false ?? loud(), m[\"b\"] ?? null ?? 3;
+            echo null ?? loud();
+        ");
+
+        assert_eq!(output, "5 0 false 3\nevaluated\n5\n");
+    }
+
+    #[test]
+    fn run_line_checks_annotations() {
+        let mut interpreter = Interpreter::new();
+
+        assert_eq!(interpreter.run_line("let x: int = \"three\";"), Err(String::from("[line 1] Can not assign String to 'x' of type Int")));
+        assert_eq!(interpreter.run_line("let x: int = 3;"), Ok(None));
+        assert_eq!(interpreter.run_line("x"), Ok(Some(LiteralValue::IntValue(3))));
+    }
+
+    #[test]
+    fn const_can_not_be_reassigned() {
+        let mut interpreter = Interpreter::new();
+
+        assert_eq!(interpreter.run_line("const x = 1;"), Ok(None));
+        assert_eq!(interpreter.run_line("x = 2;"), Err(String::from("[line 1] Cannot assign to constant 'x'")));
+        assert_eq!(interpreter.run_line("x += 2;"), Err(String::from("[line 1] Cannot assign to constant 'x'")));
+        assert_eq!(interpreter.run_line("x"), Ok(Some(LiteralValue::IntValue(1))));
+    }
+
+    #[test]
+    fn const_can_be_shadowed() {
+        let output = capture("const x = 1; { let x = 2; x = 3; echo x; } { const x = 4; echo x; } echo x;");
+
+        assert_eq!(output, "3\n4\n1\n");
+    }
+
+    #[test]
+    fn match_runs_first_matching_arm() {
+        let output = capture("
+            fn describe(x) {
+                match (x) {
+                    1 => echo \"one\";
+                    1, 2, 3 => echo \"few\";
+                    _ => echo \"many\";
+                }
+            }
+            describe(1);
+            describe(3);
+            describe(10);
+            match (\"a\") { \"b\" => echo \"b\"; }
+        ");
+
+        assert_eq!(output, "one\nfew\nmany\n");
+    }
+
+    #[test]
+    fn closure_keeps_resolved_binding() {
+        let output = capture("
+            let a = \"global\";
+            {
+                fn show() { echo a; }
+                show();
+                let a = \"block\";
+                show();
+                echo a;
+            }
+        ");
+
+        assert_eq!(output, "global\nglobal\nblock\n");
+    }
+
+    #[test]
+    fn closures_over_same_name_are_distinct() {
+        let output = capture("
+            fn counter() {
+                let count = 0;
+                return fn() { count += 1; return count; };
+            }
+            let first = counter();
+            let second = counter();
+            first();
+            first();
+            echo first(), second();
+            for (x in [1, 2]) {
+                let (a, b) = (x, 0);
+                fn swap() { (a, b) = (b, a); }
+                swap();
+                echo a, b;
+            }
+        ");
+
+        assert_eq!(output, "3 1\n0 1\n0 2\n");
+    }
+
+    #[test]
+    fn tuple_destructure() {
+        let output = capture("
+            let (a, b) = (1, 2);
+            echo a, b;
+            (a, b) = (b, a);
+            echo a, b;
+            let (x, (y, z)) = [\"x\", (true, null)];
+            echo x, y, z, (1, \"two\");
+        ");
+
+        assert_eq!(output, "1 2\n2 1\nx true null (1, two)\n");
+    }
+
+    #[test]
+    fn tuple_destructure_arity_mismatch() {
+        let mut interpreter = Interpreter::new();
+
+        assert_eq!(interpreter.run_line("let (a, b) = (1, 2, 3);"), Err(String::from("[line 1] Expected 2 values to destructure but got 3")));
+        assert_eq!(interpreter.run_line("let (a, b) = 1;"), Err(String::from("[line 1] Can only destructure Tuples and Arrays, got Int")));
+    }
+
+    #[test]
+    fn for_in_sums_an_array() {
+        let output = capture("
+            let sum = 0;
+            for (x in [10, 20, 30]) sum += x;
+            echo sum;
+            for (i, x in [10, 20]) { if (i == 0) continue; echo i, x; }
+            for (k, v in { \"b\": 2, \"a\": 1 }) echo k, v;
+            for (v in { 1: \"one\" }) echo v;
+        ");
+
+        assert_eq!(output, "60\n1 20\na 1\nb 2\none\n");
+    }
+
+    #[test]
+    fn for_in_non_iterable() {
+        let mut interpreter = Interpreter::new();
+
+        assert_eq!(interpreter.run_line("for (x in 5) echo x;"), Err(String::from("[line 1] Can only iterate over Arrays and Maps, got Int")));
+    }
+
+    #[test]
+    fn spread_in_arrays_and_calls() {
+        let output = capture("
+            let rest = [2, 3];
+            echo [1, ...rest, 4, ...[]], [...(5, 6)];
+            fn add3(a, b, c) { return a + b + c; }
+            echo add3(...rest, 10), add3(...[1, 2, 3]), max(...rest);
+        ");
+
+        assert_eq!(output, "[1, 2, 3, 4] [5, 6]\n15 6 3\n");
+    }
+
+    #[test]
+    fn spread_errors() {
+        let mut interpreter = Interpreter::new();
+        interpreter.run_line("fn add3(a, b, c) { return a + b + c; }").unwrap();
+
+        assert_eq!(interpreter.run_line("[...1]"), Err(String::from("[line 1] Can only spread Arrays and Tuples, got Int")));
+        assert_eq!(interpreter.run_line("add3(...[1, 2])"), Err(String::from("[line 1] Expected 3 arguments but got 2")));
+    }
+
+    #[test]
+    fn variadic_sum() {
+        let output = capture("
+            fn sum(...nums) {
+                let total = 0;
+                for (let i = 0; i < len(nums); i += 1) total += nums[i];
+                return total;
+            }
+            let rest = fn(first, ...others) => others;
+            echo sum(), sum(1), sum(1, 2, 3), rest(1), rest(1, 2, 3);
+        ");
+
+        assert_eq!(output, "0 1 6 [] [2, 3]\n");
+    }
+
+    #[test]
+    fn variadic_needs_its_fixed_arguments() {
+        let mut interpreter = Interpreter::new();
+        interpreter.run_line("fn f(a, ...rest) {}").unwrap();
+
+        assert_eq!(interpreter.run_line("f()"), Err(String::from("[line 1] Expected at least 1 arguments but got 0")));
+    }
+
+    #[test]
+    fn lambda_assigned_and_called() {
+        let output = capture("
+            let double = fn(a) { return a * 2; };
+            let add = fn(a, b) => a + b;
+            let n = 10;
+            let plus_n = fn(a) => a + n;
+            fn apply(f, x) { return f(x); }
+            echo double(4), add(1, 2), apply(plus_n, 5), fn(x) => x;
+        ");
+
+        assert_eq!(output, "8 3 15 lambda/1\n");
+    }
+}
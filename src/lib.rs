@@ -0,0 +1,28 @@
+#![allow(
+    clippy::needless_return,
+    clippy::redundant_field_names,
+    clippy::needless_late_init,
+    clippy::inherent_to_string
+)]
+
+pub mod tokenizer;
+
+pub mod expr;
+
+pub mod parser;
+
+pub mod interpreter;
+
+pub mod stmt;
+
+pub mod environment;
+
+pub mod error;
+
+pub mod interner;
+
+pub mod checker;
+
+pub mod resolver;
+
+pub mod formatter;
@@ -1,33 +1,37 @@
-mod tests;
-
-mod tokenizer;
-use crate::tokenizer::*;
-
-mod expr;
-
-mod parser;
-use crate::parser::*;
-
-mod interpreter;
-use crate::interpreter::*;
+#![allow(
+    clippy::needless_return,
+    clippy::module_inception
+)]
 
-mod stmt;
+mod tests;
 
-mod environment;
+use zawa::tokenizer::*;
+use zawa::parser::*;
+use zawa::interpreter::*;
+use zawa::checker::*;
+use zawa::resolver::*;
 
 use std::env;
 use std::process::exit;
 use std::fs;
-use std::io::{self, BufRead, Write};
+use std::io::{self, Write};
 
 fn run(interpreter: &mut Interpreter, contents: &str) -> Result<(), String> {
     let mut tokenizer = Tokenizer::new(contents);
-    let tokens = tokenizer.tokenize()?;
+    let tokens = tokenizer.tokenize()
+        .map_err(|errors| errors.iter().map(|err| err.display_with_source(contents)).collect::<Vec<String>>().join("\n"))?;
 
     let mut parser = Parser::new(tokens);
-    let stmts = parser.parse()?;
+    let stmts = parser.parse()
+        .map_err(|errors| errors.iter().map(|err| err.display_with_source(contents)).collect::<Vec<String>>().join("\n"))?;
+
+    TypeChecker::new().check(&stmts)
+        .map_err(|errors| errors.iter().map(|err| err.display_with_source(contents)).collect::<Vec<String>>().join("\n"))?;
+
+    Resolver::new().resolve(&stmts)
+        .map_err(|errors| errors.iter().map(|err| err.display_with_source(contents)).collect::<Vec<String>>().join("\n"))?;
     
-    interpreter.interpret(stmts.iter().collect())?;
+    interpreter.interpret(stmts.iter().collect()).map_err(|err| err.display_with_source(contents))?;
 
     return Ok(());
 }
@@ -51,10 +55,9 @@ fn run_prompt() -> Result<(), String> {
             Err(_) => return Err("Couldn't flush stdout".to_string()),
         }
 
+        // Not holding the stdin lock past this line, input() needs it too
         let mut buffer = String::new();
-        let stdin = io::stdin();
-        let mut handle = stdin.lock();
-        match handle.read_line(&mut buffer) {
+        match io::stdin().read_line(&mut buffer) {
             Ok(n) => {
                 if n <= 2 {
                     return Ok(());
@@ -63,8 +66,9 @@ fn run_prompt() -> Result<(), String> {
             Err(_) => return Err("Couldn't read line".to_string()),
         }
 
-        match run(&mut interpreter, &buffer) {
-            Ok(_) => (),
+        match interpreter.run_line(&buffer) {
+            Ok(Some(value)) => println!("{}", value.to_string()),
+            Ok(None) => (),
             Err(msg) => println!("Error: {}", msg),
         }
     }
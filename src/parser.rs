@@ -1,599 +1,2582 @@
-/*
-grammar
-
-program -> {
-    declaration*,
-    Eof
-}
-
-
-declaration -> {
-    letDecl | statement
-}
-
-statement -> {
-    exprStmt |
-    echoStmt |
-    block |
-    ifStmt |
-    whileStmt |
-    forStmt |
-    breakStmt
-}
-
-breakStmt -> {
-    "break" ";"
-}
-
-forStmt -> {
-    "for" "("
-    ( letDecl | exprStmt | ";") 
-    expression? ";"
-    expression? ")"
-    statement
-}
-
-whileStmt -> {
-    "while" "(" expression ")" statement
-}
-
-ifStmt -> {
-    "if (" expression ")" statement ("else" statement)?
-}
-
-block -> {
-    "{" declaration* "}"
-
-exprStmt -> {
-    expression ";"
-}
-
-echoStmt -> {
-    "echo" expression ";"
-}
-
-letDecl -> {
-    "let" IDENTIFIER ("=" expression)? ";"
-}
-
-expression -> {
-    assignment
-}
-
-assignment -> {
-    IDENTIFIER "=" assignment | logic_or
-}
-
-logic_or -> {
-    logic_and ("or" logic_and)*
-}
-
-logic_and -> {
-    equality ("and" equality)*
-}
-
-literal -> {
-    NUMBER | STRING |
-    "true" | "false" | "null"
-}
-
-primary -> {
-    "true" | "false" | "null" |
-    NUMBER | STRING |
-    "(" expression ")" |
-    IDENTIFIER
-}
-
-grouping -> {
-    "(" expression ")"
-}
-
-unary -> {
-    ("-" | "!") unary | call
-}
-
-call -> {
-    primary ( "(" arguments? ")" )*
-}
-
-arguments -> {
-    expression ("," expression)*
-}
-
-binary -> {
-    expression operator expression
-}
-
-operator -> {
-    "==" | "!=" | "<=" | ">=" | "<" | ">" |
-    "+" | "-" | "*" | "/"
-}
-*/
-
-use crate::tokenizer::{TokenType, Token};
-use crate::expr::{Expr, LiteralValue};
-use crate::stmt::Stmt;
-
-pub struct Parser {
-    tokens: Vec<Token>,
-    current: usize,
-}
-
-impl Parser {
-    pub fn new(tokens: Vec<Token>) -> Self {
-        Self {
-            tokens: tokens,
-            current: 0
-        }
-    }
-
-    pub fn parse(&mut self) -> Result<Vec<Stmt>, String> {
-        let mut stmts = Vec::new();
-        let mut errs = Vec::new();
-
-        while !self.is_at_end() {
-            let stmt = self.declaration();
-            match stmt {
-                Ok(s) => stmts.push(s),
-                Err(msg) => {
-                    errs.push(msg);
-                    self.synchronize();
-                },
-            }
-        }
-
-        if errs.len() == 0 {
-            Ok(stmts)
-        } else {
-            Err(errs.join("\n"))
-        }
-    }
-
-    fn declaration(&mut self) -> Result<Stmt, String> {
-        if self.match_token(TokenType::Let) {
-            match self.let_declaration() {
-                Ok(stmt) => Ok(stmt),
-                Err(msg) => Err(msg),
-            }
-        } else {
-            self.statement()
-        }
-    }
-
-    fn let_declaration(&mut self) -> Result<Stmt, String> {
-        let token = self.consume(TokenType::Identifier, "Expected variable name")?;
-
-        let initializer;
-        if self.match_token(TokenType::Equal) {
-            initializer = self.expression()?;
-        } else {
-            initializer = Expr::Literal { value: LiteralValue::Null};
-        }
-        
-        self.consume(TokenType::Semicolon, "Expected ';' after variable declaration")?;
-        Ok(Stmt::Let { name: token, initializer: initializer})
-    }
-
-    fn statement(&mut self) -> Result<Stmt, String> {
-        if self.match_token(TokenType::Echo) {
-            self.echo_statement()
-        } else if self.match_token(TokenType::LeftBrace) {
-            self.block_statement()
-        } else if self.match_token(TokenType::If) {
-            self.if_statement()
-        } else if self.match_token(TokenType::While) {
-            self.while_statement()
-        } else if self.match_token(TokenType::For) {
-            self.for_statement()
-        } else if self.match_token(TokenType::Break) {
-            self.break_statement()
-        } else {
-            self.expression_statement()
-        }
-    }
-
-    fn break_statement(&mut self) -> Result<Stmt, String> {
-        self.consume(TokenType::Semicolon, "Expected ';' after break statement")?;
-        Ok(Stmt::Break)
-    }
-
-    fn for_statement(&mut self) -> Result<Stmt, String> {
-        self.consume(TokenType::LeftParen, "Expected '(' after for")?;
-        
-        let initializer;
-        if self.match_token(TokenType::Semicolon) {
-            initializer = None;
-        } else if self.match_token(TokenType::Let) {
-            let let_decl = self.let_declaration()?;
-            initializer = Some(let_decl);
-        } else {
-            let expr = self.expression_statement()?;
-            initializer = Some(expr);
-        }
-
-        let condition;
-        if !self.check(TokenType::Semicolon) {
-            let expr = self.expression()?;
-            condition = Some(expr);
-        } else {
-            condition = None;
-        }
-
-        self.consume(TokenType::Semicolon, "Expected ';' after for loop condition")?;
-
-        let incrementer;
-        if !self.check(TokenType::RightParen) {
-            let expr = self.expression()?;
-            incrementer = Some(expr);
-        } else {
-            incrementer = None;
-        }
-        
-        self.consume(TokenType::RightParen, "Expected ')' after for loop clauses")?;
-
-        let mut body = self.statement()?;
-
-        if let Some(incr) = incrementer {
-            body = Stmt::Block {
-                statements: vec![
-                    Box::new(body),
-                    Box::new(Stmt::Expression { expression: incr })
-                ]
-            };
-        }
-
-        let cond;
-        match condition {
-            None => cond = Expr::Literal { value: LiteralValue::True },
-            Some(c) => cond = c,
-        }
-        body = Stmt::While {
-            condition: cond,
-            body: Box::new(body),
-        };
-
-        if let Some(init) = initializer {
-            body = Stmt::Block {
-                statements: vec![Box::new(init), Box::new(body)]
-            };
-        }
-
-        Ok(body)
-    }
-
-    fn while_statement(&mut self) -> Result< Stmt, String> {
-        self.consume(TokenType::LeftParen, "Expected '(' after while")?;
-        let condition = self.expression()?;
-        self.consume(TokenType::RightParen, "Expected ')' after while loop condition")?;
-        let body = Box::new(self.statement()?);
-
-        Ok(Stmt::While { condition: condition, body: body})
-    }
-
-    fn if_statement(&mut self) -> Result<Stmt, String> {
-        self.consume(TokenType::LeftParen, "Expected '(' after 'if'")?;
-        let predicate = self.expression()?;
-        self.consume(TokenType::RightParen, "Expected ')' after if predicate")?;
-
-        let then = Box::new(self.statement()?);
-
-        let otherwise =  if self.match_token(TokenType::Else) {
-            let stmt = self.statement()?;
-            Some(Box::new(stmt))
-        } else {
-            None
-        };
-
-        Ok(Stmt::If { predicate: predicate, then: then, otherwise: otherwise})
-    }
-
-    fn block_statement(&mut self) -> Result<Stmt, String> {
-        let mut statements = Vec::new();
-
-        while !self.check(TokenType::RightBrace) && !self.is_at_end() {
-            let decl = self.declaration()?;
-            statements.push(Box::new(decl));
-        }
-
-        self.consume(TokenType::RightBrace, "Expected '}' after block")?;
-        Ok(Stmt::Block { statements })
-    }
-
-    fn echo_statement(&mut self) -> Result<Stmt, String> {
-        let value = self.expression()?;
-        self.consume(TokenType::Semicolon, "Expected ';' after value")?;
-        Ok(Stmt::Echo { expression: value })
-    }
-
-    fn expression_statement(&mut self) -> Result<Stmt, String> {
-        let expr = self.expression()?;
-        self.consume(TokenType::Semicolon, "Expected ';' after expression")?;
-        Ok(Stmt::Expression { expression: expr })
-    }
-
-    fn expression(&mut self) -> Result<Expr, String> {
-        self.assignment()
-    }
-
-    fn assignment(&mut self) -> Result<Expr, String> {
-        let expr = self.or()?;
-
-        if self.match_token(TokenType::Equal) {
-            let value = self.assignment()?;
-
-            match expr {
-                Expr::Variable { name } => Ok(Expr::Assign { name: name, value: Box::from(value) }),
-                _ => Err(format!("Invalid assingment target"))
-            }
-        } else {
-            return Ok(expr);
-        }
-    }
-
-    fn or(&mut self) -> Result<Expr, String> {
-        let mut expr = self.and()?;
-
-        while self.match_token(TokenType::Or) {
-            let operator = self.previous();
-            let right = self.and()?;
-            expr = Expr::Logical { left: Box::from(expr), operator, right: Box::from(right) };
-        }
-        Ok(expr)
-    }
-
-    fn and(&mut self) -> Result<Expr, String> {
-        let mut expr = self.equality()?;
-
-        while self.match_token(TokenType::And) {
-            let operator = self.previous();
-            let right = self.equality()?;
-            expr = Expr::Logical { left: Box::from(expr), operator: operator, right:Box::from(right) };
-        }
-
-        Ok(expr)
-    }
-
-    fn equality(&mut self) -> Result<Expr, String> {
-        let mut expr = self.comparison()?;
-        let mut matches_eq = self.match_tokens(&[TokenType::BangEqual, TokenType::EqualEqual]);
-        while matches_eq {
-            let operator = self.previous();
-            let right = self.comparison()?;
-            expr = Expr::Binary {
-                left: Box::from(expr),
-                operator: operator,
-                right: Box::from(right)
-            };
-
-            matches_eq = self.match_tokens(&[TokenType::BangEqual, TokenType::EqualEqual]);
-        }
-
-        Ok(expr)
-    }
-
-    fn comparison(&mut self) -> Result<Expr, String> {
-        let mut expr = self.term()?;
-
-        while self.match_tokens(&[TokenType::Greater, TokenType::GreaterEqual , TokenType::Less, TokenType::LessEqual]) {
-            let op = self.previous();
-            let right = self.term()?;
-            expr = Expr::Binary {
-                left: Box::from(expr),
-                operator: op,
-                right: Box::from(right)
-            }
-        }
-
-        Ok(expr)
-    }
-
-    fn term(&mut self) -> Result<Expr, String> {
-        let mut expr = self.factor()?;
-
-        while self.match_tokens(&[TokenType::Minus, TokenType::Plus]) {
-            let op = self.previous();
-            let right = self.factor()?;
-            expr = Expr::Binary {
-                left: Box::from(expr),
-                operator: op,
-                right: Box::from(right)
-            }
-        }
-
-        Ok(expr)
-    }
-
-    fn factor(&mut self) -> Result<Expr, String> {
-        let mut expr = self.unary()?;
-
-        while self.match_tokens(&[TokenType::Slash, TokenType::Star]) {
-            let op = self.previous();
-            let right = self.unary()?;
-            expr = Expr::Binary {
-                left: Box::from(expr),
-                operator: op,
-                right: Box::from(right)
-            }
-        }
-
-        Ok(expr)
-    }
-
-    fn unary(&mut self) -> Result<Expr, String> {
-        if self.match_tokens(&[TokenType::Bang, TokenType::Minus]) {
-            let op = self.previous();
-            let right = self.unary()?;
-            Ok(Expr::Unary {
-                operator: op,
-                right: Box::from(right)
-            })
-        } else {
-            self.call()
-        }
-    }
-
-    fn call(&mut self) -> Result<Expr, String> {
-        let mut expr = self.primary()?;
-
-        loop {
-            if self.match_token(TokenType::LeftParen) {
-                expr = self.finish_call(expr)?;
-            } else {
-                break;
-            }
-        }
-
-        Ok(expr)
-    }
-
-    fn finish_call(&mut self, callee: Expr) -> Result<Expr, String> {
-        let mut arguments = Vec::new();
-        
-        if !self.check(TokenType::RightParen) {
-            loop {
-                let arg = self.expression()?;
-                arguments.push(arg);
-                if arguments.len() > 256 {
-                    let location = self.peek().line_number;
-                    return Err(format!("line: {}, Can not have more than 256 function arguments", location));
-                }
-
-                if !self.match_token(TokenType::Comma) {
-                    break;
-                }
-            }
-        }
-        let paren = self.consume(TokenType::RightParen, "Expected ')' after arguments")?;
-
-        Ok(Expr::Call { callee: Box::new(callee), paren: paren, arguments: arguments })
-    }
-
-    fn primary(&mut self) -> Result<Expr, String> {
-        let token = self.peek();
-        
-        let result;
-        match token.token_type {
-            TokenType::LeftParen => {
-                self.advance();
-                let expr = self.expression()?;
-                self.consume(TokenType::RightParen, "Expected ')'")?;
-                result = Expr::Grouping {
-                    expression: Box::from(expr)
-                };
-            },
-            TokenType::True | TokenType::False | TokenType::Null |  TokenType::Number | TokenType::StringLit => {
-                self.advance();
-                result = Expr::Literal {
-                    value: LiteralValue::from_token(token.clone())
-                };
-            },
-            TokenType::Identifier => {
-                self.advance();
-                result = Expr::Variable { name: self.previous() };
-            }
-            _ => {
-                return Err(String::from("Expected expression"));
-            },
-        }
-
-        //self.advance();
-
-        Ok(result)
-    }
-
-    fn consume(&mut self, token_type: TokenType, msg: &str) -> Result<Token, String> {
-        let token = self.peek();
-        if token.token_type == token_type {
-            self.advance();
-            let token = self.previous();
-            Ok(token)
-        } else {
-            Err(String::from(msg))
-        }
-    }
-
-    fn check(&mut self, type_: TokenType) -> bool {
-        self.peek().token_type == type_
-    }
-
-    fn match_token(&mut self, type_: TokenType) -> bool {
-        if self.is_at_end() {
-            false
-        } else {
-            if self.peek().token_type == type_ {
-                self.advance();
-                true
-            } else {
-                false
-            }
-        }
-    }
-
-    fn match_tokens(&mut self, types: &[TokenType]) -> bool {
-        for type_ in types {
-            if self.match_token(*type_) {
-                return true;
-            }
-        }
-
-        false
-    }
-
-    fn advance(&mut self) -> Token {
-        if !self.is_at_end() {
-            self.current += 1
-        }
-        self.previous()
-    }
-
-    fn peek(&mut self) -> Token {
-        self.tokens[self.current].clone()
-    }
-
-    fn previous(&mut self) -> Token {
-        self.tokens[self.current - 1].clone()
-    }
-
-    fn is_at_end(&mut self) -> bool {
-        self.peek().token_type == TokenType::Eof
-    }
-
-    fn synchronize(&mut self) {
-        self.advance();
-
-        while !self.is_at_end() {
-            if self.previous().token_type == TokenType::Semicolon {
-                return;
-            }
-            match self.peek().token_type {
-                TokenType::Class | TokenType::Fn | TokenType::Let |
-                TokenType::For | TokenType::If | TokenType::While |
-                TokenType::Echo | TokenType::Return => return,
-                _ => (),
-            }
-            self.advance();
-        }
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::tokenizer::Tokenizer;
-
-    #[test]
-    fn test_equality_with_paren() {
-        let src = "1 == (2 + 3);";
-        
-        let mut tokenizer = Tokenizer::new(src);
-        
-        let tokens = tokenizer.tokenize().unwrap();
-        
-        let mut parser = Parser::new(tokens);
-        
-        let parsed_expr = parser.parse().unwrap();
-        let string_expr = parsed_expr[0].to_string();
-
-        assert_eq!(string_expr, "(== 1 (group (+ 2 3)))")
-    }
-}
\ No newline at end of file
+/*
+grammar
+
+program -> {
+    declaration*,
+    Eof
+}
+
+
+declaration -> {
+    classDecl | funDecl | letDecl | statement
+}
+
+classDecl -> {
+    "class" IDENTIFIER ("<" IDENTIFIER)? "{" funDecl* "}"
+}
+
+funDecl -> {
+    "fn" IDENTIFIER "(" parameters? ")" block
+}
+
+parameters -> {
+    IDENTIFIER ("," IDENTIFIER)* ("," "..." IDENTIFIER)? ","? |
+    "..." IDENTIFIER ","?
+}
+
+statement -> {
+    exprStmt |
+    echoStmt |
+    printStmt |
+    block |
+    ifStmt |
+    matchStmt |
+    whileStmt |
+    doWhileStmt |
+    forStmt |
+    breakStmt |
+    continueStmt |
+    returnStmt |
+    assertStmt |
+    throwStmt |
+    tryStmt
+}
+
+// Any value can be thrown, it unwinds to the nearest enclosing try
+throwStmt -> {
+    "throw" expression ";"
+}
+
+// Only thrown values are caught, runtime errors still stop the program
+tryStmt -> {
+    "try" block "catch" "(" IDENTIFIER ")" block
+}
+
+// The statement fails with a runtime error when the expression is falsy
+assertStmt -> {
+    "assert" expression ("," expression)? ";"
+}
+
+returnStmt -> {
+    "return" expression? ";"
+}
+
+// break and continue are only valid inside a loop body
+breakStmt -> {
+    "break" ";"
+}
+
+continueStmt -> {
+    "continue" ";"
+}
+
+forStmt -> {
+    "for" "("
+    ( letDecl | exprStmt | ";") 
+    expression? ";"
+    expression? ")"
+    statement |
+    "for" "(" IDENTIFIER ("," IDENTIFIER)? "in" expression ")" statement
+}
+
+whileStmt -> {
+    "while" "(" expression ")" statement
+}
+
+// The body runs once before the condition is first checked
+doWhileStmt -> {
+    "do" statement "while" "(" expression ")" ";"
+}
+
+ifStmt -> {
+    "if (" expression ")" statement ("else" statement)?
+}
+
+// Arms are tried in order, "_" is the arm for no match and has to be the last one
+matchStmt -> {
+    "match" "(" expression ")" "{"
+    ( expression ("," expression)* "=>" statement )*
+    ( "_" "=>" statement )?
+    "}"
+}
+
+block -> {
+    "{" declaration* "}"
+
+exprStmt -> {
+    expression ";"
+}
+
+echoStmt -> {
+    "echo" expression ("," expression)* ";"
+}
+
+// println ends the value with a newline, print does not
+printStmt -> {
+    ("print" | "println") expression ";"
+}
+
+letDecl -> {
+    "let" binding ("," binding)* ";" |
+    "let" pattern "=" expression ";"
+}
+
+pattern -> {
+    "(" ( IDENTIFIER | pattern ) ("," ( IDENTIFIER | pattern ))* ")"
+}
+
+binding -> {
+    IDENTIFIER (":" IDENTIFIER)? ("=" expression)?
+}
+
+constDecl -> {
+    "const" IDENTIFIER "=" expression ";"
+}
+
+expression -> {
+    assignment
+}
+
+assignment -> {
+    (call ".")? IDENTIFIER "=" assignment |
+    call "[" expression "]" "=" assignment |
+    tuple "=" assignment |
+    ternary
+
+    "+=", "-=", "*=" and "/=" are accepted in place of "=" and desugar to "target = target op value"
+}
+
+ternary -> {
+    coalesce ("?" expression ":" ternary)?
+}
+
+// The right side is only evaluated when the left one is null
+coalesce -> {
+    logic_or ("??" logic_or)*
+}
+
+logic_or -> {
+    logic_and ("or" logic_and)*
+}
+
+logic_and -> {
+    equality ("and" equality)*
+}
+
+equality -> {
+    comparison (("==" | "!=") comparison)*
+}
+
+// "a < b < c" is "a < b && b < c" with b evaluated once, a false comparison skips the operands after it
+comparison -> {
+    bit_or ((">" | ">=" | "<" | "<=") bit_or)*
+}
+
+// Bitwise operators bind tighter than comparisons, so "x & 1 == 0" is "(x & 1) == 0"
+bit_or -> {
+    bit_xor ("|" bit_xor)*
+}
+
+bit_xor -> {
+    bit_and ("^" bit_and)*
+}
+
+bit_and -> {
+    shift ("&" shift)*
+}
+
+shift -> {
+    term (("<<" | ">>") term)*
+}
+
+term -> {
+    factor (("+" | "-") factor)*
+}
+
+literal -> {
+    NUMBER | STRING |
+    "true" | "false" | "null"
+}
+
+primary -> {
+    "true" | "false" | "null" |
+    NUMBER | STRING | INTERPOLATION |
+    "[" (element ("," element)* ","?)? "]" |
+    "{" (expression ":" expression ("," expression ":" expression)* ","?)? "}" |
+    "(" expression ")" |
+    tuple |
+    IDENTIFIER | "self" | "super" "." IDENTIFIER |
+    lambda
+}
+
+// The arrow form returns its expression
+lambda -> {
+    "fn" "(" parameters? ")" ( block | "=>" expression )
+}
+
+grouping -> {
+    "(" expression ")"
+}
+
+tuple -> {
+    "(" expression ("," expression)+ ")"
+}
+
+factor -> {
+    power (("*" | "/" | "%" | "~/") power)*
+}
+
+power -> {
+    unary ("**" power)?
+}
+
+unary -> {
+    ("-" | "!" | "~") unary | call
+}
+
+call -> {
+    primary ( "(" arguments? ")" | "." IDENTIFIER | "[" expression "]" | "[" expression? ":" expression? "]" )*
+}
+
+arguments -> {
+    element ("," element)* ","?
+}
+
+element -> {
+    "..."? expression
+}
+
+binary -> {
+    expression operator expression
+}
+
+operator -> {
+    "==" | "!=" | "<=" | ">=" | "<" | ">" |
+    "+" | "-" | "*" | "/" | "%" | "**" |
+    "&" | "|" | "^" | "<<" | ">>"
+}
+*/
+
+use crate::tokenizer::{self, TokenType, Token, InterpolationPart, Span};
+use crate::expr::{Depth, Expr, LiteralValue, Pattern};
+use crate::stmt::{Stmt, Binding};
+use crate::error::CorrodeError;
+
+pub struct Parser {
+    tokens: Vec<Token>,
+    current: usize,
+    function_depth: usize,
+    class_depth: usize,
+    loop_depth: usize,
+    // How deeply the code being parsed nests, capped so recursion can not overflow the stack
+    nesting: usize,
+    max_nesting: usize,
+    // parse stops after this many errors, later ones are usually caused by the first
+    max_errors: usize,
+    // Errors that do not stop the statement they are found in, like 'self' outside of a class
+    errors: Vec<CorrodeError>,
+}
+
+// Every level of an expression recurses through the whole precedence chain, which takes close to 100 KiB
+// of stack in a debug build. 16 levels leave room to spare on a 2 MiB thread, like the ones tests run on
+const DEFAULT_MAX_NESTING: usize = 16;
+const DEFAULT_MAX_ERRORS: usize = 20;
+
+impl Parser {
+    pub fn new(tokens: Vec<Token>) -> Self {
+        Self {
+            tokens: tokens,
+            current: 0,
+            function_depth: 0,
+            class_depth: 0,
+            loop_depth: 0,
+            nesting: 0,
+            max_nesting: DEFAULT_MAX_NESTING,
+            max_errors: DEFAULT_MAX_ERRORS,
+            errors: Vec::new(),
+        }
+    }
+
+    pub fn set_max_errors(&mut self, max_errors: usize) {
+        self.max_errors = max_errors;
+    }
+
+    pub fn set_max_nesting(&mut self, max_nesting: usize) {
+        self.max_nesting = max_nesting;
+    }
+
+    pub fn parse(&mut self) -> Result<Vec<Stmt>, Vec<CorrodeError>> {
+        let mut stmts = Vec::new();
+        let mut errs = Vec::new();
+
+        while !self.is_at_end() {
+            let stmt = self.declaration();
+            errs.append(&mut self.errors);
+            match stmt {
+                Ok(s) => stmts.push(s),
+                Err(msg) => {
+                    errs.push(msg);
+                    self.synchronize();
+                },
+            }
+
+            if errs.len() > self.max_errors {
+                let line = errs[self.max_errors].line();
+                errs.truncate(self.max_errors);
+                errs.push(CorrodeError::parse(line, String::from("... (too many errors)")));
+                break;
+            }
+        }
+
+        if errs.is_empty() {
+            Ok(stmts)
+        } else {
+            Err(errs)
+        }
+    }
+
+    // Parses the whole input as a single expression without a trailing ';', as typed into the REPL
+    pub fn parse_expression(&mut self) -> Result<Expr, CorrodeError> {
+        let expr = self.expression()?;
+        if !self.errors.is_empty() {
+            return Err(self.errors.remove(0));
+        }
+
+        if !self.is_at_end() {
+            let token = self.peek();
+            return Err(CorrodeError::parse(token.line_number, format!("Expected end of expression, found '{}'", token.lexeme)).with_column(token.column));
+        }
+
+        Ok(expr)
+    }
+
+    fn declaration(&mut self) -> Result<Stmt, CorrodeError> {
+        // `fn (` starts a lambda, which is an expression
+        if self.check(TokenType::Fn) && self.check_ahead(1, TokenType::LeftParen) {
+            self.statement()
+        } else if self.match_token(TokenType::Class) {
+            self.class_declaration()
+        } else if self.match_token(TokenType::Fn) {
+            self.function()
+        } else if self.match_token(TokenType::Let) {
+            match self.let_declaration() {
+                Ok(stmt) => Ok(stmt),
+                Err(msg) => Err(msg),
+            }
+        } else if self.match_token(TokenType::Const) {
+            self.const_declaration()
+        } else {
+            self.statement()
+        }
+    }
+
+    fn class_declaration(&mut self) -> Result<Stmt, CorrodeError> {
+        let start = self.previous().span();
+        let name = self.consume(TokenType::Identifier, "Expected class name")?;
+
+        let superclass = if self.match_token(TokenType::Less) {
+            let superclass = self.consume(TokenType::Identifier, "Expected superclass name")?;
+            if superclass.lexeme == name.lexeme {
+                return Err(CorrodeError::parse(superclass.line_number, String::from("A class can not inherit from itself")).with_column(superclass.column));
+            }
+            Some(Expr::Variable { span: superclass.span(), name: superclass, depth: Depth::default() })
+        } else {
+            None
+        };
+
+        self.consume(TokenType::LeftBrace, "Expected '{' before class body")?;
+
+        self.class_depth += 1;
+        let methods = self.class_methods();
+        self.class_depth -= 1;
+        let methods = methods?;
+
+        self.consume(TokenType::RightBrace, "Expected '}' after class body")?;
+        Ok(Stmt::Class { name: name, superclass: superclass, methods: methods, span: self.span_from(start) })
+    }
+
+    fn class_methods(&mut self) -> Result<Vec<Stmt>, CorrodeError> {
+        let mut methods = Vec::new();
+        while !self.check(TokenType::RightBrace) && !self.is_at_end() {
+            self.consume(TokenType::Fn, "Expected 'fn' before method declaration")?;
+            methods.push(self.function()?);
+        }
+
+        Ok(methods)
+    }
+
+    fn function(&mut self) -> Result<Stmt, CorrodeError> {
+        let start = self.previous().span();
+        let name = self.consume(TokenType::Identifier, "Expected function name")?;
+        self.consume(TokenType::LeftParen, "Expected '(' after function name")?;
+        let (params, rest) = self.parameters()?;
+
+        self.consume(TokenType::LeftBrace, "Expected '{' before function body")?;
+        let body = self.function_body(Self::block_body)?;
+
+        Ok(Stmt::Function { name: name, params: params, rest: rest, body: body, span: self.span_from(start) })
+    }
+
+    // The parameter list after its '(', up to and including the ')', and the rest parameter if there is one
+    fn parameters(&mut self) -> Result<(Vec<Token>, Option<Token>), CorrodeError> {
+        let mut params = Vec::new();
+        let mut rest = None;
+        if !self.check(TokenType::RightParen) {
+            loop {
+                if params.len() >= 255 {
+                    let token = self.peek();
+                    return Err(CorrodeError::parse(token.line_number, String::from("Can not have more than 255 parameters")).with_column(token.column));
+                }
+
+                if self.match_token(TokenType::DotDotDot) {
+                    rest = Some(self.consume(TokenType::Identifier, "Expected parameter name after '...'")?);
+                    if self.match_token(TokenType::Comma) && !self.check(TokenType::RightParen) {
+                        let token = self.peek();
+                        return Err(CorrodeError::parse(token.line_number, String::from("Rest parameter must be the last parameter")).with_column(token.column));
+                    }
+                    break;
+                }
+
+                let param = self.consume(TokenType::Identifier, "Expected parameter name")?;
+                params.push(param);
+
+                // A comma right before the ')' is allowed
+                if !self.match_token(TokenType::Comma) || self.check(TokenType::RightParen) {
+                    break;
+                }
+            }
+        }
+        self.consume(TokenType::RightParen, "Expected ')' after parameters")?;
+
+        Ok((params, rest))
+    }
+
+    fn function_body(&mut self, body: fn(&mut Self) -> Result<Vec<Stmt>, CorrodeError>) -> Result<Vec<Stmt>, CorrodeError> {
+        // A loop around the declaration does not make break valid inside the body
+        let enclosing_loop_depth = self.loop_depth;
+        self.loop_depth = 0;
+        self.function_depth += 1;
+        let body = body(self);
+        self.function_depth -= 1;
+        self.loop_depth = enclosing_loop_depth;
+
+        body
+    }
+
+    // The statements of a block whose '{' was already consumed
+    fn block_body(&mut self) -> Result<Vec<Stmt>, CorrodeError> {
+        match self.block_statement()? {
+            Stmt::Block { statements, .. } => Ok(statements),
+            _ => unreachable!(),
+        }
+    }
+
+    // `=> expression` is a body that returns the expression
+    fn arrow_body(&mut self) -> Result<Vec<Stmt>, CorrodeError> {
+        let arrow = self.previous().clone();
+        let value = self.expression()?;
+
+        let span = arrow.span().to(value.span());
+
+        Ok(vec![Stmt::Return { keyword: arrow, value: Some(value), span }])
+    }
+
+    fn let_declaration(&mut self) -> Result<Stmt, CorrodeError> {
+        let start = self.previous().span();
+        if self.match_token(TokenType::LeftParen) {
+            let pattern = self.pattern()?;
+            let equals = self.consume(TokenType::Equal, "Expected '=' after destructuring pattern")?;
+            let initializer = self.expression()?;
+
+            self.consume(TokenType::Semicolon, "Expected ';' after variable declaration")?;
+            return Ok(Stmt::LetDestructure { pattern: pattern, equals: equals, initializer: initializer, span: self.span_from(start) });
+        }
+
+        let mut bindings = Vec::new();
+
+        loop {
+            let token = self.consume(TokenType::Identifier, "Expected variable name")?;
+
+            let annotation = if self.match_token(TokenType::Colon) {
+                Some(self.consume(TokenType::Identifier, "Expected type name after ':'")?)
+            } else {
+                None
+            };
+
+            let initializer;
+            if self.match_token(TokenType::Equal) {
+                initializer = Some(self.expression()?);
+            } else {
+                initializer = None;
+            }
+            bindings.push(Binding { name: token, annotation: annotation, initializer: initializer });
+
+            if !self.match_token(TokenType::Comma) {
+                break;
+            }
+        }
+        
+        self.consume(TokenType::Semicolon, "Expected ';' after variable declaration")?;
+        Ok(Stmt::Let { bindings: bindings, span: self.span_from(start) })
+    }
+
+    // A tuple of names after its '(', up to and including the ')'
+    fn pattern(&mut self) -> Result<Pattern, CorrodeError> {
+        let mut elements = Vec::new();
+        loop {
+            if self.match_token(TokenType::LeftParen) {
+                elements.push(self.nested(Self::pattern)?);
+            } else {
+                let name = self.consume(TokenType::Identifier, "Expected variable name in pattern")?;
+                elements.push(Pattern::Name(name, Depth::default()));
+            }
+
+            if !self.match_token(TokenType::Comma) {
+                break;
+            }
+        }
+        self.consume(TokenType::RightParen, "Expected ')' after pattern")?;
+
+        Ok(Pattern::Tuple(elements))
+    }
+
+    // The tuple on the left of an assignment, read back as the names it assigns to
+    fn tuple_pattern(elements: Vec<Expr>, equals: &Token) -> Result<Pattern, CorrodeError> {
+        let mut pattern = Vec::new();
+        for element in elements {
+            pattern.push(match element {
+                Expr::Variable { name, depth, .. } => Pattern::Name(name, depth),
+                Expr::Tuple { elements, .. } => Self::tuple_pattern(elements, equals)?,
+                _ => return Err(CorrodeError::parse(equals.line_number, String::from("Invalid assingment target")).with_column(equals.column)),
+            });
+        }
+
+        Ok(Pattern::Tuple(pattern))
+    }
+
+    fn const_declaration(&mut self) -> Result<Stmt, CorrodeError> {
+        let start = self.previous().span();
+        let token = self.consume(TokenType::Identifier, "Expected constant name")?;
+        self.consume(TokenType::Equal, "Expected '=' after constant name, constants must be initialized")?;
+        let initializer = self.expression()?;
+
+        self.consume(TokenType::Semicolon, "Expected ';' after constant declaration")?;
+        Ok(Stmt::Const { name: token, initializer: initializer, span: self.span_from(start) })
+    }
+
+    fn statement(&mut self) -> Result<Stmt, CorrodeError> {
+        if self.match_token(TokenType::Echo) {
+            self.echo_statement()
+        } else if self.match_tokens(&[TokenType::Print, TokenType::Println]) {
+            self.print_statement()
+        } else if self.match_token(TokenType::LeftBrace) {
+            self.block_statement()
+        } else if self.match_token(TokenType::If) {
+            self.if_statement()
+        } else if self.match_token(TokenType::Match) {
+            self.match_statement()
+        } else if self.match_token(TokenType::While) {
+            self.while_statement()
+        } else if self.match_token(TokenType::Do) {
+            self.do_while_statement()
+        } else if self.match_token(TokenType::For) {
+            self.for_statement()
+        } else if self.match_token(TokenType::Break) {
+            self.break_statement()
+        } else if self.match_token(TokenType::Continue) {
+            self.continue_statement()
+        } else if self.match_token(TokenType::Return) {
+            self.return_statement()
+        } else if self.match_token(TokenType::Assert) {
+            self.assert_statement()
+        } else if self.match_token(TokenType::Throw) {
+            self.throw_statement()
+        } else if self.match_token(TokenType::Try) {
+            self.try_statement()
+        } else {
+            self.expression_statement()
+        }
+    }
+
+    fn return_statement(&mut self) -> Result<Stmt, CorrodeError> {
+        let keyword = self.previous().clone();
+        if self.function_depth == 0 {
+            return Err(CorrodeError::parse(keyword.line_number, String::from("Can not return from top-level code")).with_column(keyword.column));
+        }
+
+        let value = if !self.check(TokenType::Semicolon) {
+            Some(self.expression()?)
+        } else {
+            None
+        };
+
+        self.consume(TokenType::Semicolon, "Expected ';' after return value")?;
+        Ok(Stmt::Return { span: self.span_from(keyword.span()), keyword: keyword, value: value })
+    }
+
+    fn break_statement(&mut self) -> Result<Stmt, CorrodeError> {
+        let keyword = self.previous().clone();
+        let start = keyword.span();
+        self.consume(TokenType::Semicolon, "Expected ';' after break statement")?;
+        if self.loop_depth == 0 {
+            return Err(CorrodeError::parse(start.line, String::from("Can not use 'break' outside of a loop")).with_column(keyword.column));
+        }
+
+        Ok(Stmt::Break { span: self.span_from(start) })
+    }
+
+    fn continue_statement(&mut self) -> Result<Stmt, CorrodeError> {
+        let keyword = self.previous().clone();
+        let start = keyword.span();
+        self.consume(TokenType::Semicolon, "Expected ';' after continue statement")?;
+        if self.loop_depth == 0 {
+            return Err(CorrodeError::parse(start.line, String::from("Can not use 'continue' outside of a loop")).with_column(keyword.column));
+        }
+
+        Ok(Stmt::Continue { span: self.span_from(start) })
+    }
+
+    fn loop_body(&mut self) -> Result<Stmt, CorrodeError> {
+        self.loop_depth += 1;
+        let body = self.nested(Self::statement);
+        self.loop_depth -= 1;
+
+        body
+    }
+
+    // The desugared loop and its block both get the span of the whole for statement
+    fn for_statement(&mut self) -> Result<Stmt, CorrodeError> {
+        let start = self.previous().span();
+        self.consume(TokenType::LeftParen, "Expected '(' after for")?;
+
+        let is_for_in = self.check(TokenType::Identifier) && (
+            self.check_ahead(1, TokenType::In) ||
+            self.check_ahead(1, TokenType::Comma) && self.check_ahead(2, TokenType::Identifier) && self.check_ahead(3, TokenType::In)
+        );
+        if is_for_in {
+            return self.for_in_statement(start);
+        }
+        
+        let initializer;
+        if self.match_token(TokenType::Semicolon) {
+            initializer = None;
+        } else if self.match_token(TokenType::Let) {
+            let let_decl = self.let_declaration()?;
+            initializer = Some(let_decl);
+        } else {
+            let expr = self.expression_statement()?;
+            initializer = Some(expr);
+        }
+
+        let condition;
+        if !self.check(TokenType::Semicolon) {
+            let expr = self.expression()?;
+            condition = Some(expr);
+        } else {
+            condition = None;
+        }
+
+        self.consume(TokenType::Semicolon, "Expected ';' after for loop condition")?;
+
+        let incrementer;
+        if !self.check(TokenType::RightParen) {
+            let expr = self.expression()?;
+            incrementer = Some(expr);
+        } else {
+            incrementer = None;
+        }
+        
+        self.consume(TokenType::RightParen, "Expected ')' after for loop clauses")?;
+
+        let mut body = self.loop_body()?;
+        let span = self.span_from(start);
+
+        let cond;
+        match condition {
+            None => cond = Expr::Literal { value: LiteralValue::True, span },
+            Some(c) => cond = c,
+        }
+        body = Stmt::While {
+            condition: cond,
+            body: Box::new(body),
+            increment: incrementer,
+            span
+        };
+
+        if let Some(init) = initializer {
+            body = Stmt::Block {
+                statements: vec![init, body],
+                span
+            };
+        }
+
+        Ok(body)
+    }
+
+    // `for (item in iterable)` or `for (key, item in iterable)`, after the '('
+    fn for_in_statement(&mut self, start: Span) -> Result<Stmt, CorrodeError> {
+        let mut key = None;
+        let mut item = self.advance().clone();
+        if self.match_token(TokenType::Comma) {
+            key = Some(item);
+            item = self.advance().clone();
+        }
+        self.advance();
+
+        let iterable = self.expression()?;
+        self.consume(TokenType::RightParen, "Expected ')' after for-in iterable")?;
+        let body = Box::new(self.loop_body()?);
+
+        Ok(Stmt::ForIn { key: key, item: item, iterable: iterable, body: body, span: self.span_from(start) })
+    }
+
+    fn match_statement(&mut self) -> Result<Stmt, CorrodeError> {
+        let start = self.previous().span();
+        self.consume(TokenType::LeftParen, "Expected '(' after 'match'")?;
+        let subject = self.expression()?;
+        self.consume(TokenType::RightParen, "Expected ')' after match subject")?;
+        self.consume(TokenType::LeftBrace, "Expected '{' before match arms")?;
+
+        let mut arms = Vec::new();
+        let mut default = None;
+        while !self.check(TokenType::RightBrace) && !self.is_at_end() {
+            if default.is_some() {
+                let token = self.peek();
+                return Err(CorrodeError::parse(token.line_number, String::from("The '_' arm must be the last arm of a match")).with_column(token.column));
+            }
+
+            if self.peek().lexeme == "_" && self.check_ahead(1, TokenType::Arrow) {
+                self.advance();
+                self.advance();
+                default = Some(Box::new(self.nested(Self::statement)?));
+                continue;
+            }
+
+            let mut patterns = vec![self.expression()?];
+            while self.match_token(TokenType::Comma) {
+                patterns.push(self.expression()?);
+            }
+            self.consume(TokenType::Arrow, "Expected '=>' after match patterns")?;
+            arms.push((patterns, self.nested(Self::statement)?));
+        }
+        self.consume(TokenType::RightBrace, "Expected '}' after match arms")?;
+
+        Ok(Stmt::Match { subject: subject, arms: arms, default: default, span: self.span_from(start) })
+    }
+
+    fn while_statement(&mut self) -> Result<Stmt, CorrodeError> {
+        let start = self.previous().span();
+        self.consume(TokenType::LeftParen, "Expected '(' after while")?;
+        let condition = self.expression()?;
+        self.consume(TokenType::RightParen, "Expected ')' after while loop condition")?;
+        let body = Box::new(self.loop_body()?);
+
+        Ok(Stmt::While { condition: condition, body: body, increment: None, span: self.span_from(start) })
+    }
+
+    fn do_while_statement(&mut self) -> Result<Stmt, CorrodeError> {
+        let start = self.previous().span();
+        let body = Box::new(self.loop_body()?);
+        self.consume(TokenType::While, "Expected 'while' after do loop body")?;
+        self.consume(TokenType::LeftParen, "Expected '(' after while")?;
+        let condition = self.expression()?;
+        self.consume(TokenType::RightParen, "Expected ')' after do loop condition")?;
+        self.consume(TokenType::Semicolon, "Expected ';' after do loop")?;
+
+        Ok(Stmt::DoWhile { body: body, condition: condition, span: self.span_from(start) })
+    }
+
+    fn if_statement(&mut self) -> Result<Stmt, CorrodeError> {
+        let start = self.previous().span();
+        self.consume(TokenType::LeftParen, "Expected '(' after 'if'")?;
+        let predicate = self.expression()?;
+        self.consume(TokenType::RightParen, "Expected ')' after if predicate")?;
+
+        let then = Box::new(self.nested(Self::statement)?);
+
+        let otherwise =  if self.match_token(TokenType::Else) {
+            let stmt = self.nested(Self::statement)?;
+            Some(Box::new(stmt))
+        } else {
+            None
+        };
+
+        Ok(Stmt::If { predicate: predicate, then: then, otherwise: otherwise, span: self.span_from(start) })
+    }
+
+    fn block_statement(&mut self) -> Result<Stmt, CorrodeError> {
+        let start = self.previous().span();
+        let mut statements = Vec::new();
+
+        while !self.check(TokenType::RightBrace) && !self.is_at_end() {
+            let decl = self.nested(Self::declaration)?;
+            statements.push(decl);
+        }
+
+        self.consume(TokenType::RightBrace, "Expected '}' after block")?;
+        Ok(Stmt::Block { statements, span: self.span_from(start) })
+    }
+
+    fn echo_statement(&mut self) -> Result<Stmt, CorrodeError> {
+        let start = self.previous().span();
+        let mut values = vec![self.expression()?];
+        while self.match_token(TokenType::Comma) {
+            values.push(self.expression()?);
+        }
+
+        self.consume(TokenType::Semicolon, "Expected ';' after value")?;
+        Ok(Stmt::Echo { expressions: values, span: self.span_from(start) })
+    }
+
+    fn print_statement(&mut self) -> Result<Stmt, CorrodeError> {
+        let start = self.previous().span();
+        let newline = self.previous().token_type == TokenType::Println;
+        let expression = self.expression()?;
+
+        self.consume(TokenType::Semicolon, "Expected ';' after value")?;
+        Ok(Stmt::Print { expression: expression, newline: newline, span: self.span_from(start) })
+    }
+
+    fn assert_statement(&mut self) -> Result<Stmt, CorrodeError> {
+        let keyword = self.previous().clone();
+        let condition = self.expression()?;
+        let message = if self.match_token(TokenType::Comma) {
+            Some(self.expression()?)
+        } else {
+            None
+        };
+
+        self.consume(TokenType::Semicolon, "Expected ';' after assertion")?;
+        Ok(Stmt::Assert { span: self.span_from(keyword.span()), keyword: keyword, condition: condition, message: message })
+    }
+
+    fn throw_statement(&mut self) -> Result<Stmt, CorrodeError> {
+        let keyword = self.previous().clone();
+        let value = self.expression()?;
+
+        self.consume(TokenType::Semicolon, "Expected ';' after thrown value")?;
+        Ok(Stmt::Throw { span: self.span_from(keyword.span()), keyword: keyword, value: value })
+    }
+
+    fn try_statement(&mut self) -> Result<Stmt, CorrodeError> {
+        let start = self.previous().span();
+        self.consume(TokenType::LeftBrace, "Expected '{' after try")?;
+        let body = Box::new(self.block_statement()?);
+
+        self.consume(TokenType::Catch, "Expected 'catch' after try block")?;
+        self.consume(TokenType::LeftParen, "Expected '(' after catch")?;
+        let name = self.consume(TokenType::Identifier, "Expected variable name in catch")?;
+        self.consume(TokenType::RightParen, "Expected ')' after catch variable")?;
+        self.consume(TokenType::LeftBrace, "Expected '{' after catch")?;
+        let handler = Box::new(self.block_statement()?);
+
+        Ok(Stmt::Try { body: body, name: name, handler: handler, span: self.span_from(start) })
+    }
+
+    fn expression_statement(&mut self) -> Result<Stmt, CorrodeError> {
+        let expr = self.expression()?;
+        self.consume(TokenType::Semicolon, "Expected ';' after expression")?;
+        Ok(Stmt::Expression { span: self.span_from(expr.span()), expression: expr })
+    }
+
+    fn expression(&mut self) -> Result<Expr, CorrodeError> {
+        self.nested(Self::assignment)
+    }
+
+    // Runs one level of a recursive rule, failing cleanly once max_nesting is reached
+    fn nested<T>(&mut self, rule: fn(&mut Self) -> Result<T, CorrodeError>) -> Result<T, CorrodeError> {
+        if self.nesting >= self.max_nesting {
+            return Err(CorrodeError::parse(self.peek().line_number, String::from("Too much nesting")).with_column(self.peek().column));
+        }
+
+        self.nesting += 1;
+        let result = rule(self);
+        self.nesting -= 1;
+
+        result
+    }
+
+    fn assignment(&mut self) -> Result<Expr, CorrodeError> {
+        let expr = self.ternary()?;
+
+        if self.match_token(TokenType::Equal) {
+            let equals = self.previous().clone();
+            let value = self.nested(Self::assignment)?;
+
+            self.assignment_target(expr, None, value, equals)
+        } else if self.match_tokens(&[TokenType::PlusEqual, TokenType::MinusEqual, TokenType::StarEqual, TokenType::SlashEqual]) {
+            let equals = self.previous().clone();
+            let value = self.nested(Self::assignment)?;
+
+            let operator = Token {
+                token_type: match equals.token_type {
+                    TokenType::PlusEqual => TokenType::Plus,
+                    TokenType::MinusEqual => TokenType::Minus,
+                    TokenType::StarEqual => TokenType::Star,
+                    _ => TokenType::Slash,
+                },
+                lexeme: equals.lexeme[..1].to_string(),
+                literal: None,
+                symbol: None,
+                line_number: equals.line_number,
+                column: equals.column,
+                offset: equals.offset,
+            };
+
+            self.assignment_target(expr, Some(operator), value, equals)
+        } else {
+            return Ok(expr);
+        }
+    }
+
+    // `operator` is the `+` of a compound `+=`. A variable reads itself again in a Binary, which has no side
+    // effects, while a Set or IndexSet keeps the operator so its object and index are evaluated once
+    fn assignment_target(&mut self, target: Expr, operator: Option<Token>, value: Expr, equals: Token) -> Result<Expr, CorrodeError> {
+        let span = target.span().to(value.span());
+        match target {
+            Expr::Variable { name, depth, span: target_span } => {
+                let value = match operator {
+                    Some(operator) => Expr::Binary {
+                        left: Box::from(Expr::Variable { name: name.clone(), depth: depth.clone(), span: target_span }),
+                        operator: operator,
+                        right: Box::from(value),
+                        span
+                    },
+                    None => value,
+                };
+                Ok(Expr::Assign { name: name, depth: depth, value: Box::from(value), span })
+            },
+            // Compound assignment would need the tuple as a value too, so only '=' can destructure
+            Expr::Tuple { elements, .. } if operator.is_none() => Ok(Expr::Destructure {
+                pattern: Self::tuple_pattern(elements, &equals)?,
+                equals: equals,
+                value: Box::from(value),
+                span
+            }),
+            Expr::Get { object, name, .. } => Ok(Expr::Set { object: object, name: name, operator: operator, value: Box::from(value), span }),
+            Expr::Index { object, bracket, index, .. } => Ok(Expr::IndexSet {
+                object: object,
+                bracket: bracket,
+                index: index,
+                operator: operator,
+                value: Box::from(value),
+                span
+            }),
+            _ => Err(CorrodeError::parse(equals.line_number, String::from("Invalid assingment target")).with_column(equals.column))
+        }
+    }
+
+    fn ternary(&mut self) -> Result<Expr, CorrodeError> {
+        let expr = self.coalesce()?;
+
+        if self.match_token(TokenType::Question) {
+            let then_expr = self.expression()?;
+            self.consume(TokenType::Colon, "Expected ':' in ternary expression")?;
+            let else_expr = self.nested(Self::ternary)?;
+            let span = expr.span().to(else_expr.span());
+
+            return Ok(Expr::Ternary {
+                condition: Box::from(expr),
+                then_expr: Box::from(then_expr),
+                else_expr: Box::from(else_expr),
+                span
+            });
+        }
+
+        Ok(expr)
+    }
+
+    fn coalesce(&mut self) -> Result<Expr, CorrodeError> {
+        let mut expr = self.or()?;
+
+        while self.match_token(TokenType::QuestionQuestion) {
+            let operator = self.previous().clone();
+            let right = self.or()?;
+            let span = expr.span().to(right.span());
+            expr = Expr::Logical { left: Box::from(expr), operator, right: Box::from(right), span };
+        }
+        Ok(expr)
+    }
+
+    fn or(&mut self) -> Result<Expr, CorrodeError> {
+        let mut expr = self.and()?;
+
+        while self.match_token(TokenType::Or) {
+            let operator = self.previous().clone();
+            let right = self.and()?;
+            let span = expr.span().to(right.span());
+            expr = Expr::Logical { left: Box::from(expr), operator, right: Box::from(right), span };
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, CorrodeError> {
+        let mut expr = self.equality()?;
+
+        while self.match_token(TokenType::And) {
+            let operator = self.previous().clone();
+            let right = self.equality()?;
+            let span = expr.span().to(right.span());
+            expr = Expr::Logical { left: Box::from(expr), operator, right: Box::from(right), span };
+        }
+
+        Ok(expr)
+    }
+
+    fn equality(&mut self) -> Result<Expr, CorrodeError> {
+        let mut expr = self.comparison()?;
+        let mut matches_eq = self.match_tokens(&[TokenType::BangEqual, TokenType::EqualEqual]);
+        while matches_eq {
+            let operator = self.previous().clone();
+            let right = self.comparison()?;
+            let span = expr.span().to(right.span());
+            expr = Expr::Binary {
+                left: Box::from(expr),
+                operator: operator,
+                right: Box::from(right),
+                span
+            };
+
+            matches_eq = self.match_tokens(&[TokenType::BangEqual, TokenType::EqualEqual]);
+        }
+
+        Ok(expr)
+    }
+
+    fn comparison(&mut self) -> Result<Expr, CorrodeError> {
+        let mut operands = vec![self.bit_or()?];
+        let mut operators = Vec::new();
+
+        while self.match_tokens(&[TokenType::Greater, TokenType::GreaterEqual , TokenType::Less, TokenType::LessEqual]) {
+            operators.push(self.previous().clone());
+            operands.push(self.bit_or()?);
+        }
+
+        let span = operands[0].span().to(operands[operands.len() - 1].span());
+        Ok(match operators.len() {
+            0 => operands.pop().unwrap(),
+            1 => {
+                let right = operands.pop().unwrap();
+                let left = operands.pop().unwrap();
+                Expr::Binary { left: Box::from(left), operator: operators.pop().unwrap(), right: Box::from(right), span }
+            },
+            _ => Expr::Chain { operands, operators, span },
+        })
+    }
+
+    fn bit_or(&mut self) -> Result<Expr, CorrodeError> {
+        let mut expr = self.bit_xor()?;
+
+        while self.match_token(TokenType::Pipe) {
+            let op = self.previous().clone();
+            let right = self.bit_xor()?;
+            let span = expr.span().to(right.span());
+            expr = Expr::Binary {
+                left: Box::from(expr),
+                operator: op,
+                right: Box::from(right),
+                span
+            }
+        }
+
+        Ok(expr)
+    }
+
+    fn bit_xor(&mut self) -> Result<Expr, CorrodeError> {
+        let mut expr = self.bit_and()?;
+
+        while self.match_token(TokenType::Caret) {
+            let op = self.previous().clone();
+            let right = self.bit_and()?;
+            let span = expr.span().to(right.span());
+            expr = Expr::Binary {
+                left: Box::from(expr),
+                operator: op,
+                right: Box::from(right),
+                span
+            }
+        }
+
+        Ok(expr)
+    }
+
+    fn bit_and(&mut self) -> Result<Expr, CorrodeError> {
+        let mut expr = self.shift()?;
+
+        while self.match_token(TokenType::Ampersand) {
+            let op = self.previous().clone();
+            let right = self.shift()?;
+            let span = expr.span().to(right.span());
+            expr = Expr::Binary {
+                left: Box::from(expr),
+                operator: op,
+                right: Box::from(right),
+                span
+            }
+        }
+
+        Ok(expr)
+    }
+
+    fn shift(&mut self) -> Result<Expr, CorrodeError> {
+        let mut expr = self.term()?;
+
+        while self.match_tokens(&[TokenType::LessLess, TokenType::GreaterGreater]) {
+            let op = self.previous().clone();
+            let right = self.term()?;
+            let span = expr.span().to(right.span());
+            expr = Expr::Binary {
+                left: Box::from(expr),
+                operator: op,
+                right: Box::from(right),
+                span
+            }
+        }
+
+        Ok(expr)
+    }
+
+    fn term(&mut self) -> Result<Expr, CorrodeError> {
+        let mut expr = self.factor()?;
+
+        while self.match_tokens(&[TokenType::Minus, TokenType::Plus]) {
+            let op = self.previous().clone();
+            let right = self.factor()?;
+            let span = expr.span().to(right.span());
+            expr = Expr::Binary {
+                left: Box::from(expr),
+                operator: op,
+                right: Box::from(right),
+                span
+            }
+        }
+
+        Ok(expr)
+    }
+
+    fn factor(&mut self) -> Result<Expr, CorrodeError> {
+        let mut expr = self.power()?;
+
+        while self.match_tokens(&[TokenType::Slash, TokenType::Star, TokenType::Percent, TokenType::TildeSlash]) {
+            let op = self.previous().clone();
+            let right = self.power()?;
+            let span = expr.span().to(right.span());
+            expr = Expr::Binary {
+                left: Box::from(expr),
+                operator: op,
+                right: Box::from(right),
+                span
+            }
+        }
+
+        Ok(expr)
+    }
+
+    fn power(&mut self) -> Result<Expr, CorrodeError> {
+        let expr = self.unary()?;
+
+        if self.match_token(TokenType::StarStar) {
+            let op = self.previous().clone();
+            let right = self.nested(Self::power)?;
+            let span = expr.span().to(right.span());
+            return Ok(Expr::Binary {
+                left: Box::from(expr),
+                operator: op,
+                right: Box::from(right),
+                span
+            });
+        }
+
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<Expr, CorrodeError> {
+        if self.match_tokens(&[TokenType::Bang, TokenType::Minus, TokenType::Tilde]) {
+            let op = self.previous().clone();
+            let right = self.nested(Self::unary)?;
+            let span = op.span().to(right.span());
+            Ok(Expr::Unary {
+                operator: op,
+                right: Box::from(right),
+                span
+            })
+        } else {
+            self.call()
+        }
+    }
+
+    fn call(&mut self) -> Result<Expr, CorrodeError> {
+        let mut expr = self.primary()?;
+
+        loop {
+            if self.match_token(TokenType::LeftParen) {
+                expr = self.finish_call(expr)?;
+            } else if self.match_token(TokenType::Dot) {
+                let name = self.consume(TokenType::Identifier, "Expected property name after '.'")?;
+                let span = expr.span().to(name.span());
+                expr = Expr::Get { object: Box::from(expr), name: name, span };
+            } else if self.match_token(TokenType::LeftBracket) {
+                let start = if self.check(TokenType::Colon) { None } else { Some(Box::from(self.expression()?)) };
+
+                expr = match (start, self.match_token(TokenType::Colon)) {
+                    (Some(index), false) => {
+                        let bracket = self.consume(TokenType::RightBracket, "Expected ']' after index")?;
+                        let span = expr.span().to(bracket.span());
+                        Expr::Index { object: Box::from(expr), bracket: bracket, index: index, span }
+                    },
+                    (start, _) => {
+                        let end = if self.check(TokenType::RightBracket) { None } else { Some(Box::from(self.expression()?)) };
+                        let bracket = self.consume(TokenType::RightBracket, "Expected ']' after slice")?;
+                        let span = expr.span().to(bracket.span());
+                        Expr::Slice { object: Box::from(expr), bracket: bracket, start: start, end: end, span }
+                    },
+                };
+            } else {
+                break;
+            }
+        }
+
+        Ok(expr)
+    }
+
+    // An array element or call argument, `...` spreads an array into the surrounding list
+    fn element(&mut self) -> Result<Expr, CorrodeError> {
+        if self.match_token(TokenType::DotDotDot) {
+            let ellipsis = self.previous().clone();
+            let inner = self.expression()?;
+            let span = ellipsis.span().to(inner.span());
+
+            return Ok(Expr::Spread { ellipsis: ellipsis, inner: Box::from(inner), span });
+        }
+
+        self.expression()
+    }
+
+    fn finish_call(&mut self, callee: Expr) -> Result<Expr, CorrodeError> {
+        let mut arguments = Vec::new();
+        
+        if !self.check(TokenType::RightParen) {
+            loop {
+                if arguments.len() >= 255 {
+                    let token = self.peek();
+                    return Err(CorrodeError::parse(token.line_number, String::from("Can not have more than 255 function arguments")).with_column(token.column));
+                }
+
+                let arg = self.element()?;
+                arguments.push(arg);
+
+                // A comma right before the ')' is allowed
+                if !self.match_token(TokenType::Comma) || self.check(TokenType::RightParen) {
+                    break;
+                }
+            }
+        }
+        let paren = self.consume(TokenType::RightParen, "Expected ')' after arguments")?;
+        let span = callee.span().to(paren.span());
+
+        Ok(Expr::Call { callee: Box::new(callee), paren: paren, arguments: arguments, span })
+    }
+
+    fn primary(&mut self) -> Result<Expr, CorrodeError> {
+        let token = self.peek().clone();
+        
+        let result;
+        match token.token_type {
+            TokenType::LeftParen => {
+                self.advance();
+                result = self.grouping(token)?;
+            },
+            TokenType::LeftBracket => {
+                self.advance();
+                result = self.array(token)?;
+            },
+            TokenType::LeftBrace => {
+                self.advance();
+                result = self.map(token)?;
+            },
+            TokenType::True | TokenType::False | TokenType::Null |  TokenType::Number | TokenType::StringLit => {
+                self.advance();
+                result = Expr::Literal {
+                    value: LiteralValue::from_token(token.clone()),
+                    span: token.span()
+                };
+            },
+            TokenType::Identifier => {
+                self.advance();
+                result = Expr::Variable { name: self.previous().clone(), depth: Depth::default(), span: token.span() };
+            },
+            TokenType::Self_ => {
+                self.advance();
+                if self.class_depth == 0 {
+                    self.errors.push(CorrodeError::parse(token.line_number, String::from("Can not use 'self' outside of a class")).with_column(token.column));
+                }
+                result = Expr::Self_ { keyword: self.previous().clone(), span: token.span() };
+            },
+            TokenType::Super => {
+                self.advance();
+                if self.class_depth == 0 {
+                    self.errors.push(CorrodeError::parse(token.line_number, String::from("Can not use 'super' outside of a class")).with_column(token.column));
+                }
+                let keyword = self.previous().clone();
+                self.consume(TokenType::Dot, "Expected '.' after 'super'")?;
+                let method = self.consume(TokenType::Identifier, "Expected superclass method name")?;
+                result = Expr::Super { keyword: keyword, method: method, span: self.span_from(token.span()) };
+            },
+            TokenType::Interpolation => {
+                self.advance();
+                result = self.interpolation(token)?;
+            },
+            TokenType::Fn => {
+                self.advance();
+                result = self.lambda(token)?;
+            },
+            // The token is left for synchronize to skip, which always advances at least once
+            TokenType::Eof => {
+                return Err(CorrodeError::parse(token.line_number, String::from("Expected expression, found end of input")).with_column(token.column));
+            },
+            _ => {
+                return Err(CorrodeError::parse(token.line_number, format!("Expected expression, found '{}'", token.lexeme)).with_column(token.column));
+            },
+        }
+
+        //self.advance();
+
+        Ok(result)
+    }
+
+    // The bracketed forms of primary get their own functions so its frame stays small, every level of
+    // nesting goes through it
+    fn grouping(&mut self, paren: Token) -> Result<Expr, CorrodeError> {
+        let expr = self.expression()?;
+        if self.match_token(TokenType::Comma) {
+            let mut elements = vec![expr];
+            loop {
+                elements.push(self.expression()?);
+                if !self.match_token(TokenType::Comma) {
+                    break;
+                }
+            }
+            self.consume(TokenType::RightParen, "Expected ')' after tuple elements")?;
+            Ok(Expr::Tuple { elements: elements, span: self.span_from(paren.span()) })
+        } else {
+            self.consume(TokenType::RightParen, "Expected ')'")?;
+            Ok(Expr::Grouping {
+                expression: Box::from(expr),
+                span: self.span_from(paren.span())
+            })
+        }
+    }
+
+    fn array(&mut self, bracket: Token) -> Result<Expr, CorrodeError> {
+        let mut elements = Vec::new();
+        while !self.check(TokenType::RightBracket) {
+            elements.push(self.element()?);
+
+            if !self.match_token(TokenType::Comma) {
+                break;
+            }
+        }
+        self.consume(TokenType::RightBracket, "Expected ']' after array elements")?;
+        Ok(Expr::Array { elements: elements, span: self.span_from(bracket.span()) })
+    }
+
+    fn map(&mut self, open: Token) -> Result<Expr, CorrodeError> {
+        let mut entries = Vec::new();
+        while !self.check(TokenType::RightBrace) {
+            let key = self.expression()?;
+            self.consume(TokenType::Colon, "Expected ':' after map key")?;
+            let value = self.expression()?;
+            entries.push((key, value));
+
+            if !self.match_token(TokenType::Comma) {
+                break;
+            }
+        }
+        let brace = self.consume(TokenType::RightBrace, "Expected '}' after map entries")?;
+        Ok(Expr::Map { brace: brace, entries: entries, span: self.span_from(open.span()) })
+    }
+
+    fn lambda(&mut self, keyword: Token) -> Result<Expr, CorrodeError> {
+        self.consume(TokenType::LeftParen, "Expected '(' after 'fn'")?;
+        let (params, rest) = self.parameters()?;
+
+        let body = if self.match_token(TokenType::Arrow) {
+            self.function_body(Self::arrow_body)?
+        } else {
+            self.consume(TokenType::LeftBrace, "Expected '{' or '=>' before lambda body")?;
+            self.function_body(Self::block_body)?
+        };
+        Ok(Expr::Lambda { span: self.span_from(keyword.span()), keyword: keyword, params: params, rest: rest, body: body })
+    }
+
+    // The text parts have no tokens of their own and get the span of the whole string
+    fn interpolation(&mut self, token: Token) -> Result<Expr, CorrodeError> {
+        let span = token.span();
+        let parts = match token.literal {
+            Some(tokenizer::LiteralValue::Interpolation(parts)) => parts,
+            _ => return Err(CorrodeError::parse(token.line_number, String::from("Expected interpolated string")).with_column(token.column)),
+        };
+
+        let mut exprs = Vec::new();
+        for part in parts {
+            match part {
+                InterpolationPart::Text(text) => exprs.push(Expr::Literal {
+                    value: LiteralValue::StringValue(text),
+                    span
+                }),
+                InterpolationPart::Code(tokens) => {
+                    // The embedded expression sees the same context checks as the surrounding code
+                    let mut parser = Parser::new(tokens);
+                    parser.function_depth = self.function_depth;
+                    parser.class_depth = self.class_depth;
+                    parser.loop_depth = self.loop_depth;
+                    parser.nesting = self.nesting;
+                    parser.max_nesting = self.max_nesting;
+                    let expr = parser.expression()?;
+                    self.errors.append(&mut parser.errors);
+                    if !parser.is_at_end() {
+                        let token = parser.peek();
+                        return Err(CorrodeError::parse(token.line_number, String::from("Expected '}' after interpolated expression")).with_column(token.column));
+                    }
+                    exprs.push(expr);
+                },
+            }
+        }
+
+        Ok(Expr::Interpolation { parts: exprs, span })
+    }
+
+    // Returns an owned token since callers store it in the AST
+    fn consume(&mut self, token_type: TokenType, msg: &str) -> Result<Token, CorrodeError> {
+        if self.check(token_type) {
+            Ok(self.advance().clone())
+        } else {
+            let token = self.peek();
+            Err(CorrodeError::parse(token.line_number, String::from(msg)).with_column(token.column))
+        }
+    }
+
+    fn check(&self, type_: TokenType) -> bool {
+        self.peek().token_type == type_
+    }
+
+    // Looks `distance` tokens past the current one
+    fn check_ahead(&self, distance: usize, type_: TokenType) -> bool {
+        match self.tokens.get(self.current + distance) {
+            Some(token) => token.token_type == type_,
+            None => false,
+        }
+    }
+
+    fn match_token(&mut self, type_: TokenType) -> bool {
+        if self.is_at_end() {
+            false
+        } else {
+            if self.peek().token_type == type_ {
+                self.advance();
+                true
+            } else {
+                false
+            }
+        }
+    }
+
+    fn match_tokens(&mut self, types: &[TokenType]) -> bool {
+        for type_ in types {
+            if self.match_token(*type_) {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    fn advance(&mut self) -> &Token {
+        if !self.is_at_end() {
+            self.current += 1
+        }
+        self.previous()
+    }
+
+    fn peek(&self) -> &Token {
+        &self.tokens[self.current]
+    }
+
+    fn previous(&self) -> &Token {
+        &self.tokens[self.current - 1]
+    }
+
+    // From `start` to the end of the last consumed token
+    fn span_from(&self, start: Span) -> Span {
+        start.to(self.previous().span())
+    }
+
+    fn is_at_end(&self) -> bool {
+        self.peek().token_type == TokenType::Eof
+    }
+
+    fn synchronize(&mut self) {
+        self.advance();
+
+        while !self.is_at_end() {
+            if self.previous().token_type == TokenType::Semicolon {
+                return;
+            }
+            match self.peek().token_type {
+                TokenType::Class | TokenType::Fn | TokenType::Let | TokenType::Const |
+                TokenType::For | TokenType::If | TokenType::While | TokenType::Do | TokenType::Match |
+                TokenType::Echo | TokenType::Print | TokenType::Println | TokenType::Return | TokenType::Assert |
+                TokenType::Throw | TokenType::Try => return,
+                _ => (),
+            }
+            self.advance();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tokenizer::Tokenizer;
+
+    #[test]
+    fn test_equality_with_paren() {
+        let src = "1 == (2 + 3);";
+        
+        let mut tokenizer = Tokenizer::new(src);
+        
+        let tokens = tokenizer.tokenize().unwrap();
+        
+        let mut parser = Parser::new(tokens);
+        
+        let parsed_expr = parser.parse().unwrap();
+        let string_expr = parsed_expr[0].to_string();
+
+        assert_eq!(string_expr, "(== 1 (group (+ 2 3)))")
+    }
+
+    #[test]
+    fn test_block() {
+        let src = "{ let a = 1; echo a; }";
+
+        let mut tokenizer = Tokenizer::new(src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let stmts = parser.parse().unwrap();
+
+        assert_eq!(stmts.len(), 1);
+        assert_eq!(stmts[0].to_string(), "(block (let a)(print var a))");
+    }
+
+    #[test]
+    fn test_unterminated_block() {
+        let src = "{\n let a = 1;";
+
+        let mut tokenizer = Tokenizer::new(src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let result = parser.parse();
+
+        assert_eq!(result.err().unwrap(), vec![CorrodeError::parse(2, String::from("Expected '}' after block")).with_column(12)]);
+    }
+
+    #[test]
+    fn test_dangling_else() {
+        let src = "if (a) if (b) echo 1; else echo 2;";
+
+        let mut tokenizer = Tokenizer::new(src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let stmts = parser.parse().unwrap();
+
+        assert_eq!(stmts[0].to_string(), "(if var a (if var b (print 1) (print 2)))");
+    }
+
+    #[test]
+    fn test_while() {
+        let src = "while (a > 0) a = a - 1;";
+
+        let mut tokenizer = Tokenizer::new(src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let stmts = parser.parse().unwrap();
+
+        assert_eq!(stmts[0].to_string(), "(while (> var a 0) a = (- var a 1))");
+    }
+
+    #[test]
+    fn test_print_statements() {
+        let src = "print 1 + 2; println(a);";
+
+        let mut tokenizer = Tokenizer::new(src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let stmts = parser.parse().unwrap();
+
+        assert_eq!(stmts[0].to_string(), "(write (+ 1 2))");
+        assert_eq!(stmts[1].to_string(), "(writeln (group var a))");
+    }
+
+    #[test]
+    fn test_print_needs_a_value() {
+        let src = "print;";
+
+        let mut tokenizer = Tokenizer::new(src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+
+        assert_eq!(parser.parse().err().unwrap(), vec![CorrodeError::parse(1, String::from("Expected expression, found ';'")).with_column(6)]);
+    }
+
+    #[test]
+    fn test_do_while() {
+        let src = "do { a = a - 1; } while (a > 0);";
+
+        let mut tokenizer = Tokenizer::new(src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let stmts = parser.parse().unwrap();
+
+        assert_eq!(stmts[0].to_string(), "(do (block a = (- var a 1)) while (> var a 0))");
+    }
+
+    #[test]
+    fn test_for_desugars_to_while() {
+        let src = "for (let i = 0; i < 3; i = i + 1) echo i;";
+
+        let mut tokenizer = Tokenizer::new(src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let stmts = parser.parse().unwrap();
+
+        assert_eq!(
+            stmts[0].to_string(),
+            "(block (let i)(while (< var i 3) (print var i) i = (+ var i 1)))"
+        );
+    }
+
+    #[test]
+    fn test_for_empty_clauses() {
+        let src = "for (;;) break;";
+
+        let mut tokenizer = Tokenizer::new(src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let stmts = parser.parse().unwrap();
+
+        assert_eq!(stmts[0].to_string(), "(while true (break))");
+    }
+
+    #[test]
+    fn test_modulo_precedence() {
+        let src = "1 + 7 % 3;";
+
+        let mut tokenizer = Tokenizer::new(src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let stmts = parser.parse().unwrap();
+
+        assert_eq!(stmts[0].to_string(), "(+ 1 (% 7 3))");
+    }
+
+    #[test]
+    fn test_floor_division_precedence() {
+        let src = "1 + 7 ~/ 2 * 3;";
+
+        let mut tokenizer = Tokenizer::new(src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let stmts = parser.parse().unwrap();
+
+        assert_eq!(stmts[0].to_string(), "(+ 1 (* (~/ 7 2) 3))");
+    }
+
+    #[test]
+    fn test_power_right_associative() {
+        let src = "2 ** 3 ** 2;";
+
+        let mut tokenizer = Tokenizer::new(src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let stmts = parser.parse().unwrap();
+
+        assert_eq!(stmts[0].to_string(), "(** 2 (** 3 2))");
+    }
+
+    #[test]
+    fn test_power_precedence() {
+        let src = "2 * 3 ** 2;";
+
+        let mut tokenizer = Tokenizer::new(src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let stmts = parser.parse().unwrap();
+
+        assert_eq!(stmts[0].to_string(), "(* 2 (** 3 2))");
+    }
+
+    #[test]
+    fn test_interpolation() {
+        let src = "\"a${b}c${1 + 2}\";";
+
+        let mut tokenizer = Tokenizer::new(src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let stmts = parser.parse().unwrap();
+
+        assert_eq!(stmts[0].to_string(), "(interpolate a var b c (+ 1 2) )");
+    }
+
+    #[test]
+    fn test_interpolation_trailing_tokens() {
+        let src = "\"${a b}\";";
+
+        let mut tokenizer = Tokenizer::new(src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+
+        assert!(parser.parse().is_err());
+    }
+
+    #[test]
+    fn test_ternary_right_associative() {
+        let src = "a ? b : c ? d : e;";
+
+        let mut tokenizer = Tokenizer::new(src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let stmts = parser.parse().unwrap();
+
+        assert_eq!(stmts[0].to_string(), "(? var a var b (? var c var d var e))");
+    }
+
+    #[test]
+    fn test_coalesce() {
+        let src = "a ?? b ?? c or d; a ?? b ? c : d;";
+
+        let mut tokenizer = Tokenizer::new(src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let stmts = parser.parse().unwrap();
+
+        assert_eq!(stmts[0].to_string(), "(?? (?? var a var b) (or var c var d))");
+        assert_eq!(stmts[1].to_string(), "(? (?? var a var b) var c var d)");
+    }
+
+    #[test]
+    fn test_comparison_chain() {
+        let src = "a < b; a < b >= c; a < b < c < d;";
+
+        let mut tokenizer = Tokenizer::new(src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let stmts = parser.parse().unwrap();
+
+        assert_eq!(stmts[0].to_string(), "(< var a var b)");
+        assert_eq!(stmts[1].to_string(), "(chain var a < var b >= var c)");
+        assert_eq!(stmts[2].to_string(), "(chain var a < var b < var c < var d)");
+    }
+
+    #[test]
+    fn test_ternary_missing_colon() {
+        let src = "a ? b;";
+
+        let mut tokenizer = Tokenizer::new(src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+
+        assert_eq!(parser.parse().err().unwrap(), vec![CorrodeError::parse(1, String::from("Expected ':' in ternary expression")).with_column(6)]);
+    }
+
+    #[test]
+    fn test_function_declaration() {
+        let src = "fn add(a, b) { echo a + b; }";
+
+        let mut tokenizer = Tokenizer::new(src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let stmts = parser.parse().unwrap();
+
+        assert_eq!(stmts[0].to_string(), "(fn add (a b) (print (+ var a var b)))");
+    }
+
+    #[test]
+    fn test_function_missing_parameter_name() {
+        let src = "fn add(a, , b) {}";
+
+        let mut tokenizer = Tokenizer::new(src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+
+        assert_eq!(parser.parse().err().unwrap(), vec![CorrodeError::parse(1, String::from("Expected parameter name")).with_column(11)]);
+    }
+
+    #[test]
+    fn test_function_too_many_parameters() {
+        let params = (0..256).map(|i| format!("p{}", i)).collect::<Vec<String>>().join(", ");
+        let src = format!("fn f({}) {{}}", params);
+
+        let mut tokenizer = Tokenizer::new(&src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+
+        assert_eq!(parser.parse().err().unwrap(), vec![CorrodeError::parse(1, String::from("Can not have more than 255 parameters")).with_column(1426)]);
+    }
+
+    #[test]
+    fn test_match() {
+        let src = "match (x) { 1 => echo \"one\"; 2, 3 => { echo \"few\"; } _ => echo \"many\"; }";
+
+        let mut tokenizer = Tokenizer::new(src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let stmts = parser.parse().unwrap();
+
+        assert_eq!(stmts[0].to_string(), "(match var x (1 => (print one)) (2 3 => (block (print few))) (_ => (print many)))");
+    }
+
+    #[test]
+    fn test_match_default_not_last() {
+        let src = "match (x) { _ => echo 1; 1 => echo 2; }";
+
+        let mut tokenizer = Tokenizer::new(src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+
+        // Recovery skips to the ';' of the misplaced arm, so the closing '}' gives a second error
+        assert_eq!(parser.parse().err().unwrap()[0], CorrodeError::parse(1, String::from("The '_' arm must be the last arm of a match")).with_column(26));
+    }
+
+    #[test]
+    fn test_tuple_destructure() {
+        let src = "let (a, (b, c)) = (1, (2, 3)); (a, b) = (b, a);";
+
+        let mut tokenizer = Tokenizer::new(src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let stmts = parser.parse().unwrap();
+
+        assert_eq!(stmts[0].to_string(), "(let (a (b c)) (tuple 1 (tuple 2 3)))");
+        assert_eq!(stmts[1].to_string(), "(= (a b) (tuple var b var a))");
+    }
+
+    #[test]
+    fn test_tuple_invalid_target() {
+        let src = "(a, 1) = (1, 2);";
+
+        let mut tokenizer = Tokenizer::new(src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+
+        assert_eq!(parser.parse().err().unwrap(), vec![CorrodeError::parse(1, String::from("Invalid assingment target")).with_column(8)]);
+    }
+
+    #[test]
+    fn test_for_in() {
+        let src = "for (x in xs) echo x; for (k, v in m) echo k, v;";
+
+        let mut tokenizer = Tokenizer::new(src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let stmts = parser.parse().unwrap();
+
+        assert_eq!(stmts[0].to_string(), "(for x in var xs (print var x))");
+        assert_eq!(stmts[1].to_string(), "(for k v in var m (print var k var v))");
+    }
+
+    #[test]
+    fn test_rest_parameter() {
+        let src = "fn sum(first, ...rest) {}";
+
+        let mut tokenizer = Tokenizer::new(src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let stmts = parser.parse().unwrap();
+
+        assert_eq!(stmts[0].to_string(), "(fn sum (first ...rest) )");
+    }
+
+    #[test]
+    fn test_spread() {
+        let src = "[1, ...rest, 5]; f(...args, a + 1);";
+
+        let mut tokenizer = Tokenizer::new(src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let stmts = parser.parse().unwrap();
+
+        assert_eq!(stmts[0].to_string(), "(array 1 (... var rest) 5)");
+        assert_eq!(stmts[1].to_string(), "(var f [(... var args), (+ var a 1)])");
+    }
+
+    #[test]
+    fn test_trailing_commas() {
+        let src = "f(1, 2,); fn g(a, b,) {} fn h(a, ...rest,) {} [1, 2,]; echo {\"a\": 1,};";
+
+        let mut tokenizer = Tokenizer::new(src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let stmts = parser.parse().unwrap();
+
+        assert_eq!(
+            stmts.iter().map(Stmt::to_string).collect::<Vec<String>>(),
+            vec!["(var f [1, 2])", "(fn g (a b) )", "(fn h (a ...rest) )", "(array 1 2)", "(print (map (a 1)))"]
+        );
+    }
+
+    #[test]
+    fn test_trailing_comma_needs_an_element() {
+        for (src, message, column) in [
+            ("f(,);", "Expected expression, found ','", 3),
+            ("f(1,,);", "Expected expression, found ','", 5),
+            ("fn g(a,,) {}", "Expected parameter name", 8),
+            ("[1,,];", "Expected expression, found ','", 4),
+            ("let m = {\"a\": 1,,};", "Expected expression, found ','", 17),
+        ] {
+            let mut tokenizer = Tokenizer::new(src);
+            let tokens = tokenizer.tokenize().unwrap();
+            let mut parser = Parser::new(tokens);
+
+            assert_eq!(parser.parse().err().unwrap(), vec![CorrodeError::parse(1, String::from(message)).with_column(column)], "{}", src);
+        }
+    }
+
+    #[test]
+    fn test_rest_parameter_not_last() {
+        let src = "fn f(...rest, a) {}";
+
+        let mut tokenizer = Tokenizer::new(src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+
+        assert_eq!(parser.parse().err().unwrap(), vec![CorrodeError::parse(1, String::from("Rest parameter must be the last parameter")).with_column(15)]);
+    }
+
+    #[test]
+    fn test_lambda() {
+        let src = "fn(a) { return a * 2; }; f = fn(a, b) => a + b;";
+
+        let mut tokenizer = Tokenizer::new(src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let stmts = parser.parse().unwrap();
+
+        assert_eq!(stmts[0].to_string(), "(fn (a) (return (* var a 2)))");
+        assert_eq!(stmts[1].to_string(), "f = (fn (a b) (return (+ var a var b)))");
+    }
+
+    #[test]
+    fn test_lambda_missing_body() {
+        let src = "let f = fn(a) a;";
+
+        let mut tokenizer = Tokenizer::new(src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+
+        assert_eq!(parser.parse().err().unwrap(), vec![CorrodeError::parse(1, String::from("Expected '{' or '=>' before lambda body")).with_column(15)]);
+    }
+
+    #[test]
+    fn test_call() {
+        let src = "f(1, 2);";
+
+        let mut tokenizer = Tokenizer::new(src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let stmts = parser.parse().unwrap();
+
+        assert_eq!(stmts[0].to_string(), "(var f [1, 2])");
+    }
+
+    #[test]
+    fn test_chained_call() {
+        let src = "f()(3);";
+
+        let mut tokenizer = Tokenizer::new(src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let stmts = parser.parse().unwrap();
+
+        assert_eq!(stmts[0].to_string(), "((var f []) [3])");
+    }
+
+    #[test]
+    fn test_call_too_many_arguments() {
+        let args = (0..256).map(|i| i.to_string()).collect::<Vec<String>>().join(", ");
+        let src = format!("f({});", args);
+
+        let mut tokenizer = Tokenizer::new(&src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+
+        assert_eq!(parser.parse().err().unwrap(), vec![CorrodeError::parse(1, String::from("Can not have more than 255 function arguments")).with_column(1168)]);
+    }
+
+    #[test]
+    fn test_return() {
+        let src = "fn f() { return 1; } fn g() { return; }";
+
+        let mut tokenizer = Tokenizer::new(src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let stmts = parser.parse().unwrap();
+
+        assert_eq!(stmts[0].to_string(), "(fn f () (return 1))");
+        assert_eq!(stmts[1].to_string(), "(fn g () (return))");
+    }
+
+    #[test]
+    fn test_top_level_return() {
+        let src = "return 1;";
+
+        let mut tokenizer = Tokenizer::new(src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+
+        assert_eq!(parser.parse().err().unwrap(), vec![CorrodeError::parse(1, String::from("Can not return from top-level code")).with_column(1)]);
+    }
+
+    #[test]
+    fn test_class_declaration() {
+        let src = "class Point { fn sum() { return 1; } }";
+
+        let mut tokenizer = Tokenizer::new(src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let stmts = parser.parse().unwrap();
+
+        assert_eq!(stmts[0].to_string(), "(class Point (fn sum () (return 1)))");
+    }
+
+    #[test]
+    fn test_get_and_set() {
+        let src = "a.b.c = d.e;";
+
+        let mut tokenizer = Tokenizer::new(src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let stmts = parser.parse().unwrap();
+
+        assert_eq!(stmts[0].to_string(), "(set (get var a b) c (get var d e))");
+    }
+
+    #[test]
+    fn test_method_call() {
+        let src = "a.b();";
+
+        let mut tokenizer = Tokenizer::new(src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let stmts = parser.parse().unwrap();
+
+        assert_eq!(stmts[0].to_string(), "((get var a b) [])");
+    }
+
+    #[test]
+    fn test_self_outside_class() {
+        let src = "echo self;";
+
+        let mut tokenizer = Tokenizer::new(src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+
+        assert_eq!(parser.parse().err().unwrap(), vec![CorrodeError::parse(1, String::from("Can not use 'self' outside of a class")).with_column(6)]);
+    }
+
+    #[test]
+    fn test_self_outside_class_keeps_parsing() {
+        let src = "fn f() { return self; }\nfn g() { return super.g() + \"${self}\"; }\necho 1;";
+
+        let mut tokenizer = Tokenizer::new(src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+
+        assert_eq!(parser.parse().err().unwrap(), vec![
+            CorrodeError::parse(1, String::from("Can not use 'self' outside of a class")).with_column(17),
+            CorrodeError::parse(2, String::from("Can not use 'super' outside of a class")).with_column(17),
+            CorrodeError::parse(2, String::from("Can not use 'self' outside of a class")).with_column(32),
+        ]);
+    }
+
+    #[test]
+    fn test_subclass() {
+        let src = "class Dog < Animal { fn speak() { return super.speak(); } }";
+
+        let mut tokenizer = Tokenizer::new(src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let stmts = parser.parse().unwrap();
+
+        assert_eq!(stmts[0].to_string(), "(class Dog < var Animal (fn speak () (return ((super speak) []))))");
+    }
+
+    #[test]
+    fn test_self_in_interpolation() {
+        let src = "class A { fn name() { return \"${self.n}\"; } }";
+
+        let mut tokenizer = Tokenizer::new(src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let stmts = parser.parse().unwrap();
+
+        assert_eq!(stmts[0].to_string(), "(class A (fn name () (return (interpolate  (get self n) ))))");
+    }
+
+    #[test]
+    fn test_inherit_from_self() {
+        let src = "class Dog < Dog {}";
+
+        let mut tokenizer = Tokenizer::new(src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+
+        assert_eq!(parser.parse().err().unwrap(), vec![CorrodeError::parse(1, String::from("A class can not inherit from itself")).with_column(13)]);
+    }
+
+    #[test]
+    fn test_echo_many() {
+        let src = "echo 1; echo a, 2 + 3;";
+
+        let mut tokenizer = Tokenizer::new(src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let stmts = parser.parse().unwrap();
+
+        assert_eq!(stmts[0].to_string(), "(print 1)");
+        assert_eq!(stmts[1].to_string(), "(print var a (+ 2 3))");
+    }
+
+    #[test]
+    fn test_array() {
+        let src = "[]; [1, 2 + 3]; [1, 2,];";
+
+        let mut tokenizer = Tokenizer::new(src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let stmts = parser.parse().unwrap();
+
+        assert_eq!(stmts[0].to_string(), "(array )");
+        assert_eq!(stmts[1].to_string(), "(array 1 (+ 2 3))");
+        assert_eq!(stmts[2].to_string(), "(array 1 2)");
+    }
+
+    #[test]
+    fn test_index() {
+        let src = "a[0][i + 1]; a[0] = 9;";
+
+        let mut tokenizer = Tokenizer::new(src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let stmts = parser.parse().unwrap();
+
+        assert_eq!(stmts[0].to_string(), "(index (index var a 0) (+ var i 1))");
+        assert_eq!(stmts[1].to_string(), "(index-set var a 0 9)");
+    }
+
+    #[test]
+    fn test_slice() {
+        let src = "a[1:n - 1]; a[:2]; a[-2:]; a[:]; a[b ? 1 : 2:];";
+
+        let mut tokenizer = Tokenizer::new(src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let stmts = parser.parse().unwrap();
+
+        assert_eq!(stmts[0].to_string(), "(slice var a 1:(- var n 1))");
+        assert_eq!(stmts[1].to_string(), "(slice var a :2)");
+        assert_eq!(stmts[2].to_string(), "(slice var a (- 2):)");
+        assert_eq!(stmts[3].to_string(), "(slice var a :)");
+        assert_eq!(stmts[4].to_string(), "(slice var a (? var b 1 2):)");
+    }
+
+    #[test]
+    fn test_map() {
+        let src = "echo {}; echo { \"a\": 1, 2: 3, };";
+
+        let mut tokenizer = Tokenizer::new(src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let stmts = parser.parse().unwrap();
+
+        assert_eq!(stmts[0].to_string(), "(print (map ))");
+        assert_eq!(stmts[1].to_string(), "(print (map (a 1) (2 3)))");
+    }
+
+    #[test]
+    fn test_compound_assignment() {
+        let src = "x += 2; a[0] *= y; a.b -= 1;";
+
+        let mut tokenizer = Tokenizer::new(src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let stmts = parser.parse().unwrap();
+
+        assert_eq!(stmts[0].to_string(), "x = (+ var x 2)");
+        assert_eq!(stmts[1].to_string(), "(index-set var a 0 *= var y)");
+        assert_eq!(stmts[2].to_string(), "(set var a b -= 1)");
+    }
+
+    #[test]
+    fn test_compound_assignment_target() {
+        let src = "1 -= 2;";
+
+        let mut tokenizer = Tokenizer::new(src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+
+        assert_eq!(parser.parse().err().unwrap(), vec![CorrodeError::parse(1, String::from("Invalid assingment target")).with_column(3)]);
+    }
+
+    #[test]
+    fn test_bitwise_precedence() {
+        let src = "a | b ^ c & 1 << 2 + 3 == ~d;";
+
+        let mut tokenizer = Tokenizer::new(src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let stmts = parser.parse().unwrap();
+
+        assert_eq!(stmts[0].to_string(), "(== (| var a (^ var b (& var c (<< 1 (+ 2 3))))) (~ var d))");
+    }
+
+    #[test]
+    fn test_too_much_nesting() {
+        let src = format!("echo {}1{};", "(".repeat(50_000), ")".repeat(50_000));
+        let tokens = Tokenizer::new(&src).tokenize().unwrap();
+        assert_eq!(Parser::new(tokens).parse().err().unwrap(), vec![CorrodeError::parse(1, String::from("Too much nesting")).with_column(22)]);
+
+        let tokens = Tokenizer::new(&format!("{}1", "-".repeat(50_000))).tokenize().unwrap();
+        assert!(Parser::new(tokens).parse_expression().is_err());
+
+        let src = format!("{}1{}", "(".repeat(15), ")".repeat(15));
+        let tokens = Tokenizer::new(&src).tokenize().unwrap();
+        assert!(Parser::new(tokens).parse_expression().is_ok());
+    }
+
+    #[test]
+    fn test_too_much_statement_nesting() {
+        let sources = [
+            format!("{}echo 1;{}", "{".repeat(50_000), "}".repeat(50_000)),
+            format!("{}echo 1;", "if (true) ".repeat(50_000)),
+            format!("{}echo 1;", "while (true) ".repeat(50_000)),
+            format!("{}{}", "fn f() {".repeat(50_000), "}".repeat(50_000)),
+            format!("let {}a{} = 1;", "(".repeat(50_000), ")".repeat(50_000)),
+        ];
+
+        for src in sources {
+            let tokens = Tokenizer::new(&src).tokenize().unwrap();
+            let errors = Parser::new(tokens).parse().err().unwrap();
+            assert_eq!(errors[0].to_string(), "[line 1] Too much nesting", "{}", &src[..20]);
+        }
+
+        let src = format!("{}echo 1;{}", "{".repeat(15), "}".repeat(15));
+        let tokens = Tokenizer::new(&src).tokenize().unwrap();
+        assert!(Parser::new(tokens).parse().is_ok());
+    }
+
+    #[test]
+    fn test_max_nesting() {
+        let tokens = Tokenizer::new("((1))").tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        parser.set_max_nesting(3);
+        assert!(parser.parse_expression().is_ok());
+
+        let tokens = Tokenizer::new("(((1)))").tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        parser.set_max_nesting(3);
+        assert_eq!(parser.parse_expression().err().unwrap(), CorrodeError::parse(1, String::from("Too much nesting")).with_column(4));
+
+        let tokens = Tokenizer::new("1 ** 2 ** 3 ** 4").tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        parser.set_max_nesting(3);
+        assert!(parser.parse_expression().is_err());
+    }
+
+    #[test]
+    fn test_error_limit() {
+        let src = "let = 1;\n".repeat(50);
+
+        let tokens = Tokenizer::new(&src).tokenize().unwrap();
+        let errors = Parser::new(tokens).parse().err().unwrap();
+        assert_eq!(errors.len(), 21);
+        assert_eq!(errors[19], CorrodeError::parse(20, String::from("Expected variable name")).with_column(5));
+        assert_eq!(errors[20], CorrodeError::parse(21, String::from("... (too many errors)")));
+
+        let tokens = Tokenizer::new(&src).tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        parser.set_max_errors(3);
+        assert_eq!(parser.parse().err().unwrap().len(), 4);
+
+        let tokens = Tokenizer::new(&"let = 1;\n".repeat(20)).tokenize().unwrap();
+        assert_eq!(Parser::new(tokens).parse().err().unwrap().len(), 20);
+    }
+
+    #[test]
+    fn test_parse_expression() {
+        let tokens = Tokenizer::new("1 + 2 * x").tokenize().unwrap();
+        let expr = Parser::new(tokens).parse_expression().unwrap();
+
+        assert_eq!(expr.to_string(), "(+ 1 (* 2 var x))");
+    }
+
+    #[test]
+    fn test_parse_expression_trailing_tokens() {
+        let tokens = Tokenizer::new("1 + 2 3").tokenize().unwrap();
+        assert_eq!(
+            Parser::new(tokens).parse_expression().err().unwrap(),
+            CorrodeError::parse(1, String::from("Expected end of expression, found '3'")).with_column(7)
+        );
+
+        let tokens = Tokenizer::new("1 + 2;").tokenize().unwrap();
+        assert_eq!(
+            Parser::new(tokens).parse_expression().err().unwrap(),
+            CorrodeError::parse(1, String::from("Expected end of expression, found ';'")).with_column(6)
+        );
+    }
+
+    #[test]
+    fn test_let_several_names() {
+        let src = "let a = 1, b, c = a;";
+
+        let mut tokenizer = Tokenizer::new(src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let stmts = parser.parse().unwrap();
+
+        assert_eq!(stmts.len(), 1);
+        assert_eq!(stmts[0].to_string(), "(let a b c)");
+    }
+
+    #[test]
+    fn test_let_annotation() {
+        let src = "let x: int = 3, y, z: str;";
+
+        let mut tokenizer = Tokenizer::new(src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let stmts = parser.parse().unwrap();
+
+        assert_eq!(stmts[0].to_string(), "(let x: int y z: str)");
+    }
+
+    #[test]
+    fn test_let_malformed_annotation() {
+        let src = "let x: = 3;";
+
+        let mut tokenizer = Tokenizer::new(src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+
+        assert_eq!(parser.parse().err().unwrap(), vec![CorrodeError::parse(1, String::from("Expected type name after ':'")).with_column(8)]);
+    }
+
+    #[test]
+    fn test_const_requires_initializer() {
+        let src = "const x;";
+
+        let mut tokenizer = Tokenizer::new(src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+
+        assert_eq!(
+            parser.parse().err().unwrap(),
+            vec![CorrodeError::parse(1, String::from("Expected '=' after constant name, constants must be initialized")).with_column(8)]
+        );
+    }
+
+    #[test]
+    fn test_try_catch() {
+        let src = "try { throw 1 + 2; } catch (e) { echo e; }";
+
+        let mut tokenizer = Tokenizer::new(src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let stmts = parser.parse().unwrap();
+
+        assert_eq!(stmts[0].to_string(), "(try (block (throw (+ 1 2))) catch e (block (print var e)))");
+    }
+
+    #[test]
+    fn test_try_without_catch() {
+        let src = "try { throw 1; } echo 2;";
+
+        let mut tokenizer = Tokenizer::new(src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+
+        assert_eq!(parser.parse().err().unwrap(), vec![CorrodeError::parse(1, String::from("Expected 'catch' after try block")).with_column(18)]);
+    }
+
+    #[test]
+    fn test_break_outside_loop() {
+        let src = "if (true) { break; }";
+
+        let mut tokenizer = Tokenizer::new(src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+
+        assert_eq!(parser.parse().err().unwrap(), vec![CorrodeError::parse(1, String::from("Can not use 'break' outside of a loop")).with_column(13)]);
+    }
+
+    #[test]
+    fn test_continue_in_function_in_loop() {
+        let src = "while (true) { fn f() { continue; } }";
+
+        let mut tokenizer = Tokenizer::new(src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+
+        assert_eq!(parser.parse().err().unwrap(), vec![CorrodeError::parse(1, String::from("Can not use 'continue' outside of a loop")).with_column(25)]);
+    }
+
+    #[test]
+    fn test_pretty() {
+        let src = "fn add(a, b) { return a + b * 2; }\nif (add(1, 2) > 3) echo \"big\"; else echo [];";
+
+        let mut tokenizer = Tokenizer::new(src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let stmts = parser.parse().unwrap();
+
+        assert_eq!(stmts[0].pretty(0), "\
+Function
+  name: add
+  params: (a, b)
+  body:
+    - Return
+      value: Binary
+        op: +
+        left: Variable a
+        right: Binary
+          op: *
+          left: Variable b
+          right: Literal 2");
+
+        assert_eq!(stmts[1].pretty(0), "\
+If
+  predicate: Binary
+    op: >
+    left: Call
+      callee: Variable add
+      arguments:
+        - Literal 1
+        - Literal 2
+    right: Literal 3
+  then: Echo
+    expressions:
+      - Literal \"big\"
+  otherwise: Echo
+    expressions:
+      - Array
+        elements: []");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serialize_json() {
+        let src = "let x = -1.5 + 2;";
+
+        let mut tokenizer = Tokenizer::new(src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let stmts = parser.parse().unwrap();
+
+        assert_eq!(serde_json::to_string(&stmts[0]).unwrap(), concat!(
+            r#"{"Let":{"bindings":[{"name":{"token_type":"Identifier","lexeme":"x","literal":null,"line_number":1,"column":5,"offset":4},"#,
+            r#""annotation":null,"initializer":{"Binary":{"left":{"Unary":{"operator":{"token_type":"Minus","lexeme":"-","literal":null,"line_number":1,"column":9,"offset":8},"#,
+            r#""right":{"Literal":{"value":1.5,"span":{"start":9,"end":12,"line":1}}},"span":{"start":8,"end":12,"line":1}}},"#,
+            r#""operator":{"token_type":"Plus","lexeme":"+","literal":null,"line_number":1,"column":14,"offset":13},"#,
+            r#""right":{"Literal":{"value":2,"span":{"start":15,"end":16,"line":1}}},"span":{"start":8,"end":16,"line":1}}}}],"#,
+            r#""span":{"start":0,"end":17,"line":1}}}"#
+        ));
+    }
+
+    #[test]
+    fn test_expected_expression_names_token() {
+        let mut tokenizer = Tokenizer::new(")");
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+
+        assert_eq!(parser.parse().err().unwrap(), vec![CorrodeError::parse(1, String::from("Expected expression, found ')'")).with_column(1)]);
+
+        let mut tokenizer = Tokenizer::new("\n1 +");
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+
+        assert_eq!(parser.parse().err().unwrap(), vec![CorrodeError::parse(2, String::from("Expected expression, found end of input")).with_column(4)]);
+    }
+
+    #[test]
+    fn test_errors_keep_their_lines() {
+        let src = "let = 1;\necho 2;\necho ;";
+
+        let mut tokenizer = Tokenizer::new(src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let errors = parser.parse().err().unwrap();
+
+        assert_eq!(errors.iter().map(|err| err.line()).collect::<Vec<usize>>(), vec![1, 3]);
+        assert_eq!(errors[1].message(), "Expected expression, found ';'");
+    }
+
+    #[test]
+    fn test_stored_tokens() {
+        let src = "let abc = 1;\nabc = abc * 2;";
+
+        let mut tokenizer = Tokenizer::new(src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let stmts = parser.parse().unwrap();
+
+        match &stmts[0] {
+            Stmt::Let { bindings, span: _ } => {
+                let name = &bindings[0].name;
+                assert_eq!((name.lexeme.as_str(), name.line_number, name.column), ("abc", 1, 5));
+            },
+            _ => panic!("Expected a let statement"),
+        }
+        match &stmts[1] {
+            Stmt::Expression { expression: Expr::Assign { name, depth: _, value, span: _ }, span: _ } => {
+                assert_eq!((name.lexeme.as_str(), name.line_number, name.column), ("abc", 2, 1));
+                match value.as_ref() {
+                    Expr::Binary { left: _, operator, right: _, span: _ } => assert_eq!((operator.lexeme.as_str(), operator.column), ("*", 11)),
+                    _ => panic!("Expected a binary expression"),
+                }
+            },
+            _ => panic!("Expected an assignment"),
+        }
+    }
+
+    #[test]
+    fn test_spans() {
+        let src = "echo 0;
+x = 1 + 2;
+if (x) { f(x)[0]; }";
+
+        let mut tokenizer = Tokenizer::new(src);
+        let tokens = tokenizer.tokenize().unwrap();
+        let mut parser = Parser::new(tokens);
+        let stmts = parser.parse().unwrap();
+
+        let sum = match &stmts[1] {
+            Stmt::Expression { expression: Expr::Assign { value, .. }, .. } => value.as_ref(),
+            _ => panic!("Expected an assignment"),
+        };
+        assert_eq!(sum.span(), Span { start: 12, end: 17, line: 2 });
+        assert_eq!(&src[sum.span().start..sum.span().end], "1 + 2");
+        assert_eq!(sum.to_string(), "(+ 1 2)");
+
+        let spanned = stmts.iter().map(|stmt| &src[stmt.span().start..stmt.span().end]).collect::<Vec<&str>>();
+        assert_eq!(spanned, vec!["echo 0;", "x = 1 + 2;", "if (x) { f(x)[0]; }"]);
+
+        let index = match &stmts[2] {
+            Stmt::If { then, .. } => match then.as_ref() {
+                Stmt::Block { statements, .. } => statements[0].span(),
+                _ => panic!("Expected a block"),
+            },
+            _ => panic!("Expected an if statement"),
+        };
+        assert_eq!(&src[index.start..index.end], "f(x)[0];");
+        assert_eq!(index.line, 3);
+    }
+}
@@ -0,0 +1,416 @@
+use crate::error::CorrodeError;
+use crate::expr::{Depth, Expr, ExprVisitor, LiteralValue, Pattern};
+use crate::interner::Symbol;
+use crate::stmt::{Binding, Stmt, StmtVisitor};
+use crate::tokenizer::Token;
+use std::collections::HashMap;
+
+// Works out which declaration every variable reference means before the program runs, so a closure keeps
+// reading the variable it closed over even if the same name is declared again later in that scope.
+// The scopes pushed here have to match the environments the interpreter creates one for one
+pub struct Resolver {
+    // The value is false between declaring a name and the end of its initializer
+    scopes: Vec<HashMap<Symbol, bool>>,
+    errors: Vec<CorrodeError>,
+}
+
+impl Default for Resolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Resolver {
+    pub fn new() -> Self {
+        Self {
+            scopes: Vec::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    pub fn resolve(&mut self, stmts: &[Stmt]) -> Result<(), Vec<CorrodeError>> {
+        for stmt in stmts {
+            stmt.accept(self);
+        }
+
+        self.finish()
+    }
+
+    // For a bare expression typed into the REPL
+    pub fn resolve_expr(&mut self, expr: &Expr) -> Result<(), Vec<CorrodeError>> {
+        expr.accept(self);
+
+        self.finish()
+    }
+
+    fn finish(&mut self) -> Result<(), Vec<CorrodeError>> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(std::mem::take(&mut self.errors))
+        }
+    }
+
+    // Names at the top level are globals and are not tracked
+    fn declare(&mut self, name: &Token) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.symbol(), false);
+        }
+    }
+
+    fn define(&mut self, name: &Token) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.symbol(), true);
+        }
+    }
+
+    fn define_pattern(&mut self, pattern: &Pattern) {
+        match pattern {
+            Pattern::Name(name, _) => self.define(name),
+            Pattern::Tuple(elements) => {
+                for element in elements {
+                    self.define_pattern(element);
+                }
+            },
+        }
+    }
+
+    fn resolve_local(&mut self, name: &Token, depth: &Depth) {
+        let symbol = name.symbol();
+        depth.set(self.scopes.iter().rev().position(|scope| scope.contains_key(&symbol)));
+    }
+
+    fn resolve_pattern(&mut self, pattern: &Pattern) {
+        match pattern {
+            Pattern::Name(name, depth) => self.resolve_local(name, depth),
+            Pattern::Tuple(elements) => {
+                for element in elements {
+                    self.resolve_pattern(element);
+                }
+            },
+        }
+    }
+
+    fn scoped(&mut self, resolve: impl FnOnce(&mut Self)) {
+        self.scopes.push(HashMap::new());
+        resolve(self);
+        self.scopes.pop();
+    }
+
+    // Parameters and body share the one environment Function::call creates
+    fn resolve_function(&mut self, params: &[Token], rest: Option<&Token>, body: &[Stmt]) {
+        self.scoped(|resolver| {
+            for param in params.iter().chain(rest) {
+                resolver.define(param);
+            }
+            for stmt in body {
+                stmt.accept(resolver);
+            }
+        });
+    }
+}
+
+impl ExprVisitor<()> for Resolver {
+    fn visit_binary(&mut self, left: &Expr, _operator: &Token, right: &Expr) {
+        left.accept(self);
+        right.accept(self);
+    }
+
+    fn visit_chain(&mut self, operands: &[Expr], _operators: &[Token]) {
+        for operand in operands {
+            operand.accept(self);
+        }
+    }
+
+    fn visit_grouping(&mut self, expression: &Expr) {
+        expression.accept(self);
+    }
+
+    fn visit_tuple(&mut self, elements: &[Expr]) {
+        for element in elements {
+            element.accept(self);
+        }
+    }
+
+    fn visit_destructure(&mut self, pattern: &Pattern, _equals: &Token, value: &Expr) {
+        value.accept(self);
+        self.resolve_pattern(pattern);
+    }
+
+    fn visit_call(&mut self, callee: &Expr, _paren: &Token, arguments: &[Expr]) {
+        callee.accept(self);
+        for argument in arguments {
+            argument.accept(self);
+        }
+    }
+
+    fn visit_literal(&mut self, _value: &LiteralValue) {}
+
+    fn visit_logical(&mut self, left: &Expr, _operator: &Token, right: &Expr) {
+        left.accept(self);
+        right.accept(self);
+    }
+
+    fn visit_unary(&mut self, _operator: &Token, right: &Expr) {
+        right.accept(self);
+    }
+
+    fn visit_variable(&mut self, name: &Token, depth: &Depth) {
+        let declared_only = self.scopes.last()
+            .is_some_and(|scope| scope.get(&name.symbol()) == Some(&false));
+        if declared_only {
+            self.errors.push(CorrodeError::resolve(
+                name.line_number,
+                String::from("Can't read local variable in its own initializer")
+            ).with_column(name.column));
+        }
+
+        self.resolve_local(name, depth);
+    }
+
+    fn visit_assign(&mut self, name: &Token, depth: &Depth, value: &Expr) {
+        value.accept(self);
+        self.resolve_local(name, depth);
+    }
+
+    fn visit_interpolation(&mut self, parts: &[Expr]) {
+        for part in parts {
+            part.accept(self);
+        }
+    }
+
+    fn visit_array(&mut self, elements: &[Expr]) {
+        for element in elements {
+            element.accept(self);
+        }
+    }
+
+    fn visit_spread(&mut self, _ellipsis: &Token, inner: &Expr) {
+        inner.accept(self);
+    }
+
+    fn visit_map(&mut self, _brace: &Token, entries: &[(Expr, Expr)]) {
+        for (key, value) in entries {
+            key.accept(self);
+            value.accept(self);
+        }
+    }
+
+    fn visit_index(&mut self, object: &Expr, _bracket: &Token, index: &Expr) {
+        object.accept(self);
+        index.accept(self);
+    }
+
+    fn visit_slice(&mut self, object: &Expr, _bracket: &Token, start: Option<&Expr>, end: Option<&Expr>) {
+        object.accept(self);
+        for bound in start.into_iter().chain(end) {
+            bound.accept(self);
+        }
+    }
+
+    fn visit_index_set(&mut self, object: &Expr, _bracket: &Token, index: &Expr, _operator: Option<&Token>, value: &Expr) {
+        object.accept(self);
+        index.accept(self);
+        value.accept(self);
+    }
+
+    fn visit_ternary(&mut self, condition: &Expr, then_expr: &Expr, else_expr: &Expr) {
+        condition.accept(self);
+        then_expr.accept(self);
+        else_expr.accept(self);
+    }
+
+    fn visit_get(&mut self, object: &Expr, _name: &Token) {
+        object.accept(self);
+    }
+
+    fn visit_set(&mut self, object: &Expr, _name: &Token, _operator: Option<&Token>, value: &Expr) {
+        object.accept(self);
+        value.accept(self);
+    }
+
+    // `self` and `super` are still looked up by name, they are always in the nearest scope that has them
+    fn visit_self(&mut self, _keyword: &Token) {}
+
+    fn visit_super(&mut self, _keyword: &Token, _method: &Token) {}
+
+    fn visit_lambda(&mut self, _keyword: &Token, params: &[Token], rest: Option<&Token>, body: &[Stmt]) {
+        self.resolve_function(params, rest, body);
+    }
+}
+
+impl StmtVisitor<()> for Resolver {
+    fn visit_expression(&mut self, expression: &Expr) {
+        expression.accept(self);
+    }
+
+    fn visit_echo(&mut self, expressions: &[Expr]) {
+        for expression in expressions {
+            expression.accept(self);
+        }
+    }
+
+    fn visit_print(&mut self, expression: &Expr, _newline: bool) {
+        expression.accept(self);
+    }
+
+    fn visit_let(&mut self, bindings: &[Binding]) {
+        for binding in bindings {
+            self.declare(&binding.name);
+            if let Some(initializer) = &binding.initializer {
+                initializer.accept(self);
+            }
+            self.define(&binding.name);
+        }
+    }
+
+    fn visit_let_destructure(&mut self, pattern: &Pattern, _equals: &Token, initializer: &Expr) {
+        initializer.accept(self);
+        self.define_pattern(pattern);
+    }
+
+    fn visit_const(&mut self, name: &Token, initializer: &Expr) {
+        self.declare(name);
+        initializer.accept(self);
+        self.define(name);
+    }
+
+    fn visit_block(&mut self, statements: &[Stmt]) {
+        self.scoped(|resolver| {
+            for stmt in statements {
+                stmt.accept(resolver);
+            }
+        });
+    }
+
+    fn visit_if(&mut self, predicate: &Expr, then: &Stmt, otherwise: Option<&Stmt>) {
+        predicate.accept(self);
+        then.accept(self);
+        if let Some(otherwise) = otherwise {
+            otherwise.accept(self);
+        }
+    }
+
+    fn visit_match(&mut self, subject: &Expr, arms: &[(Vec<Expr>, Stmt)], default: Option<&Stmt>) {
+        subject.accept(self);
+        for (patterns, body) in arms {
+            for pattern in patterns {
+                pattern.accept(self);
+            }
+            body.accept(self);
+        }
+        if let Some(default) = default {
+            default.accept(self);
+        }
+    }
+
+    fn visit_while(&mut self, condition: &Expr, body: &Stmt, increment: Option<&Expr>) {
+        condition.accept(self);
+        body.accept(self);
+        if let Some(increment) = increment {
+            increment.accept(self);
+        }
+    }
+
+    fn visit_do_while(&mut self, body: &Stmt, condition: &Expr) {
+        body.accept(self);
+        condition.accept(self);
+    }
+
+    fn visit_for_in(&mut self, key: Option<&Token>, item: &Token, iterable: &Expr, body: &Stmt) {
+        iterable.accept(self);
+
+        self.scoped(|resolver| {
+            if let Some(key) = key {
+                resolver.define(key);
+            }
+            resolver.define(item);
+            body.accept(resolver);
+        });
+    }
+
+    // Defined before the body so the function can call itself
+    fn visit_function(&mut self, name: &Token, params: &[Token], rest: Option<&Token>, body: &[Stmt]) {
+        self.define(name);
+        self.resolve_function(params, rest, body);
+    }
+
+    // Methods close over a scope holding `super` when there is a superclass, and are bound in one holding `self`
+    fn visit_class(&mut self, name: &Token, superclass: Option<&Expr>, methods: &[Stmt]) {
+        if let Some(superclass) = superclass {
+            superclass.accept(self);
+        }
+        self.define(name);
+
+        let resolve_methods = |resolver: &mut Self| resolver.scoped(|resolver| {
+            for method in methods {
+                if let Stmt::Function { name: _, params, rest, body, .. } = method {
+                    resolver.resolve_function(params, rest.as_ref(), body);
+                }
+            }
+        });
+
+        if superclass.is_some() {
+            self.scoped(resolve_methods);
+        } else {
+            resolve_methods(self);
+        }
+    }
+
+    fn visit_return(&mut self, _keyword: &Token, value: Option<&Expr>) {
+        if let Some(value) = value {
+            value.accept(self);
+        }
+    }
+
+    fn visit_break(&mut self) {}
+
+    fn visit_continue(&mut self) {}
+
+    fn visit_assert(&mut self, _keyword: &Token, condition: &Expr, message: Option<&Expr>) {
+        condition.accept(self);
+        if let Some(message) = message {
+            message.accept(self);
+        }
+    }
+
+    fn visit_throw(&mut self, _keyword: &Token, value: &Expr) {
+        value.accept(self);
+    }
+
+    // The caught value lives in its own scope around the handler, like the names of a for-in loop
+    fn visit_try(&mut self, body: &Stmt, name: &Token, handler: &Stmt) {
+        body.accept(self);
+
+        self.scoped(|resolver| {
+            resolver.define(name);
+            handler.accept(resolver);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tokenizer::Tokenizer;
+    use crate::parser::Parser;
+
+    fn resolve(source: &str) -> Result<(), Vec<CorrodeError>> {
+        let tokens = Tokenizer::new(source).tokenize().unwrap();
+        let stmts = Parser::new(tokens).parse().unwrap();
+
+        Resolver::new().resolve(&stmts)
+    }
+
+    #[test]
+    fn own_initializer() {
+        assert_eq!(resolve("{\n let a = 1; { let a = a; } }"), Err(vec![
+            CorrodeError::resolve(2, String::from("Can't read local variable in its own initializer")).with_column(23)
+        ]));
+    }
+
+    #[test]
+    fn globals_may_read_themselves() {
+        assert_eq!(resolve("let a = 1; let a = a + 1; { let b = a; }"), Ok(()));
+    }
+}
@@ -1,30 +1,398 @@
-use crate::expr::Expr;
-use crate::tokenizer::Token;
-
-pub enum Stmt {
-    Expression { expression: Expr },
-    Echo { expression: Expr },
-    Let { name: Token, initializer: Expr },
-    Block { statements: Vec<Box<Stmt>> },
-    If { predicate: Expr, then: Box<Stmt>, otherwise: Option<Box<Stmt>> },
-    While { condition: Expr,  body: Box<Stmt> },
-    Break
-}
-
-impl Stmt {
-    #[allow(dead_code)]
-    pub fn to_string(&self) -> String {
-        match self {
-            Stmt::Expression { expression } => expression.to_string() ,
-            Stmt::Echo { expression } => format!("(print {})", expression.to_string()),
-            Stmt::Let { name, initializer: _ } => format!("(let {})", name.lexeme),
-            Stmt::Block { statements } => format!(
-                "(block {})",
-                statements.into_iter().map(|stmt| stmt.to_string()).collect::<String>()
-            ),
-            Stmt::If { predicate: _, then: _, otherwise: _ } => todo!(),
-            Stmt::While { condition: _, body: _ } => todo!(),
-            Stmt::Break => String::from("(break)")
-        }
-    }
-}
\ No newline at end of file
+use crate::expr::{Expr, AstPrinter, Pattern, pretty_list, params_to_string};
+use crate::tokenizer::{Span, Token};
+
+// One name in a let declaration. The annotation is only used by the TypeChecker
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Binding {
+    pub name: Token,
+    pub annotation: Option<Token>,
+    // None leaves the variable unset until it is assigned, reading it before then is an error
+    pub initializer: Option<Expr>,
+}
+
+impl Binding {
+    fn declared_name(&self) -> String {
+        match &self.annotation {
+            Some(annotation) => format!("{}: {}", self.name.lexeme, annotation.lexeme),
+            None => self.name.lexeme.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Stmt {
+    Expression { expression: Expr, span: Span },
+    Echo { expressions: Vec<Expr>, span: Span },
+    // `print` writes the value as it is, `println` ends it with a newline
+    Print { expression: Expr, newline: bool, span: Span },
+    // `let a = 1, b;` declares each name in order
+    Let { bindings: Vec<Binding>, span: Span },
+    // `let (a, b) = value;` defines every name of the pattern
+    LetDestructure { pattern: Pattern, equals: Token, initializer: Expr, span: Span },
+    Const { name: Token, initializer: Expr, span: Span },
+    Block { statements: Vec<Stmt>, span: Span },
+    If { predicate: Expr, then: Box<Stmt>, otherwise: Option<Box<Stmt>>, span: Span },
+    // Each arm runs when the subject equals one of its patterns, `default` is the `_` arm
+    Match { subject: Expr, arms: Vec<(Vec<Expr>, Stmt)>, default: Option<Box<Stmt>>, span: Span },
+    // `increment` is the third clause of a desugared for loop, it still runs after a continue
+    While { condition: Expr,  body: Box<Stmt>, increment: Option<Expr>, span: Span },
+    // The condition is first checked after the body has run once
+    DoWhile { body: Box<Stmt>, condition: Expr, span: Span },
+    // `key` is the index or map key of the two name form, `item` the element or map value
+    ForIn { key: Option<Token>, item: Token, iterable: Expr, body: Box<Stmt>, span: Span },
+    // `rest` is a trailing `...name` collecting the extra arguments
+    Function { name: Token, params: Vec<Token>, rest: Option<Token>, body: Vec<Stmt>, span: Span },
+    Class { name: Token, superclass: Option<Expr>, methods: Vec<Stmt>, span: Span },
+    Return {
+        keyword: Token,
+        value: Option<Expr>,
+        span: Span
+    },
+    Break { span: Span },
+    Continue { span: Span },
+    // `keyword` is kept for the line of the error when the condition is falsy
+    Assert { keyword: Token, condition: Expr, message: Option<Expr>, span: Span },
+    Throw { keyword: Token, value: Expr, span: Span },
+    // `handler` runs with the thrown value bound to `name` when `body` throws
+    Try { body: Box<Stmt>, name: Token, handler: Box<Stmt>, span: Span },
+}
+
+impl Stmt {
+    // Multi-line tree dump for debugging, laid out like Expr::pretty
+    pub fn pretty(&self, indent: usize) -> String {
+        let pad = " ".repeat(indent + 2);
+        let child = indent + 2;
+        let list = |label: &str, stmts: &Vec<Stmt>| pretty_list(
+            label,
+            stmts.iter().map(|stmt| stmt.pretty(child + 2)).collect(),
+            indent
+        );
+
+        match self {
+            Stmt::Expression { expression, .. } => format!("Expression\n{pad}expression: {}", expression.pretty(child)),
+            Stmt::Echo { expressions, .. } => format!(
+                "Echo{}",
+                pretty_list("expressions", expressions.iter().map(|expr| expr.pretty(child + 2)).collect(), indent)
+            ),
+            Stmt::Print { expression, newline, .. } => format!(
+                "Print\n{pad}newline: {}\n{pad}expression: {}",
+                newline,
+                expression.pretty(child)
+            ),
+            Stmt::Let { bindings, .. } => format!(
+                "Let{}",
+                pretty_list(
+                    "bindings",
+                    bindings.iter().map(|binding| match &binding.initializer {
+                        Some(initializer) => format!("{} = {}", binding.declared_name(), initializer.pretty(child + 2)),
+                        None => binding.declared_name(),
+                    }).collect(),
+                    indent
+                )
+            ),
+            Stmt::LetDestructure { pattern, equals: _, initializer, .. } => format!(
+                "LetDestructure\n{pad}pattern: {}\n{pad}initializer: {}",
+                pattern.to_string(),
+                initializer.pretty(child)
+            ),
+            Stmt::Const { name, initializer, .. } => format!(
+                "Const\n{pad}name: {}\n{pad}initializer: {}",
+                name.lexeme,
+                initializer.pretty(child)
+            ),
+            Stmt::Block { statements, .. } => format!("Block{}", list("statements", statements)),
+            Stmt::If { predicate, then, otherwise, .. } => {
+                let mut result = format!(
+                    "If\n{pad}predicate: {}\n{pad}then: {}",
+                    predicate.pretty(child),
+                    then.pretty(child)
+                );
+                if let Some(otherwise) = otherwise {
+                    result.push_str(&format!("\n{pad}otherwise: {}", otherwise.pretty(child)));
+                }
+                result
+            },
+            Stmt::Match { subject, arms, default, .. } => {
+                let mut arms = arms.iter().map(|(patterns, body)| format!(
+                    "Arm{}\n{}body: {}",
+                    pretty_list("patterns", patterns.iter().map(|pattern| pattern.pretty(child + 6)).collect(), child + 2),
+                    " ".repeat(child + 4),
+                    body.pretty(child + 4)
+                )).collect::<Vec<String>>();
+                if let Some(default) = default {
+                    arms.push(format!("Default\n{}body: {}", " ".repeat(child + 4), default.pretty(child + 4)));
+                }
+
+                format!("Match\n{pad}subject: {}{}", subject.pretty(child), pretty_list("arms", arms, indent))
+            },
+            Stmt::While { condition, body, increment, .. } => {
+                let mut result = format!(
+                    "While\n{pad}condition: {}\n{pad}body: {}",
+                    condition.pretty(child),
+                    body.pretty(child)
+                );
+                if let Some(increment) = increment {
+                    result.push_str(&format!("\n{pad}increment: {}", increment.pretty(child)));
+                }
+                result
+            },
+            Stmt::DoWhile { body, condition, .. } => format!(
+                "DoWhile\n{pad}body: {}\n{pad}condition: {}",
+                body.pretty(child),
+                condition.pretty(child)
+            ),
+            Stmt::ForIn { key, item, iterable, body, .. } => {
+                let mut result = String::from("ForIn");
+                if let Some(key) = key {
+                    result.push_str(&format!("\n{pad}key: {}", key.lexeme));
+                }
+                result.push_str(&format!(
+                    "\n{pad}item: {}\n{pad}iterable: {}\n{pad}body: {}",
+                    item.lexeme,
+                    iterable.pretty(child),
+                    body.pretty(child)
+                ));
+                result
+            },
+            Stmt::Function { name, params, rest, body, .. } => format!(
+                "Function\n{pad}name: {}\n{pad}params: ({}){}",
+                name.lexeme,
+                params_to_string(params, rest.as_ref(), ", "),
+                list("body", body)
+            ),
+            Stmt::Class { name, superclass, methods, .. } => {
+                let mut result = format!("Class\n{pad}name: {}", name.lexeme);
+                if let Some(superclass) = superclass {
+                    result.push_str(&format!("\n{pad}superclass: {}", superclass.pretty(child)));
+                }
+                result.push_str(&list("methods", methods));
+                result
+            },
+            Stmt::Return { keyword: _, value, .. } => match value {
+                Some(value) => format!("Return\n{pad}value: {}", value.pretty(child)),
+                None => String::from("Return"),
+            },
+            Stmt::Break { .. } => String::from("Break"),
+            Stmt::Continue { .. } => String::from("Continue"),
+            Stmt::Assert { keyword: _, condition, message, .. } => {
+                let mut result = format!("Assert\n{pad}condition: {}", condition.pretty(child));
+                if let Some(message) = message {
+                    result.push_str(&format!("\n{pad}message: {}", message.pretty(child)));
+                }
+                result
+            },
+            Stmt::Throw { keyword: _, value, .. } => format!("Throw\n{pad}value: {}", value.pretty(child)),
+            Stmt::Try { body, name, handler, .. } => format!(
+                "Try\n{pad}body: {}\n{pad}name: {}\n{pad}handler: {}",
+                body.pretty(child),
+                name.lexeme,
+                handler.pretty(child)
+            ),
+        }
+    }
+
+    // The source the statement was parsed from, for tooling and diagnostics
+    pub fn span(&self) -> Span {
+        match self {
+            Stmt::Expression { span, .. } |
+            Stmt::Echo { span, .. } |
+            Stmt::Print { span, .. } |
+            Stmt::Let { span, .. } |
+            Stmt::LetDestructure { span, .. } |
+            Stmt::Const { span, .. } |
+            Stmt::Block { span, .. } |
+            Stmt::If { span, .. } |
+            Stmt::Match { span, .. } |
+            Stmt::While { span, .. } |
+            Stmt::DoWhile { span, .. } |
+            Stmt::ForIn { span, .. } |
+            Stmt::Function { span, .. } |
+            Stmt::Class { span, .. } |
+            Stmt::Return { span, .. } |
+            Stmt::Break { span, .. } |
+            Stmt::Continue { span, .. } |
+            Stmt::Assert { span, .. } |
+            Stmt::Throw { span, .. } |
+            Stmt::Try { span, .. } => *span,
+        }
+    }
+
+    pub fn accept<T, V: StmtVisitor<T>>(&self, visitor: &mut V) -> T {
+        match self {
+            Stmt::Expression { expression, .. } => visitor.visit_expression(expression),
+            Stmt::Echo { expressions, .. } => visitor.visit_echo(expressions),
+            Stmt::Print { expression, newline, .. } => visitor.visit_print(expression, *newline),
+            Stmt::Let { bindings, .. } => visitor.visit_let(bindings),
+            Stmt::LetDestructure { pattern, equals, initializer, .. } => visitor.visit_let_destructure(pattern, equals, initializer),
+            Stmt::Const { name, initializer, .. } => visitor.visit_const(name, initializer),
+            Stmt::Block { statements, .. } => visitor.visit_block(statements),
+            Stmt::If { predicate, then, otherwise, .. } => visitor.visit_if(predicate, then, otherwise.as_deref()),
+            Stmt::Match { subject, arms, default, .. } => visitor.visit_match(subject, arms, default.as_deref()),
+            Stmt::While { condition, body, increment, .. } => visitor.visit_while(condition, body, increment.as_ref()),
+            Stmt::DoWhile { body, condition, .. } => visitor.visit_do_while(body, condition),
+            Stmt::ForIn { key, item, iterable, body, .. } => visitor.visit_for_in(key.as_ref(), item, iterable, body),
+            Stmt::Function { name, params, rest, body, .. } => visitor.visit_function(name, params, rest.as_ref(), body),
+            Stmt::Class { name, superclass, methods, .. } => visitor.visit_class(name, superclass.as_ref(), methods),
+            Stmt::Return { keyword, value, .. } => visitor.visit_return(keyword, value.as_ref()),
+            Stmt::Break { .. } => visitor.visit_break(),
+            Stmt::Continue { .. } => visitor.visit_continue(),
+            Stmt::Assert { keyword, condition, message, .. } => visitor.visit_assert(keyword, condition, message.as_ref()),
+            Stmt::Throw { keyword, value, .. } => visitor.visit_throw(keyword, value),
+            Stmt::Try { body, name, handler, .. } => visitor.visit_try(body, name, handler),
+        }
+    }
+
+    pub fn to_string(&self) -> String {
+        self.accept(&mut AstPrinter)
+    }
+}
+
+// The statement counterpart of ExprVisitor
+pub trait StmtVisitor<T> {
+    fn visit_expression(&mut self, expression: &Expr) -> T;
+    fn visit_echo(&mut self, expressions: &[Expr]) -> T;
+    fn visit_print(&mut self, expression: &Expr, newline: bool) -> T;
+    fn visit_let(&mut self, bindings: &[Binding]) -> T;
+    fn visit_let_destructure(&mut self, pattern: &Pattern, equals: &Token, initializer: &Expr) -> T;
+    fn visit_const(&mut self, name: &Token, initializer: &Expr) -> T;
+    fn visit_block(&mut self, statements: &[Stmt]) -> T;
+    fn visit_if(&mut self, predicate: &Expr, then: &Stmt, otherwise: Option<&Stmt>) -> T;
+    fn visit_match(&mut self, subject: &Expr, arms: &[(Vec<Expr>, Stmt)], default: Option<&Stmt>) -> T;
+    fn visit_while(&mut self, condition: &Expr, body: &Stmt, increment: Option<&Expr>) -> T;
+    fn visit_do_while(&mut self, body: &Stmt, condition: &Expr) -> T;
+    fn visit_for_in(&mut self, key: Option<&Token>, item: &Token, iterable: &Expr, body: &Stmt) -> T;
+    fn visit_function(&mut self, name: &Token, params: &[Token], rest: Option<&Token>, body: &[Stmt]) -> T;
+    fn visit_class(&mut self, name: &Token, superclass: Option<&Expr>, methods: &[Stmt]) -> T;
+    fn visit_return(&mut self, keyword: &Token, value: Option<&Expr>) -> T;
+    fn visit_break(&mut self) -> T;
+    fn visit_continue(&mut self) -> T;
+    fn visit_assert(&mut self, keyword: &Token, condition: &Expr, message: Option<&Expr>) -> T;
+    fn visit_throw(&mut self, keyword: &Token, value: &Expr) -> T;
+    fn visit_try(&mut self, body: &Stmt, name: &Token, handler: &Stmt) -> T;
+}
+
+impl StmtVisitor<String> for AstPrinter {
+    fn visit_expression(&mut self, expression: &Expr) -> String {
+        expression.accept(self)
+    }
+
+    fn visit_echo(&mut self, expressions: &[Expr]) -> String {
+        format!(
+            "(print {})",
+            expressions.iter().map(|expr| expr.accept(self)).collect::<Vec<String>>().join(" ")
+        )
+    }
+
+    fn visit_print(&mut self, expression: &Expr, newline: bool) -> String {
+        let keyword = if newline { "writeln" } else { "write" };
+        format!("({} {})", keyword, expression.accept(self))
+    }
+
+    fn visit_let(&mut self, bindings: &[Binding]) -> String {
+        format!(
+            "(let {})",
+            bindings.iter().map(Binding::declared_name).collect::<Vec<String>>().join(" ")
+        )
+    }
+
+    fn visit_let_destructure(&mut self, pattern: &Pattern, _equals: &Token, initializer: &Expr) -> String {
+        format!("(let {} {})", pattern.to_string(), initializer.accept(self))
+    }
+
+    fn visit_const(&mut self, name: &Token, initializer: &Expr) -> String {
+        format!("(const {} {})", name.lexeme, initializer.accept(self))
+    }
+
+    fn visit_block(&mut self, statements: &[Stmt]) -> String {
+        format!(
+            "(block {})",
+            statements.iter().map(|stmt| stmt.accept(self)).collect::<String>()
+        )
+    }
+
+    fn visit_if(&mut self, predicate: &Expr, then: &Stmt, otherwise: Option<&Stmt>) -> String {
+        match otherwise {
+            Some(otherwise) => format!("(if {} {} {})", predicate.accept(self), then.accept(self), otherwise.accept(self)),
+            None => format!("(if {} {})", predicate.accept(self), then.accept(self)),
+        }
+    }
+
+    fn visit_match(&mut self, subject: &Expr, arms: &[(Vec<Expr>, Stmt)], default: Option<&Stmt>) -> String {
+        let mut result = format!("(match {}", subject.accept(self));
+        for (patterns, body) in arms {
+            result.push_str(&format!(" ({} => {})", self.join(patterns), body.accept(self)));
+        }
+        if let Some(default) = default {
+            result.push_str(&format!(" (_ => {})", default.accept(self)));
+        }
+        result.push(')');
+        result
+    }
+
+    fn visit_while(&mut self, condition: &Expr, body: &Stmt, increment: Option<&Expr>) -> String {
+        match increment {
+            Some(increment) => format!("(while {} {} {})", condition.accept(self), body.accept(self), increment.accept(self)),
+            None => format!("(while {} {})", condition.accept(self), body.accept(self)),
+        }
+    }
+
+    fn visit_do_while(&mut self, body: &Stmt, condition: &Expr) -> String {
+        format!("(do {} while {})", body.accept(self), condition.accept(self))
+    }
+
+    fn visit_for_in(&mut self, key: Option<&Token>, item: &Token, iterable: &Expr, body: &Stmt) -> String {
+        match key {
+            Some(key) => format!("(for {} {} in {} {})", key.lexeme, item.lexeme, iterable.accept(self), body.accept(self)),
+            None => format!("(for {} in {} {})", item.lexeme, iterable.accept(self), body.accept(self)),
+        }
+    }
+
+    fn visit_function(&mut self, name: &Token, params: &[Token], rest: Option<&Token>, body: &[Stmt]) -> String {
+        format!(
+            "(fn {} ({}) {})",
+            name.lexeme,
+            params_to_string(params, rest, " "),
+            body.iter().map(|stmt| stmt.accept(self)).collect::<String>()
+        )
+    }
+
+    fn visit_class(&mut self, name: &Token, superclass: Option<&Expr>, methods: &[Stmt]) -> String {
+        let methods = methods.iter().map(|method| method.accept(self)).collect::<String>();
+        match superclass {
+            Some(superclass) => format!("(class {} < {} {})", name.lexeme, superclass.accept(self), methods),
+            None => format!("(class {} {})", name.lexeme, methods),
+        }
+    }
+
+    fn visit_return(&mut self, _keyword: &Token, value: Option<&Expr>) -> String {
+        match value {
+            Some(value) => format!("(return {})", value.accept(self)),
+            None => String::from("(return)"),
+        }
+    }
+
+    fn visit_break(&mut self) -> String {
+        String::from("(break)")
+    }
+
+    fn visit_continue(&mut self) -> String {
+        String::from("(continue)")
+    }
+
+    fn visit_assert(&mut self, _keyword: &Token, condition: &Expr, message: Option<&Expr>) -> String {
+        match message {
+            Some(message) => format!("(assert {} {})", condition.accept(self), message.accept(self)),
+            None => format!("(assert {})", condition.accept(self)),
+        }
+    }
+
+    fn visit_throw(&mut self, _keyword: &Token, value: &Expr) -> String {
+        format!("(throw {})", value.accept(self))
+    }
+
+    fn visit_try(&mut self, body: &Stmt, name: &Token, handler: &Stmt) -> String {
+        format!("(try {} catch {} {})", body.accept(self), name.lexeme, handler.accept(self))
+    }
+}
@@ -1,132 +1,410 @@
-#[cfg(test)]
-mod tests {
-    use std::process::Command;
-
-    #[test]
-    fn helloworld() {
-        println!("Hello, world!");
-    }
-
-    #[test]
-    fn interpret_helloworld() {
-        let output = Command::new("cargo")
-            .args(["r", "C:/Users/Misha/Documents/zawa/src/tests/cases/helloworld.zw"])
-            .output()
-            .unwrap();
-
-        let lines = std::str::from_utf8(output.stdout.as_slice())
-            .unwrap()
-            .split("\n")
-            .collect::<Vec<&str>>();
-
-        assert_eq!(lines.len(), 2);
-        assert_eq!(lines[0], "Hello, world!")
-    }
-
-    #[test]
-    fn interpret_block() {
-        let output = Command::new("cargo")
-            .args(["r", "C:/Users/Misha/Documents/zawa/src/tests/cases/block.zw"])
-            .output()
-            .unwrap();
-
-        let lines = std::str::from_utf8(output.stdout.as_slice())
-            .unwrap()
-            .split("\n")
-            .collect::<Vec<&str>>();
-
-        assert_eq!(lines.len(), 3);
-        assert_eq!(lines[0], "3");
-        assert_eq!(lines[1], "3");
-
-    }
-
-    #[test]
-    fn interpret_while() {
-        let output = Command::new("cargo")
-            .args(["r", "C:/Users/Misha/Documents/zawa/src/tests/cases/while.zw"])
-            .output()
-            .unwrap();
-
-        let lines = std::str::from_utf8(output.stdout.as_slice())
-            .unwrap()
-            .split("\n")
-            .collect::<Vec<&str>>();
-
-        assert_eq!(lines.len(), 6);
-        assert_eq!(lines[0], "4");
-        assert_eq!(lines[1], "3");
-        assert_eq!(lines[2], "2");
-        assert_eq!(lines[3], "1");
-        assert_eq!(lines[4], "0");
-    }
-
-    #[test]
-    fn interpret_for_0_8() {
-        let output = Command::new("cargo")
-            .args(["r", "C:/Users/Misha/Documents/zawa/src/tests/cases/for_0_8.zw"])
-            .output()
-            .unwrap();
-
-        let lines = std::str::from_utf8(output.stdout.as_slice())
-            .unwrap()
-            .split("\n")
-            .collect::<Vec<&str>>();
-
-        assert_eq!(lines.len(), 6);
-        assert_eq!(lines[0], "0");
-        assert_eq!(lines[1], "1");
-        assert_eq!(lines[2], "2");
-        assert_eq!(lines[3], "3");
-        assert_eq!(lines[4], "4");
-    }
-
-    #[test]
-    fn interpret_for() {
-        let output = Command::new("cargo")
-            .args(["r", "C:/Users/Misha/Documents/zawa/src/tests/cases/for.zw"])
-            .output()
-            .unwrap();
-
-        let lines = std::str::from_utf8(output.stdout.as_slice())
-            .unwrap()
-            .split("\n")
-            .collect::<Vec<&str>>();
-
-            assert_eq!(lines.len(), 13);
-            assert_eq!(lines[0], "0");
-            assert_eq!(lines[1], "1");
-            assert_eq!(lines[2], "1");
-            assert_eq!(lines[3], "2");
-            assert_eq!(lines[4], "3");
-            assert_eq!(lines[5], "5");
-            assert_eq!(lines[6], "8");
-            assert_eq!(lines[7], "13");
-            assert_eq!(lines[8], "21");
-            assert_eq!(lines[9], "34");
-            assert_eq!(lines[10], "55");
-            assert_eq!(lines[11], "89");
-    }
-
-    #[test]
-    fn interpret_break() {
-        let output = Command::new("cargo")
-            .args(["r", "C:/Users/Misha/Documents/zawa/src/tests/cases/break.zw"])
-            .output()
-            .unwrap();
-
-        let lines = std::str::from_utf8(output.stdout.as_slice())
-            .unwrap()
-            .split("\n")
-            .collect::<Vec<&str>>();
-
-        assert_eq!(lines.len(), 8);
-        assert_eq!(lines[0], "0 1 2 3 4 5 ");
-        assert_eq!(lines[1], "0 1 2 3 4 5 ");
-        assert_eq!(lines[2], "0 1 2 3 4 5 ");
-        assert_eq!(lines[3], "0 1 2 3 4 5 ");
-        assert_eq!(lines[4], "0 1 2 3 4 5 ");
-        assert_eq!(lines[5], "0 1 2 3 4 5 ");
-        assert_eq!(lines[6], "That's correct!");
-    }
-}
\ No newline at end of file
+#[cfg(test)]
+mod tests {
+    use std::process::Command;
+
+    fn run_case(name: &str) -> Vec<String> {
+        let path = format!("{}/src/tests/cases/{}", env!("CARGO_MANIFEST_DIR"), name);
+        let output = Command::new("cargo")
+            .args(["r", &path])
+            .output()
+            .unwrap();
+
+        std::str::from_utf8(output.stdout.as_slice())
+            .unwrap()
+            .split("\n")
+            .map(String::from)
+            .collect::<Vec<String>>()
+    }
+
+    #[test]
+    fn helloworld() {
+        println!("Hello, world!");
+    }
+
+    #[test]
+    fn interpret_helloworld() {
+        let output = Command::new("cargo")
+            .args(["r", concat!(env!("CARGO_MANIFEST_DIR"), "/src/tests/cases/helloworld.zw")])
+            .output()
+            .unwrap();
+
+        let lines = std::str::from_utf8(output.stdout.as_slice())
+            .unwrap()
+            .split("\n")
+            .collect::<Vec<&str>>();
+
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0], "Hello, world!")
+    }
+
+    #[test]
+    fn interpret_block() {
+        let output = Command::new("cargo")
+            .args(["r", concat!(env!("CARGO_MANIFEST_DIR"), "/src/tests/cases/block.zw")])
+            .output()
+            .unwrap();
+
+        let lines = std::str::from_utf8(output.stdout.as_slice())
+            .unwrap()
+            .split("\n")
+            .collect::<Vec<&str>>();
+
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], "3");
+        assert_eq!(lines[1], "3");
+
+    }
+
+    #[test]
+    fn interpret_while() {
+        let output = Command::new("cargo")
+            .args(["r", concat!(env!("CARGO_MANIFEST_DIR"), "/src/tests/cases/while.zw")])
+            .output()
+            .unwrap();
+
+        let lines = std::str::from_utf8(output.stdout.as_slice())
+            .unwrap()
+            .split("\n")
+            .collect::<Vec<&str>>();
+
+        assert_eq!(lines.len(), 6);
+        assert_eq!(lines[0], "4");
+        assert_eq!(lines[1], "3");
+        assert_eq!(lines[2], "2");
+        assert_eq!(lines[3], "1");
+        assert_eq!(lines[4], "0");
+    }
+
+    #[test]
+    fn interpret_for_0_8() {
+        let output = Command::new("cargo")
+            .args(["r", concat!(env!("CARGO_MANIFEST_DIR"), "/src/tests/cases/for_0_8.zw")])
+            .output()
+            .unwrap();
+
+        let lines = std::str::from_utf8(output.stdout.as_slice())
+            .unwrap()
+            .split("\n")
+            .collect::<Vec<&str>>();
+
+        assert_eq!(lines.len(), 6);
+        assert_eq!(lines[0], "0");
+        assert_eq!(lines[1], "1");
+        assert_eq!(lines[2], "2");
+        assert_eq!(lines[3], "3");
+        assert_eq!(lines[4], "4");
+    }
+
+    #[test]
+    fn interpret_for() {
+        let output = Command::new("cargo")
+            .args(["r", concat!(env!("CARGO_MANIFEST_DIR"), "/src/tests/cases/for.zw")])
+            .output()
+            .unwrap();
+
+        let lines = std::str::from_utf8(output.stdout.as_slice())
+            .unwrap()
+            .split("\n")
+            .collect::<Vec<&str>>();
+
+            assert_eq!(lines.len(), 13);
+            assert_eq!(lines[0], "0");
+            assert_eq!(lines[1], "1");
+            assert_eq!(lines[2], "1");
+            assert_eq!(lines[3], "2");
+            assert_eq!(lines[4], "3");
+            assert_eq!(lines[5], "5");
+            assert_eq!(lines[6], "8");
+            assert_eq!(lines[7], "13");
+            assert_eq!(lines[8], "21");
+            assert_eq!(lines[9], "34");
+            assert_eq!(lines[10], "55");
+            assert_eq!(lines[11], "89");
+    }
+
+    #[test]
+    fn interpret_break() {
+        let output = Command::new("cargo")
+            .args(["r", concat!(env!("CARGO_MANIFEST_DIR"), "/src/tests/cases/break.zw")])
+            .output()
+            .unwrap();
+
+        let lines = std::str::from_utf8(output.stdout.as_slice())
+            .unwrap()
+            .split("\n")
+            .collect::<Vec<&str>>();
+
+        assert_eq!(lines.len(), 8);
+        assert_eq!(lines[0], "0 1 2 3 4 5 ");
+        assert_eq!(lines[1], "0 1 2 3 4 5 ");
+        assert_eq!(lines[2], "0 1 2 3 4 5 ");
+        assert_eq!(lines[3], "0 1 2 3 4 5 ");
+        assert_eq!(lines[4], "0 1 2 3 4 5 ");
+        assert_eq!(lines[5], "0 1 2 3 4 5 ");
+        assert_eq!(lines[6], "That's correct!");
+    }
+
+    #[test]
+    fn interpret_if_else() {
+        let lines = run_case("if_else.zw");
+
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], "1");
+        assert_eq!(lines[1], "4");
+        assert_eq!(lines[2], "6");
+    }
+
+    #[test]
+    fn interpret_while_never_runs() {
+        let lines = run_case("while_never_runs.zw");
+
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0], "0");
+    }
+
+    #[test]
+    fn interpret_while_error() {
+        let lines = run_case("while_error.zw");
+
+        assert_eq!(lines.len(), 6);
+        assert_eq!(lines[0], "0");
+        assert_eq!(lines[1], "1");
+        assert!(lines[2].starts_with("Error: "));
+    }
+
+    #[test]
+    fn interpret_for_0_3() {
+        let lines = run_case("for_0_3.zw");
+
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], "0");
+        assert_eq!(lines[1], "1");
+        assert_eq!(lines[2], "2");
+    }
+
+    #[test]
+    fn interpret_short_circuit() {
+        let lines = run_case("short_circuit.zw");
+
+        assert_eq!(lines.len(), 7);
+        assert_eq!(lines[0], "false");
+        assert_eq!(lines[1], "false");
+        assert_eq!(lines[2], "false");
+        assert_eq!(lines[3], "true");
+        assert_eq!(lines[4], "stringval");
+        assert_eq!(lines[5], "stringval");
+    }
+
+    #[test]
+    fn interpret_shadow() {
+        let lines = run_case("shadow.zw");
+
+        assert_eq!(lines.len(), 7);
+        assert_eq!(lines[0], "inner");
+        assert_eq!(lines[1], "outer");
+        assert_eq!(lines[2], "changed");
+        assert!(lines[3].starts_with("Error: "));
+    }
+
+    #[test]
+    fn interpret_runtime_error_line() {
+        let lines = run_case("runtime_error_line.zw");
+
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with("Error: [line 4] Operands must be numbers"));
+    }
+
+    #[test]
+    fn interpret_division_by_zero() {
+        let lines = run_case("division_by_zero.zw");
+
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[0], "0.5");
+        assert_eq!(lines[1], "Error: [line 2] Division by zero");
+        assert_eq!(lines[2], "2 | echo 1 / 0;");
+        assert_eq!(lines[3], "  |        ^");
+    }
+
+    #[test]
+    fn interpret_lex_error() {
+        let lines = run_case("lex_error.zw");
+
+        assert_eq!(lines, vec!["Error: [line 3] Unterminated string", "3 | echo \"not closed;", "  |      ^", ""]);
+    }
+
+    #[test]
+    fn interpret_modulo() {
+        let lines = run_case("modulo.zw");
+
+        assert_eq!(lines.len(), 6);
+        assert_eq!(lines[0], "1");
+        assert_eq!(lines[1], "0.5");
+        assert_eq!(lines[2], "Error: [line 3] Division by zero");
+    }
+
+    #[test]
+    fn interpret_power() {
+        let lines = run_case("power.zw");
+
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[0], "512");
+        assert_eq!(lines[1], "64");
+        assert_eq!(lines[2], "18");
+        assert_eq!(lines[3], "0.25");
+    }
+
+    #[test]
+    fn interpret_int_and_float() {
+        let lines = run_case("int_and_float.zw");
+
+        assert_eq!(lines.len(), 7);
+        assert_eq!(lines[0], "3.5");
+        assert_eq!(lines[1], "6");
+        assert_eq!(lines[2], "3.5");
+        assert_eq!(lines[3], "true");
+        assert_eq!(lines[4], "true");
+        assert_eq!(lines[5], "-0.5");
+    }
+
+    #[test]
+    fn interpret_radix_literals() {
+        let lines = run_case("radix_literals.zw");
+
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], "255");
+        assert_eq!(lines[1], "10");
+        assert_eq!(lines[2], "15");
+    }
+
+    #[test]
+    fn interpret_escape_sequences() {
+        let lines = run_case("escape_sequences.zw");
+
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], "first");
+        assert_eq!(lines[1], "second");
+        assert_eq!(lines[2], "say \"hi\"\t\\");
+    }
+
+    #[test]
+    fn interpret_interpolation() {
+        let lines = run_case("interpolation.zw");
+
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], "hello Zawa, you are 6");
+        assert_eq!(lines[1], "nested: inner 3");
+        assert_eq!(lines[2], "escaped: ${name}");
+    }
+
+    #[test]
+    fn interpret_ternary() {
+        let lines = run_case("ternary.zw");
+
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[0], "yes");
+        assert_eq!(lines[1], "two");
+        assert_eq!(lines[2], "0");
+        assert_eq!(lines[3], "1");
+    }
+
+    #[test]
+    fn interpret_function() {
+        let lines = run_case("function.zw");
+
+        assert_eq!(lines.len(), 7);
+        assert_eq!(lines[0], "3");
+        assert_eq!(lines[1], "hello world");
+        assert_eq!(lines[2], "add/2");
+        assert_eq!(lines[3], "Error: [line 10] Expected 2 arguments but got 1");
+    }
+
+    #[test]
+    fn interpret_return() {
+        let lines = run_case("return.zw");
+
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], "3");
+        assert_eq!(lines[1], "null");
+        assert_eq!(lines[2], "55");
+    }
+
+    #[test]
+    fn interpret_closure_counter() {
+        let lines = run_case("closure_counter.zw");
+
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[0], "1");
+        assert_eq!(lines[1], "2");
+        assert_eq!(lines[2], "1");
+        assert_eq!(lines[3], "3");
+    }
+
+    #[test]
+    fn interpret_clock() {
+        let lines = run_case("clock.zw");
+
+        assert_eq!(lines.len(), 6);
+        assert_eq!(lines[0], "true");
+        assert_eq!(lines[1], "clock/0");
+        assert_eq!(lines[2], "Error: [line 3] Expected 0 arguments but got 1");
+    }
+
+    #[test]
+    fn interpret_class() {
+        let lines = run_case("class.zw");
+
+        assert_eq!(lines.len(), 9);
+        assert_eq!(lines[0], "Point");
+        assert_eq!(lines[1], "Point instance");
+        assert_eq!(lines[2], "3");
+        assert_eq!(lines[3], "origin");
+        assert_eq!(lines[4], "4");
+        assert_eq!(lines[5], "Error: [line 19] Undefined property 'z'");
+    }
+
+    #[test]
+    fn interpret_methods() {
+        let lines = run_case("methods.zw");
+
+        assert_eq!(lines.len(), 8);
+        assert_eq!(lines[0], "Hi, I am Zawa");
+        assert_eq!(lines[1], "Hi, I am Zawa");
+        assert_eq!(lines[2], "2");
+        assert_eq!(lines[3], "true");
+        assert_eq!(lines[4], "Error: [line 27] Expected 1 arguments but got 0");
+    }
+
+    #[test]
+    fn interpret_inheritance() {
+        let lines = run_case("inheritance.zw");
+
+        assert_eq!(lines.len(), 7);
+        assert_eq!(lines[0], "Rex makes a sound");
+        assert_eq!(lines[1], "Rex makes a sound and barks");
+        assert_eq!(lines[2], "Rex");
+        assert_eq!(lines[3], "Error: [line 22] Superclass must be a class");
+    }
+
+    #[test]
+    fn interpret_index() {
+        let lines = run_case("index.zw");
+
+        assert_eq!(lines.len(), 8);
+        assert_eq!(lines[0], "1");
+        assert_eq!(lines[1], "[9, 2, 3]");
+        assert_eq!(lines[2], "[9, 2, 3]");
+        assert_eq!(lines[3], "e");
+        assert_eq!(lines[4], "Error: [line 10] Index 3 out of bounds for length 3");
+    }
+
+    #[test]
+    fn interpret_continue() {
+        let lines = run_case("continue.zw");
+
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[0], "1");
+        assert_eq!(lines[1], "3");
+        assert_eq!(lines[2], "1");
+        assert_eq!(lines[3], "3");
+    }
+}
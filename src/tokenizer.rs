@@ -1,356 +1,1219 @@
-use std::{collections::HashMap, string::String};
-
-fn is_digit(c: char) -> bool {
-    let u8c = c as u8;
-    ('0' as u8) <= u8c && u8c <= ('9' as u8)
-}
-
-fn is_alpha(c: char) -> bool {
-    let u8c = c as u8;
-    ( ('a' as u8) <= u8c && u8c <= ('z' as u8) ) || ( ('A' as u8) <= u8c && u8c <= ('Z' as u8) ) || ( c == '_')
-}
-
-fn is_alpha_numeric(c: char) -> bool {
-    is_alpha(c) || is_digit(c)
-}
-
-fn get_keyword_hashmap() -> HashMap<&'static str, TokenType> {
-    HashMap::from([
-        ("and", TokenType::And),
-        ("or", TokenType::Or),
-        ("true", TokenType::True),
-        ("false", TokenType::False),
-        ("if", TokenType::If),
-        ("else", TokenType::Else),
-        ("class", TokenType::Class),
-        ("self", TokenType::Self_),
-        ("fn", TokenType::Fn),
-        ("return", TokenType::Return),
-        ("for", TokenType::For),
-        ("while", TokenType::While),
-        ("null", TokenType::Null),
-        ("echo", TokenType::Echo),
-        ("super", TokenType::Super),
-        ("let", TokenType::Let),
-        ("break", TokenType::Break)
-    ])
-}
-
-pub struct Tokenizer {
-    source: String,
-    tokens: Vec<Token>,
-    start: usize,
-    current: usize,
-    line: usize,
-
-    keywords: HashMap<&'static str, TokenType>,
-}
-
-impl Tokenizer {
-    pub fn new(source: &str) -> Self {
-        Self {
-            source: source.to_string(),
-            tokens: Vec::new(),
-            start: 0,
-            current: 0,
-            line: 0,
-            keywords: get_keyword_hashmap(),
-        }
-    }
-
-    pub fn tokenize(&mut self) -> Result<Vec<Token>, String> {
-        let mut errors = Vec::new();
-
-        while !self.is_at_end() {
-            self.start = self.current;
-            match self.scan_token() {
-                Ok(_) => (),
-                Err(msg) => errors.push(msg),
-            }
-        }
-
-        self.tokens.push(Token {
-            token_type: TokenType::Eof,
-            lexeme: "".to_string(),
-            literal: None,
-            line_number: self.line
-        });
-        
-        if errors.len() > 0 {
-            let mut joined = String::new();
-            for error in errors {
-                joined.push_str(&error);
-                joined.push_str("\n");
-            }
-            
-            return Err(joined);
-        }
-
-        Ok(self.tokens.clone())
-    }
-
-    fn is_at_end(&self) -> bool {
-        self.current >= self.source.len()
-    }
-
-    fn scan_token(&mut self) -> Result<(), String> {
-        let c = self.advance();
-
-        match c {
-            '(' => self.add_token(TokenType::LeftParen),
-            ')' => self.add_token(TokenType::RightParen),
-            '{' => self.add_token(TokenType::LeftBrace),
-            '}' => self.add_token(TokenType::RightBrace),
-            ',' => self.add_token(TokenType::Comma),
-            '.' => self.add_token(TokenType::Dot),
-            '-' => self.add_token(TokenType::Minus),
-            '+' => self.add_token(TokenType::Plus),
-            ';' => self.add_token(TokenType::Semicolon),
-            '*' => self.add_token(TokenType::Star),
-            '!' => {
-                let token = if self.char_match('=') {
-                    TokenType::BangEqual
-                } else {
-                    TokenType::Bang
-                };
-
-                self.add_token(token);
-            },
-            '=' => {
-                let token = if self.char_match('=') {
-                    TokenType::EqualEqual
-                } else {
-                    TokenType::Equal
-                };
-
-                self.add_token(token);
-            },
-            '<' => {
-                let token = if self.char_match('=') {
-                    TokenType::LessEqual
-                } else {
-                    TokenType::Less
-                };
-
-                self.add_token(token);
-            },
-            '>' => {
-                let token = if self.char_match('=') {
-                    TokenType::GreaterEqual
-                } else {
-                    TokenType::Greater
-                };
-
-                self.add_token(token);
-            },
-            '/' => {
-                if self.char_match('/') {
-                    loop {
-                        if self.peek() == '\n' || self.is_at_end() {
-                            break;
-                        }
-                        self.advance();
-                    }
-                } else {
-                    self.add_token(TokenType::Slash);
-                }
-            },
-
-            ' ' | '\t' | '\r' => {},
-            '\n' => self.line += 1,
-            '"' => self.string()?,
-
-            c => {
-                if is_digit(c) {
-                    self.number()?;
-                } else if is_alpha(c) {
-                    self.identifier();
-                } else {
-                    return Err(format!("line: {}, Unrecognized char: {}", self.line, c))
-                }
-            }
-        }
-
-        Ok(())
-    }
-
-    fn identifier(&mut self) {
-        while is_alpha_numeric(self.peek()) {
-            self.advance();
-        }
-        let substring = &self.source[self.start..self.current];
-        if let Some(&token_type) = self.keywords.get(substring) {
-            self.add_token(token_type);
-        } else {
-            self.add_token(TokenType::Identifier);
-        }
-    }
-
-    fn number(&mut self) -> Result<(), String> {
-        while is_digit(self.peek()) {
-            self.advance();
-        }
-
-        if self.peek() == '.' && is_digit(self.peek_next()) {
-            self.advance();
-            
-            while is_digit(self.peek()) {
-                self.advance();
-            }
-        }
-
-        let substring = &self.source[self.start..self.current];
-        let value = substring.parse::<f64>();
-        match value {
-            Ok(value) => self.add_token_lit(TokenType::Number, Some(LiteralValue::FValue(value))),
-            Err(_) => return Err(format!("Could not parse number: {}", substring)),
-        }
-        
-        Ok(())
-    }
-
-    fn peek_next(&self) -> char {
-        if self.current + 1 >= self.source.len() {
-            return '\0';
-        }
-
-        self.source.chars().nth(self.current + 1).unwrap()
-    }
-
-    fn string(&mut self) -> Result<(), String> {
-        while self.peek() != '"' && !self.is_at_end() {
-            if self.peek() == '\n' {
-                self.line += 1;
-            }
-            self.advance();
-        }
-
-        if self.is_at_end() {
-            return Err("Unterminated string".to_string());
-        }
-
-        self.advance();
-        let value = &self.source[self.start + 1..self.current - 1];
-
-        self.add_token_lit(TokenType::StringLit, Some(LiteralValue::StringValue(value.to_string())));
-
-        Ok(())
-    }
-
-    fn peek(&self) -> char {
-        if self.is_at_end() {
-            return '\0';
-        }
-        self.source.chars().nth(self.current).unwrap()
-    }
-
-    fn char_match(&mut self, c: char) -> bool {
-        if self.is_at_end() {
-            return false;
-        }
-        if self.source.chars().nth(self.current).unwrap() != c {
-            return false;
-        } else {
-            self.current += 1;
-            return true;
-        }
-    }
-
-    fn advance(&mut self) -> char {
-        let c = self.source.chars().nth(self.current).unwrap();
-        self.current += 1;
-
-        c
-    }
-
-    fn add_token(&mut self, token_type: TokenType) {
-        self.add_token_lit(token_type, None);
-    }
-
-    fn add_token_lit(&mut self, token_type: TokenType, literal: Option<LiteralValue>) {
-        let text = String::from(&self.source[self.start..self.current]);
-
-        self.tokens.push(Token {
-            token_type: token_type,
-            lexeme: text,
-            literal: literal,
-            line_number: self.line,
-        })
-    }
-}
-
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub enum TokenType {
-    LeftParen,
-    RightParen,
-    LeftBrace,
-    RightBrace,
-    Comma,
-    Dot,
-    Minus,
-    Plus,
-    Semicolon,
-    Slash,
-    Star,
-
-    Bang,
-    BangEqual,
-    Equal,
-    EqualEqual,
-    Greater,
-    GreaterEqual,
-    Less,
-    LessEqual,
-
-    Identifier,
-    StringLit,
-    Number,
-
-    And,
-    Or,
-    True,
-    False,
-    If,
-    Else,
-    Class,
-    Self_,
-    Fn,
-    Return,
-    For,
-    While,
-    Null,
-    Echo,
-    Super,
-    Let,
-    Break,
-
-    Eof
-}
-
-impl std::fmt::Display for TokenType {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "{:?}", self)
-    }
-}
-
-#[derive(Debug, Clone)]
-pub enum LiteralValue {
-    FValue(f64),
-    StringValue(String)
-}
-
-#[allow(dead_code)]
-#[derive(Debug, Clone)]
-pub struct Token {
-    pub token_type: TokenType,
-    pub lexeme: String,
-    pub literal: Option<LiteralValue>,
-    pub line_number: usize,
-}
-
-impl Token {
-    #[allow(dead_code)]
-    pub fn to_string(&self) -> String {
-        format!("{} {} {:?}", self.token_type, self.lexeme, self.literal)
-    }
-}
\ No newline at end of file
+use std::{collections::HashMap, string::String};
+use crate::error::CorrodeError;
+use crate::interner::Symbol;
+
+fn is_digit(c: char) -> bool {
+    c.is_ascii_digit()
+}
+
+// Identifiers may use any Unicode letter, keywords stay ASCII
+fn is_alpha(c: char) -> bool {
+    c.is_alphabetic() || c == '_'
+}
+
+fn is_alpha_numeric(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn get_keyword_hashmap() -> HashMap<&'static str, TokenType> {
+    HashMap::from([
+        ("and", TokenType::And),
+        ("or", TokenType::Or),
+        ("true", TokenType::True),
+        ("false", TokenType::False),
+        ("if", TokenType::If),
+        ("else", TokenType::Else),
+        ("class", TokenType::Class),
+        ("self", TokenType::Self_),
+        ("fn", TokenType::Fn),
+        ("return", TokenType::Return),
+        ("for", TokenType::For),
+        ("in", TokenType::In),
+        ("while", TokenType::While),
+        ("do", TokenType::Do),
+        ("match", TokenType::Match),
+        ("null", TokenType::Null),
+        ("echo", TokenType::Echo),
+        ("print", TokenType::Print),
+        ("println", TokenType::Println),
+        ("super", TokenType::Super),
+        ("let", TokenType::Let),
+        ("const", TokenType::Const),
+        ("break", TokenType::Break),
+        ("continue", TokenType::Continue),
+        ("assert", TokenType::Assert),
+        ("throw", TokenType::Throw),
+        ("try", TokenType::Try),
+        ("catch", TokenType::Catch)
+    ])
+}
+
+// Interpolated code is tokenized recursively, so strings nested deeper than this are an error instead of a
+// stack overflow. The parser's default nesting limit is the same
+const MAX_INTERPOLATION_DEPTH: usize = 16;
+
+pub struct Tokenizer {
+    source: String,
+    // Tokens scanned but not yet handed out by the iterator
+    pending: Vec<Token>,
+    // Set once Eof has been produced
+    finished: bool,
+    start: usize,
+    current: usize,
+    line: usize,
+    // Offset where the current line starts, used to compute token columns
+    line_start: usize,
+    // `line` and `line_start` when the current token began, a string can end on a later line
+    start_line: usize,
+    start_line_start: usize,
+    // Offset of this tokenizer's source inside the whole program, non-zero for interpolated code
+    base: usize,
+    // How many interpolated strings the source is nested in, each one tokenizes its code recursively
+    depth: usize,
+
+    keywords: HashMap<&'static str, TokenType>,
+}
+
+impl Tokenizer {
+    pub fn new(source: &str) -> Self {
+        Self {
+            source: source.to_string(),
+            pending: Vec::new(),
+            finished: false,
+            start: 0,
+            current: 0,
+            line: 1,
+            line_start: 0,
+            start_line: 1,
+            start_line_start: 0,
+            base: 0,
+            depth: 0,
+            keywords: get_keyword_hashmap(),
+        }
+    }
+
+    // Collects the whole token stream, reporting every lex error at once
+    pub fn tokenize(&mut self) -> Result<Vec<Token>, Vec<CorrodeError>> {
+        let mut tokens = Vec::new();
+        let mut errors = Vec::new();
+
+        for result in self.by_ref() {
+            match result {
+                Ok(token) => tokens.push(token),
+                Err(msg) => errors.push(msg),
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        Ok(tokens)
+    }
+
+    fn is_at_end(&self) -> bool {
+        self.current >= self.source.len()
+    }
+
+    fn scan_token(&mut self) -> Result<(), CorrodeError> {
+        let c = self.advance();
+
+        match c {
+            '(' => self.add_token(TokenType::LeftParen),
+            ')' => self.add_token(TokenType::RightParen),
+            '{' => self.add_token(TokenType::LeftBrace),
+            '}' => self.add_token(TokenType::RightBrace),
+            '[' => self.add_token(TokenType::LeftBracket),
+            ']' => self.add_token(TokenType::RightBracket),
+            ',' => self.add_token(TokenType::Comma),
+            '.' => {
+                if self.peek() == '.' && self.peek_next() == '.' {
+                    self.current += 2;
+                    self.add_token(TokenType::DotDotDot);
+                } else {
+                    self.add_token(TokenType::Dot);
+                }
+            },
+            '-' => {
+                let token = if self.char_match('=') {
+                    TokenType::MinusEqual
+                } else {
+                    TokenType::Minus
+                };
+
+                self.add_token(token);
+            },
+            '+' => {
+                let token = if self.char_match('=') {
+                    TokenType::PlusEqual
+                } else {
+                    TokenType::Plus
+                };
+
+                self.add_token(token);
+            },
+            ';' => self.add_token(TokenType::Semicolon),
+            '?' => {
+                let token = if self.char_match('?') {
+                    TokenType::QuestionQuestion
+                } else {
+                    TokenType::Question
+                };
+
+                self.add_token(token);
+            },
+            ':' => self.add_token(TokenType::Colon),
+            '*' => {
+                let token = if self.char_match('*') {
+                    TokenType::StarStar
+                } else if self.char_match('=') {
+                    TokenType::StarEqual
+                } else {
+                    TokenType::Star
+                };
+
+                self.add_token(token);
+            },
+            '%' => self.add_token(TokenType::Percent),
+            '!' => {
+                let token = if self.char_match('=') {
+                    TokenType::BangEqual
+                } else {
+                    TokenType::Bang
+                };
+
+                self.add_token(token);
+            },
+            '=' => {
+                let token = if self.char_match('=') {
+                    TokenType::EqualEqual
+                } else if self.char_match('>') {
+                    TokenType::Arrow
+                } else {
+                    TokenType::Equal
+                };
+
+                self.add_token(token);
+            },
+            '<' => {
+                let token = if self.char_match('=') {
+                    TokenType::LessEqual
+                } else if self.char_match('<') {
+                    TokenType::LessLess
+                } else {
+                    TokenType::Less
+                };
+
+                self.add_token(token);
+            },
+            '>' => {
+                let token = if self.char_match('=') {
+                    TokenType::GreaterEqual
+                } else if self.char_match('>') {
+                    TokenType::GreaterGreater
+                } else {
+                    TokenType::Greater
+                };
+
+                self.add_token(token);
+            },
+            '&' => {
+                if self.char_match('&') {
+                    self.add_token(TokenType::And);
+                } else {
+                    self.add_token(TokenType::Ampersand);
+                }
+            },
+            '|' => {
+                if self.char_match('|') {
+                    self.add_token(TokenType::Or);
+                } else {
+                    self.add_token(TokenType::Pipe);
+                }
+            },
+            '^' => self.add_token(TokenType::Caret),
+            '~' => {
+                // `//` already starts a comment, so floor division is spelled `~/`. A `/` that starts
+                // a comment after the `~` is left for the comment
+                if self.peek() == '/' && !matches!(self.peek_next(), '/' | '*') {
+                    self.advance();
+                    self.add_token(TokenType::TildeSlash);
+                } else {
+                    self.add_token(TokenType::Tilde);
+                }
+            },
+            '/' => {
+                if self.char_match('/') {
+                    loop {
+                        if self.peek() == '\n' || self.is_at_end() {
+                            break;
+                        }
+                        self.advance();
+                    }
+                } else if self.char_match('*') {
+                    self.block_comment()?;
+                } else if self.char_match('=') {
+                    self.add_token(TokenType::SlashEqual);
+                } else {
+                    self.add_token(TokenType::Slash);
+                }
+            },
+
+            ' ' | '\t' | '\r' => {},
+            '\n' => self.newline(self.current),
+            '"' if self.peek() == '"' && self.peek_next() == '"' => {
+                self.current += 2;
+                self.string(true)?;
+            },
+            '"' => self.string(false)?,
+
+            'r' if self.peek() == '"' => {
+                self.advance();
+                self.raw_string()?;
+            },
+
+            c => {
+                if is_digit(c) {
+                    self.number()?;
+                } else if is_alpha(c) {
+                    self.identifier();
+                } else {
+                    return Err(self.error(format!("Unrecognized char: {}", c)))
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // Block comments nest, so "/* a /* b */ c */" is a single comment
+    fn block_comment(&mut self) -> Result<(), CorrodeError> {
+        let mut depth = 1;
+
+        while depth > 0 {
+            if self.is_at_end() {
+                return Err(self.error("Unterminated block comment".to_string()));
+            }
+
+            let c = self.advance();
+            if c == '\n' {
+                self.newline(self.current);
+            } else if c == '/' && self.char_match('*') {
+                depth += 1;
+            } else if c == '*' && self.char_match('/') {
+                depth -= 1;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn identifier(&mut self) {
+        while is_alpha_numeric(self.peek()) {
+            self.advance();
+        }
+        let substring = &self.source[self.start..self.current];
+        let token_type = match self.keywords.get(substring) {
+            Some(&token_type) => token_type,
+            None => TokenType::Identifier,
+        };
+        let symbol = Symbol::intern(substring);
+
+        self.add_token(token_type);
+        if let Some(token) = self.pending.last_mut() {
+            token.symbol = Some(symbol);
+        }
+    }
+
+    fn number(&mut self) -> Result<(), CorrodeError> {
+        if &self.source[self.start..self.current] == "0" {
+            match self.peek() {
+                'x' | 'X' => return self.radix_number(16, "hex"),
+                'b' | 'B' => return self.radix_number(2, "binary"),
+                'o' | 'O' => return self.radix_number(8, "octal"),
+                _ => (),
+            }
+        }
+
+        while is_digit(self.peek()) || self.peek() == '_' {
+            self.advance();
+        }
+
+        let mut is_float = false;
+        if self.peek() == '.' {
+            if !is_digit(self.peek_next()) {
+                let substring = &self.source[self.start..self.current];
+                return Err(self.error(format!("Expected digits after '.' in number: {}.", substring)));
+            }
+
+            is_float = true;
+            self.advance();
+            
+            while is_digit(self.peek()) || self.peek() == '_' {
+                self.advance();
+            }
+        }
+
+        if self.peek() == 'e' || self.peek() == 'E' {
+            is_float = true;
+            self.advance();
+
+            if self.peek() == '+' || self.peek() == '-' {
+                self.advance();
+            }
+
+            if !is_digit(self.peek()) {
+                let substring = &self.source[self.start..self.current];
+                return Err(self.error(format!("Expected exponent digits in number: {}", substring)));
+            }
+
+            while is_digit(self.peek()) || self.peek() == '_' {
+                self.advance();
+            }
+        }
+
+        let substring = &self.source[self.start..self.current];
+        let digits = self.strip_separators(substring, 10)?;
+        if is_float {
+            match digits.parse::<f64>() {
+                Ok(value) => self.add_token_lit(TokenType::Number, Some(LiteralValue::FloatValue(value))),
+                Err(_) => return Err(self.error(format!("Could not parse number: {}", substring))),
+            }
+        } else {
+            match digits.parse::<i64>() {
+                Ok(value) => self.add_token_lit(TokenType::Number, Some(LiteralValue::IntValue(value))),
+                Err(_) => return Err(self.error(format!("Could not parse number: {}", substring))),
+            }
+        }
+        
+        Ok(())
+    }
+
+    fn radix_number(&mut self, radix: u32, name: &str) -> Result<(), CorrodeError> {
+        self.advance();
+
+        while is_alpha_numeric(self.peek()) {
+            self.advance();
+        }
+
+        let substring = &self.source[self.start..self.current];
+        let digits = self.strip_separators(&substring[2..], radix)?;
+        if digits.is_empty() || !digits.chars().all(|c| c.is_digit(radix)) {
+            return Err(self.error(format!("Invalid {} literal: {}", name, substring)));
+        }
+
+        match i64::from_str_radix(&digits, radix) {
+            Ok(value) => self.add_token_lit(TokenType::Number, Some(LiteralValue::IntValue(value))),
+            Err(_) => return Err(self.error(format!("Could not parse number: {}", substring))),
+        }
+
+        Ok(())
+    }
+
+    // An underscore is only allowed between two digits, so "1_000" is valid but "1__0" or "5_" are not
+    fn strip_separators(&self, text: &str, radix: u32) -> Result<String, CorrodeError> {
+        let chars = text.chars().collect::<Vec<char>>();
+
+        for (i, c) in chars.iter().enumerate() {
+            if *c != '_' {
+                continue;
+            }
+
+            let prev_is_digit = i > 0 && chars[i - 1].is_digit(radix);
+            let next_is_digit = i + 1 < chars.len() && chars[i + 1].is_digit(radix);
+            if !prev_is_digit || !next_is_digit {
+                return Err(self.error(format!("Invalid digit separator in number: {}", &self.source[self.start..self.current])));
+            }
+        }
+
+        Ok(chars.into_iter().filter(|c| *c != '_').collect())
+    }
+
+    fn at_string_end(&self, triple: bool) -> bool {
+        if triple {
+            self.source[self.current..].starts_with("\"\"\"")
+        } else {
+            self.peek() == '"'
+        }
+    }
+
+    fn peek_next(&self) -> char {
+        self.source[self.current..].chars().nth(1).unwrap_or('\0')
+    }
+
+    // r"..." keeps backslashes as they are written, a \" is kept too and does not end the string
+    fn raw_string(&mut self) -> Result<(), CorrodeError> {
+        let mut value = String::new();
+
+        while self.peek() != '"' && !self.is_at_end() {
+            let c = self.advance();
+            if c == '\n' {
+                self.newline(self.current);
+            }
+            value.push(c);
+
+            if c == '\\' && self.peek() == '"' {
+                value.push(self.advance());
+            }
+        }
+
+        if self.is_at_end() {
+            return Err(self.error("Unterminated string".to_string()));
+        }
+
+        self.advance();
+        self.add_token_lit(TokenType::StringLit, Some(LiteralValue::StringValue(value)));
+
+        Ok(())
+    }
+
+    // A triple quoted string ends at the next three quotes, so it can hold single quotes without escaping them
+    fn string(&mut self, triple: bool) -> Result<(), CorrodeError> {
+        let mut value = String::new();
+        let mut parts = Vec::new();
+        let mut error = None;
+
+        while !self.at_string_end(triple) && !self.is_at_end() {
+            let c = self.advance();
+            match c {
+                '\n' => {
+                    self.newline(self.current);
+                    value.push(c);
+                },
+                '\\' => {
+                    if self.is_at_end() {
+                        break;
+                    }
+
+                    // Where the backslash is, before an escaped newline moves to the next line
+                    let (line, column) = (self.line, self.base + self.current - self.line_start);
+                    match self.advance() {
+                        'n' => value.push('\n'),
+                        't' => value.push('\t'),
+                        'r' => value.push('\r'),
+                        '\\' => value.push('\\'),
+                        '"' => value.push('"'),
+                        '0' => value.push('\0'),
+                        '$' => value.push('$'),
+                        other => {
+                            if other == '\n' {
+                                self.newline(self.current);
+                            }
+                            if error.is_none() {
+                                error = Some(CorrodeError::lex(line, format!("Unknown escape sequence: \\{}", other)).with_column(column));
+                            }
+                        },
+                    }
+                },
+                '$' if self.peek() == '{' => {
+                    self.advance();
+                    parts.push(InterpolationPart::Text(std::mem::take(&mut value)));
+
+                    let code_line = self.line;
+                    let code_line_start = self.line_start;
+                    let code_start = self.current;
+                    if !self.skip_interpolated_code() {
+                        break;
+                    }
+
+                    let code = self.source[code_start..self.current].to_string();
+                    self.advance();
+
+                    let tokens = if self.depth >= MAX_INTERPOLATION_DEPTH {
+                        Err(CorrodeError::lex(code_line, String::from("Too much nesting")).with_column(self.base + code_start - code_line_start + 1))
+                    } else {
+                        Tokenizer::tokenize_interpolated_code(&code, code_line, code_line_start, self.base + code_start, self.depth + 1)
+                    };
+                    match tokens {
+                        Ok(tokens) => parts.push(InterpolationPart::Code(tokens)),
+                        Err(err) => if error.is_none() {
+                            error = Some(err);
+                        },
+                    }
+                },
+                _ => value.push(c),
+            }
+        }
+
+        if self.is_at_end() && triple {
+            return Err(self.error("Unterminated triple quoted string".to_string()));
+        }
+        if self.is_at_end() {
+            return Err(self.error("Unterminated string".to_string()));
+        }
+
+        self.current += if triple { 3 } else { 1 };
+
+        if let Some(error) = error {
+            return Err(error);
+        }
+
+        if parts.is_empty() {
+            self.add_token_lit(TokenType::StringLit, Some(LiteralValue::StringValue(value)));
+        } else {
+            parts.push(InterpolationPart::Text(value));
+            self.add_token_lit(TokenType::Interpolation, Some(LiteralValue::Interpolation(parts)));
+        }
+
+        Ok(())
+    }
+
+    // Moves the cursor to the '}' closing an interpolated expression, skipping nested braces and strings.
+    // Returns false if the source ends first
+    fn skip_interpolated_code(&mut self) -> bool {
+        let mut depth = 1;
+
+        while !self.is_at_end() {
+            match self.peek() {
+                '{' => depth += 1,
+                '}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return true;
+                    }
+                },
+                '"' => {
+                    self.advance();
+                    while self.peek() != '"' && !self.is_at_end() {
+                        if self.peek() == '\\' {
+                            self.advance();
+                        }
+                        if self.peek() == '\n' {
+                            self.newline(self.current + 1);
+                        }
+                        self.advance();
+                    }
+                },
+                '\n' => self.newline(self.current + 1),
+                _ => (),
+            }
+            self.advance();
+        }
+
+        false
+    }
+
+    fn tokenize_interpolated_code(code: &str, line: usize, line_start: usize, base: usize, depth: usize) -> Result<Vec<Token>, CorrodeError> {
+        let mut tokenizer = Tokenizer::new(code);
+        tokenizer.line = line;
+        tokenizer.line_start = line_start;
+        tokenizer.base = base;
+        tokenizer.depth = depth;
+
+        tokenizer.collect()
+    }
+
+    // Moves to the next line, `line_start` is the local offset of its first character
+    fn newline(&mut self, line_start: usize) {
+        self.line += 1;
+        self.line_start = self.base + line_start;
+    }
+
+    fn peek(&self) -> char {
+        self.source[self.current..].chars().next().unwrap_or('\0')
+    }
+
+    fn char_match(&mut self, c: char) -> bool {
+        if self.is_at_end() || self.peek() != c {
+            return false;
+        }
+        self.current += c.len_utf8();
+
+        true
+    }
+
+    // `current` is a byte offset, so it moves by the length of the character
+    fn advance(&mut self) -> char {
+        let c = self.peek();
+        self.current += c.len_utf8();
+
+        c
+    }
+
+    // An error pointing at the first character of the current token
+    fn error(&self, message: String) -> CorrodeError {
+        CorrodeError::lex(self.start_line, message).with_column(self.base + self.start - self.start_line_start + 1)
+    }
+
+    fn add_token(&mut self, token_type: TokenType) {
+        self.add_token_lit(token_type, None);
+    }
+
+    fn add_token_lit(&mut self, token_type: TokenType, literal: Option<LiteralValue>) {
+        let text = String::from(&self.source[self.start..self.current]);
+        let offset = self.base + self.start;
+
+        self.pending.push(Token {
+            token_type: token_type,
+            lexeme: text,
+            literal: literal,
+            symbol: None,
+            line_number: self.start_line,
+            column: offset - self.start_line_start + 1,
+            offset: offset,
+        })
+    }
+}
+
+// Scans lazily, one token at a time, ending with Eof
+impl Iterator for Tokenizer {
+    type Item = Result<Token, CorrodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if !self.pending.is_empty() {
+                return Some(Ok(self.pending.remove(0)));
+            }
+
+            if self.finished {
+                return None;
+            }
+
+            self.start = self.current;
+            self.start_line = self.line;
+            self.start_line_start = self.line_start;
+            if self.is_at_end() {
+                self.add_token(TokenType::Eof);
+                self.finished = true;
+            } else if let Err(error) = self.scan_token() {
+                return Some(Err(error));
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TokenType {
+    LeftParen,
+    RightParen,
+    LeftBrace,
+    RightBrace,
+    LeftBracket,
+    RightBracket,
+    Comma,
+    Dot,
+    DotDotDot,
+    Minus,
+    Plus,
+    Semicolon,
+    Question,
+    QuestionQuestion,
+    Colon,
+    Slash,
+    Star,
+    StarStar,
+    Percent,
+    TildeSlash,
+
+    PlusEqual,
+    MinusEqual,
+    StarEqual,
+    SlashEqual,
+
+    Bang,
+    BangEqual,
+    Equal,
+    EqualEqual,
+    Greater,
+    GreaterEqual,
+    Less,
+    LessEqual,
+    LessLess,
+    GreaterGreater,
+
+    Arrow,
+
+    Ampersand,
+    Pipe,
+    Caret,
+    Tilde,
+
+    Identifier,
+    StringLit,
+    Interpolation,
+    Number,
+
+    And,
+    Or,
+    True,
+    False,
+    If,
+    Else,
+    Class,
+    Self_,
+    Fn,
+    Return,
+    For,
+    In,
+    While,
+    Do,
+    Match,
+    Null,
+    Echo,
+    Print,
+    Println,
+    Super,
+    Let,
+    Const,
+    Break,
+    Continue,
+    Assert,
+    Throw,
+    Try,
+    Catch,
+
+    Eof
+}
+
+impl std::fmt::Display for TokenType {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[allow(clippy::enum_variant_names)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LiteralValue {
+    IntValue(i64),
+    FloatValue(f64),
+    StringValue(String),
+    Interpolation(Vec<InterpolationPart>)
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum InterpolationPart {
+    Text(String),
+    Code(Vec<Token>)
+}
+
+// The bytes `start..end` of the source something was parsed from, `line` is the line where it starts
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+}
+
+impl Span {
+    // From the start of `self` to the end of `last`
+    pub fn to(self, last: Span) -> Span {
+        Span { start: self.start, end: last.end.max(self.end), line: self.line }
+    }
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Token {
+    pub token_type: TokenType,
+    pub lexeme: String,
+    pub literal: Option<LiteralValue>,
+    // Interned name, set for identifiers and keywords. Not serialized, since it is derived from the lexeme
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub symbol: Option<Symbol>,
+    pub line_number: usize,
+    // 1-based column and byte offset of the first character of the lexeme
+    pub column: usize,
+    pub offset: usize,
+}
+
+impl Token {
+    // For tokens that do not come from source, the column and offset are left at 0
+    pub fn new(token_type: TokenType, lexeme: &str, line_number: usize) -> Self {
+        Self {
+            token_type: token_type,
+            lexeme: lexeme.to_string(),
+            literal: None,
+            symbol: None,
+            line_number: line_number,
+            column: 0,
+            offset: 0,
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        Span { start: self.offset, end: self.offset + self.lexeme.len(), line: self.line_number }
+    }
+
+    // Falls back to interning the lexeme for tokens the tokenizer did not make
+    pub fn symbol(&self) -> Symbol {
+        self.symbol.unwrap_or_else(|| Symbol::intern(&self.lexeme))
+    }
+
+    pub fn to_string(&self) -> String {
+        format!("{} {} {:?}", self.token_type, self.lexeme, self.literal)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokenize_logical_symbols() {
+        let mut tokenizer = Tokenizer::new("a && b || c and d or e");
+        let tokens = tokenizer.tokenize().unwrap();
+        let types = tokens.iter().map(|t| t.token_type).collect::<Vec<TokenType>>();
+
+        assert_eq!(types, vec![
+            TokenType::Identifier, TokenType::And, TokenType::Identifier,
+            TokenType::Or, TokenType::Identifier, TokenType::And,
+            TokenType::Identifier, TokenType::Or, TokenType::Identifier,
+            TokenType::Eof
+        ]);
+    }
+
+    #[test]
+    fn tokenize_bitwise_symbols() {
+        let mut tokenizer = Tokenizer::new("a & b | c ^ ~d << 1 >> 2 <= >=");
+        let tokens = tokenizer.tokenize().unwrap();
+        let types = tokens.iter().map(|t| t.token_type).collect::<Vec<TokenType>>();
+
+        assert_eq!(types, vec![
+            TokenType::Identifier, TokenType::Ampersand, TokenType::Identifier,
+            TokenType::Pipe, TokenType::Identifier, TokenType::Caret,
+            TokenType::Tilde, TokenType::Identifier, TokenType::LessLess,
+            TokenType::Number, TokenType::GreaterGreater, TokenType::Number,
+            TokenType::LessEqual, TokenType::GreaterEqual, TokenType::Eof
+        ]);
+    }
+
+    #[test]
+    fn tokenize_star_star() {
+        let mut tokenizer = Tokenizer::new("2 ** 3 * 4");
+        let tokens = tokenizer.tokenize().unwrap();
+        let types = tokens.iter().map(|t| t.token_type).collect::<Vec<TokenType>>();
+
+        assert_eq!(types, vec![
+            TokenType::Number, TokenType::StarStar, TokenType::Number,
+            TokenType::Star, TokenType::Number, TokenType::Eof
+        ]);
+    }
+
+    #[test]
+    fn tokenize_tilde_slash() {
+        let mut tokenizer = Tokenizer::new("7 ~/ 2 ~x // comment");
+        let tokens = tokenizer.tokenize().unwrap();
+        let types = tokens.iter().map(|t| t.token_type).collect::<Vec<TokenType>>();
+
+        assert_eq!(types, vec![
+            TokenType::Number, TokenType::TildeSlash, TokenType::Number,
+            TokenType::Tilde, TokenType::Identifier, TokenType::Eof
+        ]);
+    }
+
+    #[test]
+    fn tokenize_tilde_before_comment() {
+        let mut tokenizer = Tokenizer::new("~/* note */ 1 ~// note");
+        let tokens = tokenizer.tokenize().unwrap();
+        let types = tokens.iter().map(|t| t.token_type).collect::<Vec<TokenType>>();
+
+        assert_eq!(types, vec![TokenType::Tilde, TokenType::Number, TokenType::Tilde, TokenType::Eof]);
+    }
+
+    #[test]
+    fn tokenize_compound_assignment() {
+        let mut tokenizer = Tokenizer::new("+= -= *= /= ** *");
+        let tokens = tokenizer.tokenize().unwrap();
+        let types = tokens.iter().map(|t| t.token_type).collect::<Vec<TokenType>>();
+
+        assert_eq!(types, vec![
+            TokenType::PlusEqual, TokenType::MinusEqual, TokenType::StarEqual,
+            TokenType::SlashEqual, TokenType::StarStar, TokenType::Star, TokenType::Eof
+        ]);
+    }
+
+    #[test]
+    fn tokenize_question_question() {
+        let mut tokenizer = Tokenizer::new("a ?? b ? c : d");
+        let tokens = tokenizer.tokenize().unwrap();
+        let types = tokens.iter().map(|t| t.token_type).collect::<Vec<TokenType>>();
+
+        assert_eq!(types, vec![
+            TokenType::Identifier, TokenType::QuestionQuestion, TokenType::Identifier, TokenType::Question,
+            TokenType::Identifier, TokenType::Colon, TokenType::Identifier, TokenType::Eof
+        ]);
+    }
+
+    #[test]
+    fn tokenize_columns() {
+        let mut tokenizer = Tokenizer::new("let x = 1;\n  echo \"a ${x}\";");
+        let tokens = tokenizer.tokenize().unwrap();
+        let positions = tokens.iter().map(|t| (t.line_number, t.column, t.offset)).collect::<Vec<(usize, usize, usize)>>();
+
+        assert_eq!(positions, vec![
+            (1, 1, 0), (1, 5, 4), (1, 7, 6), (1, 9, 8), (1, 10, 9),
+            (2, 3, 13), (2, 8, 18), (2, 16, 26), (2, 17, 27)
+        ]);
+
+        let code = match &tokens[6].literal {
+            Some(LiteralValue::Interpolation(parts)) => match &parts[1] {
+                InterpolationPart::Code(code) => code.clone(),
+                _ => panic!("Expected interpolated code"),
+            },
+            _ => panic!("Expected an interpolation"),
+        };
+        assert_eq!((code[0].line_number, code[0].column, code[0].offset), (2, 13, 23));
+    }
+
+    #[test]
+    fn tokenize_unicode_identifiers() {
+        let mut tokenizer = Tokenizer::new("let café = \"é\"; π _ü2 lét");
+        let tokens = tokenizer.tokenize().unwrap();
+        let lexemes = tokens.iter().map(|t| (t.token_type, t.lexeme.as_str(), t.offset)).collect::<Vec<(TokenType, &str, usize)>>();
+
+        // Offsets count bytes, so everything after a multi-byte character moves by its length
+        assert_eq!(lexemes, vec![
+            (TokenType::Let, "let", 0), (TokenType::Identifier, "café", 4), (TokenType::Equal, "=", 10),
+            (TokenType::StringLit, "\"é\"", 12), (TokenType::Semicolon, ";", 16), (TokenType::Identifier, "π", 18),
+            (TokenType::Identifier, "_ü2", 21), (TokenType::Identifier, "lét", 26), (TokenType::Eof, "", 30)
+        ]);
+    }
+
+    #[test]
+    fn tokenize_int_and_float() {
+        let mut tokenizer = Tokenizer::new("12 3.5");
+        let tokens = tokenizer.tokenize().unwrap();
+
+        assert!(matches!(tokens[0].literal, Some(LiteralValue::IntValue(12))));
+        assert!(matches!(tokens[1].literal, Some(LiteralValue::FloatValue(x)) if x == 3.5));
+    }
+
+    #[test]
+    fn tokenize_trailing_dot() {
+        let mut tokenizer = Tokenizer::new("3.;");
+
+        assert!(tokenizer.tokenize().is_err());
+    }
+
+    #[test]
+    fn tokenize_scientific_notation() {
+        let mut tokenizer = Tokenizer::new("1e3 2.5e-4 6.02E23 1e+2");
+        let tokens = tokenizer.tokenize().unwrap();
+
+        assert!(matches!(tokens[0].literal, Some(LiteralValue::FloatValue(x)) if x == 1000.0));
+        assert!(matches!(tokens[1].literal, Some(LiteralValue::FloatValue(x)) if x == 0.00025));
+        assert!(matches!(tokens[2].literal, Some(LiteralValue::FloatValue(x)) if x == 6.02e23));
+        assert!(matches!(tokens[3].literal, Some(LiteralValue::FloatValue(x)) if x == 100.0));
+        assert_eq!(tokens.len(), 5);
+    }
+
+    #[test]
+    fn tokenize_missing_exponent() {
+        assert!(Tokenizer::new("1e;").tokenize().is_err());
+        assert!(Tokenizer::new("1e-;").tokenize().is_err());
+    }
+
+    #[test]
+    fn tokenize_hex() {
+        let mut tokenizer = Tokenizer::new("0xFF 0X1a");
+        let tokens = tokenizer.tokenize().unwrap();
+
+        assert!(matches!(tokens[0].literal, Some(LiteralValue::IntValue(255))));
+        assert_eq!(tokens[0].lexeme, "0xFF");
+        assert!(matches!(tokens[1].literal, Some(LiteralValue::IntValue(26))));
+    }
+
+    #[test]
+    fn tokenize_invalid_hex() {
+        let mut tokenizer = Tokenizer::new("\n0xG");
+
+        assert_eq!(tokenizer.tokenize().err().unwrap(), vec![CorrodeError::lex(2, String::from("Invalid hex literal: 0xG")).with_column(1)]);
+        assert!(Tokenizer::new("0x").tokenize().is_err());
+    }
+
+    #[test]
+    fn tokenize_binary_and_octal() {
+        let mut tokenizer = Tokenizer::new("0b1010 0o17");
+        let tokens = tokenizer.tokenize().unwrap();
+
+        assert!(matches!(tokens[0].literal, Some(LiteralValue::IntValue(10))));
+        assert!(matches!(tokens[1].literal, Some(LiteralValue::IntValue(15))));
+    }
+
+    #[test]
+    fn tokenize_invalid_binary_and_octal() {
+        assert!(Tokenizer::new("0b").tokenize().is_err());
+        assert!(Tokenizer::new("0o").tokenize().is_err());
+        assert!(Tokenizer::new("0b102").tokenize().is_err());
+        assert!(Tokenizer::new("0o8").tokenize().is_err());
+    }
+
+    #[test]
+    fn tokenize_digit_separators() {
+        let mut tokenizer = Tokenizer::new("1_000_000 1_0.2_5 1e1_0 0xFF_FF 0b1010_1010");
+        let tokens = tokenizer.tokenize().unwrap();
+
+        assert!(matches!(tokens[0].literal, Some(LiteralValue::IntValue(1000000))));
+        assert_eq!(tokens[0].lexeme, "1_000_000");
+        assert!(matches!(tokens[1].literal, Some(LiteralValue::FloatValue(x)) if x == 10.25));
+        assert!(matches!(tokens[2].literal, Some(LiteralValue::FloatValue(x)) if x == 1e10));
+        assert!(matches!(tokens[3].literal, Some(LiteralValue::IntValue(0xFFFF))));
+        assert!(matches!(tokens[4].literal, Some(LiteralValue::IntValue(0b10101010))));
+    }
+
+    #[test]
+    fn tokenize_invalid_digit_separators() {
+        assert!(Tokenizer::new("5_").tokenize().is_err());
+        assert!(Tokenizer::new("1__0").tokenize().is_err());
+        assert!(Tokenizer::new("1_.5").tokenize().is_err());
+        assert!(Tokenizer::new("0x_FF").tokenize().is_err());
+        assert!(Tokenizer::new("0xFF_").tokenize().is_err());
+
+        // A leading underscore starts an identifier, not a number
+        let tokens = Tokenizer::new("_5").tokenize().unwrap();
+        assert_eq!(tokens[0].token_type, TokenType::Identifier);
+    }
+
+    #[test]
+    fn tokenize_escape_sequences() {
+        let mut tokenizer = Tokenizer::new(r#""a\nb\t\r\\\"\0""#);
+        let tokens = tokenizer.tokenize().unwrap();
+
+        assert!(matches!(&tokens[0].literal, Some(LiteralValue::StringValue(s)) if s == "a\nb\t\r\\\"\0"));
+        assert_eq!(tokens.len(), 2);
+    }
+
+    #[test]
+    fn tokenize_raw_string() {
+        let mut tokenizer = Tokenizer::new("r\"C:\\path\\n ${x} \\\"q\\\"\" r\"a\nb\" raw");
+        let tokens = tokenizer.tokenize().unwrap();
+
+        assert!(matches!(&tokens[0].literal, Some(LiteralValue::StringValue(s)) if s == "C:\\path\\n ${x} \\\"q\\\""));
+        assert!(matches!(&tokens[1].literal, Some(LiteralValue::StringValue(s)) if s == "a\nb"));
+        // A token spanning lines keeps the line and column it starts at
+        assert_eq!((tokens[1].line_number, tokens[1].column), (1, 25));
+        assert_eq!((tokens[2].lexeme.as_str(), tokens[2].line_number), ("raw", 2));
+    }
+
+    #[test]
+    fn tokenize_triple_quoted_string() {
+        let mut tokenizer = Tokenizer::new("\"\"\"one \"quoted\"\ntwo\\t\"\"\" \"\" \"\"\"\"\"\" end");
+        let tokens = tokenizer.tokenize().unwrap();
+
+        assert!(matches!(&tokens[0].literal, Some(LiteralValue::StringValue(s)) if s == "one \"quoted\"\ntwo\t"));
+        assert!(matches!(&tokens[1].literal, Some(LiteralValue::StringValue(s)) if s.is_empty()));
+        assert!(matches!(&tokens[2].literal, Some(LiteralValue::StringValue(s)) if s.is_empty()));
+        assert_eq!((tokens[3].lexeme.as_str(), tokens[3].line_number), ("end", 2));
+    }
+
+    #[test]
+    fn tokenize_unterminated_triple_quoted_string() {
+        let mut tokenizer = Tokenizer::new("1\n\"\"\"a\nb\"\"");
+
+        assert_eq!(tokenizer.tokenize().err().unwrap(), vec![CorrodeError::lex(2, String::from("Unterminated triple quoted string")).with_column(1)]);
+    }
+
+    #[test]
+    fn tokenize_unknown_escape() {
+        let mut tokenizer = Tokenizer::new(r#""\q" 1"#);
+
+        assert_eq!(tokenizer.tokenize().err().unwrap(), vec![CorrodeError::lex(1, String::from("Unknown escape sequence: \\q")).with_column(2)]);
+    }
+
+    #[test]
+    fn tokenize_block_comment() {
+        let mut tokenizer = Tokenizer::new("1 /* a\n/* nested */\n*/ 2");
+        let tokens = tokenizer.tokenize().unwrap();
+
+        assert_eq!(tokens.len(), 3);
+        assert!(matches!(tokens[1].literal, Some(LiteralValue::IntValue(2))));
+        assert_eq!(tokens[1].line_number, 3);
+    }
+
+    #[test]
+    fn tokenize_unterminated_block_comment() {
+        let mut tokenizer = Tokenizer::new("\n/* a /* b */\n");
+
+        assert_eq!(tokenizer.tokenize().err().unwrap(), vec![CorrodeError::lex(2, String::from("Unterminated block comment")).with_column(1)]);
+    }
+
+    #[test]
+    fn tokenize_line_comment() {
+        let mut tokenizer = Tokenizer::new("1 / 2 // 3 + 4\n5");
+        let tokens = tokenizer.tokenize().unwrap();
+        let types = tokens.iter().map(|t| t.token_type).collect::<Vec<TokenType>>();
+
+        assert_eq!(types, vec![
+            TokenType::Number, TokenType::Slash, TokenType::Number,
+            TokenType::Number, TokenType::Eof
+        ]);
+        assert!(matches!(tokens[3].literal, Some(LiteralValue::IntValue(5))));
+        assert_eq!(tokens[3].line_number, 2);
+    }
+
+    #[test]
+    fn tokenize_interpolation() {
+        let mut tokenizer = Tokenizer::new(r#""a ${b + {}.c} \${d} ${"}"}""#);
+        let tokens = tokenizer.tokenize().unwrap();
+
+        assert_eq!(tokens[0].token_type, TokenType::Interpolation);
+        match &tokens[0].literal {
+            Some(LiteralValue::Interpolation(parts)) => {
+                assert_eq!(parts.len(), 5);
+                assert!(matches!(&parts[0], InterpolationPart::Text(s) if s == "a "));
+                assert!(matches!(&parts[1], InterpolationPart::Code(t) if t.len() == 7));
+                assert!(matches!(&parts[2], InterpolationPart::Text(s) if s == " ${d} "));
+                assert!(matches!(&parts[3], InterpolationPart::Code(t) if t.len() == 2));
+                assert!(matches!(&parts[4], InterpolationPart::Text(s) if s.is_empty()));
+            },
+            _ => panic!("Expected interpolation literal"),
+        }
+    }
+
+    #[test]
+    fn tokenize_unterminated_interpolation() {
+        assert!(Tokenizer::new(r#""a ${b"#).tokenize().is_err());
+    }
+
+    #[test]
+    fn tokenize_deep_interpolation() {
+        let nested = |depth: usize| (0..depth).fold(String::from("1"), |code, _| format!("\"${{{}}}\"", code));
+
+        assert!(Tokenizer::new(&nested(16)).tokenize().is_ok());
+        assert_eq!(Tokenizer::new(&nested(1_000)).tokenize().err().unwrap()[0], CorrodeError::lex(1, String::from("Too much nesting")).with_column(52));
+    }
+
+    #[test]
+    fn tokenize_interns_identifiers() {
+        let tokens = Tokenizer::new("let foo = foo + bar;").tokenize().unwrap();
+
+        assert_eq!(tokens[1].symbol, tokens[3].symbol);
+        assert_ne!(tokens[1].symbol, tokens[5].symbol);
+        assert_eq!(tokens[1].symbol, Some(Symbol::intern("foo")));
+        assert_eq!(tokens[0].symbol, Some(Symbol::intern("let")));
+        assert_eq!(tokens[2].symbol, None);
+    }
+
+    #[test]
+    fn tokenize_streaming_matches_batch() {
+        let source = "let a = 1;\n/* two\nlines */ echo \"${a + 1}\";\nfn f(x) { return x; }\n";
+        let batch = Tokenizer::new(source).tokenize().unwrap();
+        let streamed = Tokenizer::new(source).collect::<Result<Vec<Token>, CorrodeError>>().unwrap();
+
+        assert_eq!(batch.len(), streamed.len());
+        for (a, b) in batch.iter().zip(streamed.iter()) {
+            assert_eq!(a.token_type, b.token_type);
+            assert_eq!(a.lexeme, b.lexeme);
+            assert_eq!(a.line_number, b.line_number);
+            assert_eq!(a.column, b.column);
+        }
+        assert_eq!(streamed.last().unwrap().token_type, TokenType::Eof);
+        assert_eq!(streamed.last().unwrap().line_number, 5);
+    }
+
+    #[test]
+    fn tokenize_streaming_is_lazy() {
+        let mut tokenizer = Tokenizer::new("a\nb @ c");
+
+        assert_eq!(tokenizer.next().unwrap().unwrap().line_number, 1);
+        assert_eq!(tokenizer.next().unwrap().unwrap().line_number, 2);
+        assert!(tokenizer.next().unwrap().is_err());
+        assert_eq!(tokenizer.next().unwrap().unwrap().lexeme, "c");
+        assert_eq!(tokenizer.next().unwrap().unwrap().token_type, TokenType::Eof);
+        assert!(tokenizer.next().is_none());
+    }
+}